@@ -1,9 +1,61 @@
 use indexmap::IndexMap;
-use serde::Deserialize;
+use serde::{Deserialize, de::IgnoredAny};
+
+/// Keys that are not part of the config, kept to warn about them instead of silently ignoring
+/// typos.
+pub type UnknownKeys = IndexMap<String, IgnoredAny>;
 
 #[derive(Deserialize, Debug)]
 pub struct Root {
+    /// Other config files whose tasks are added, relative to this file.
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
     pub tasks: IndexMap<String, Task>,
+    #[serde(default)]
+    pub env: IndexMap<String, String>,
+    /// Param values shared by every task, see `TaskerieContext::vars`.
+    #[serde(default)]
+    pub vars: IndexMap<String, String>,
+    /// Task run when none is given on the command line.
+    pub default: Option<String>,
+    /// Delimiters of the variables, `{{` and `}}` when not given. They also apply to the included
+    /// files.
+    pub interpolation: Option<Interpolation>,
+    /// Dotenv file loaded along the config, relative to it.
+    pub env_file: Option<String>,
+    /// Size of the output of a command after which it is dropped, see
+    /// `TaskerieContext::max_output_bytes`.
+    pub max_output_bytes: Option<usize>,
+    #[serde(flatten)]
+    pub unknown: UnknownKeys,
+}
+
+impl Root {
+    /// Path of every unknown key of the config, such as `tasks.build.action`.
+    #[must_use]
+    pub fn unknown_keys(&self) -> Vec<String> {
+        let mut keys = self.unknown.keys().cloned().collect::<Vec<_>>();
+        for (task_name, task) in &self.tasks {
+            let task_path = format!("tasks.{task_name}");
+            keys.extend(task.unknown.keys().map(|key| format!("{task_path}.{key}")));
+            for (param_name, param) in &task.params {
+                keys.extend(
+                    param
+                        .unknown
+                        .keys()
+                        .map(|key| format!("{task_path}.params.{param_name}.{key}")),
+                );
+            }
+        }
+        keys
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Interpolation {
+    pub open: String,
+    pub close: String,
 }
 
 #[derive(Debug)]
@@ -11,8 +63,34 @@ pub enum Action {
     TaskCall {
         name: String,
         params: IndexMap<String, String>,
+        continue_on_error: bool,
     },
-    Command(String),
+    Command(Command),
+}
+
+#[derive(Default, Debug)]
+pub struct Command {
+    pub run: String,
+    /// Shown instead of the command when it runs.
+    pub label: Option<String>,
+    pub continue_on_error: bool,
+    /// Name of the param receiving the command output.
+    pub capture: Option<String>,
+    pub retries: u32,
+    pub retry_delay_ms: u64,
+    /// Do not forward the standard output of the command.
+    pub silent: bool,
+    pub stdin: Option<String>,
+    /// Overrides the working directory of the task.
+    pub working_directory: Option<String>,
+    /// Exit codes other than 0 meaning the command succeeded.
+    pub allowed_exit_codes: Vec<u32>,
+    /// Run the command through the shell, the default, or else start its program with its
+    /// arguments directly.
+    pub shell: Option<bool>,
+    /// Split the command on its `&&` and `;` operators, to run the parts without relying on the
+    /// shell.
+    pub split_operators: bool,
 }
 
 impl<'de> Deserialize<'de> for Action {
@@ -26,32 +104,100 @@ impl<'de> Deserialize<'de> for Action {
             type Value = Action;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-                formatter.write_str("A task reference or a command")
+                formatter.write_str("A task reference, a command or a command with options")
             }
 
             fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
             where
                 E: serde::de::Error,
             {
-                Ok(Action::Command(v.to_owned()))
+                Ok(Action::Command(Command {
+                    run: v.to_owned(),
+                    ..Default::default()
+                }))
             }
 
             fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
             where
                 A: serde::de::MapAccess<'de>,
             {
-                let (task_name, params) = map
-                    .next_entry::<String, IndexMap<String, String>>()?
-                    .ok_or_else(|| serde::de::Error::custom("Unexpected empty task"))?;
+                let mut run = None;
+                let mut command = Command::default();
+                let mut has_command_options = false;
+                let mut task_call = None;
 
-                if let Ok(Some(_)) = map.next_key::<String>() {
-                    return Err(serde::de::Error::custom("Unexpected extra key"));
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "run" => run = Some(map.next_value::<String>()?),
+                        "label" => {
+                            command.label = Some(map.next_value()?);
+                            has_command_options = true;
+                        }
+                        "continue_on_error" => command.continue_on_error = map.next_value()?,
+                        "capture" => {
+                            command.capture = Some(map.next_value()?);
+                            has_command_options = true;
+                        }
+                        "retries" => {
+                            command.retries = map.next_value()?;
+                            has_command_options = true;
+                        }
+                        "retry_delay_ms" => {
+                            command.retry_delay_ms = map.next_value()?;
+                            has_command_options = true;
+                        }
+                        "silent" => {
+                            command.silent = map.next_value()?;
+                            has_command_options = true;
+                        }
+                        "stdin" => {
+                            command.stdin = Some(map.next_value()?);
+                            has_command_options = true;
+                        }
+                        "split_operators" => {
+                            command.split_operators = map.next_value()?;
+                            has_command_options = true;
+                        }
+                        "working_directory" => {
+                            command.working_directory = Some(map.next_value()?);
+                            has_command_options = true;
+                        }
+                        "allowed_exit_codes" => {
+                            command.allowed_exit_codes = map.next_value()?;
+                            has_command_options = true;
+                        }
+                        "shell" => {
+                            command.shell = Some(map.next_value()?);
+                            has_command_options = true;
+                        }
+                        _ if task_call.is_some() => {
+                            return Err(serde::de::Error::custom("Unexpected extra key"));
+                        }
+                        _ => {
+                            task_call = Some((key, map.next_value::<IndexMap<String, String>>()?));
+                        }
+                    }
                 }
 
-                Ok(Action::TaskCall {
-                    name: task_name,
-                    params,
-                })
+                match (run, task_call) {
+                    (Some(run), None) => Ok(Action::Command(Command { run, ..command })),
+                    (None, Some((name, params))) => {
+                        if has_command_options {
+                            return Err(serde::de::Error::custom(
+                                "Only the continue_on_error option can be used on a task call",
+                            ));
+                        }
+                        Ok(Action::TaskCall {
+                            name,
+                            params,
+                            continue_on_error: command.continue_on_error,
+                        })
+                    }
+                    (Some(_), Some(_)) => Err(serde::de::Error::custom(
+                        "An action cannot both run a command and call a task",
+                    )),
+                    (None, None) => Err(serde::de::Error::custom("Unexpected empty task")),
+                }
             }
         }
 
@@ -61,13 +207,75 @@ impl<'de> Deserialize<'de> for Action {
 
 #[derive(Deserialize, Debug)]
 pub struct Task {
+    pub description: Option<String>,
     pub working_directory: Option<String>,
+    #[serde(default)]
+    pub create_working_directory: bool,
+    /// Whether the working directory is checked and resolved before running the commands, true
+    /// when left out.
+    pub canonicalize_cwd: Option<bool>,
+    /// Can be left out by tasks that only run the tasks they need.
+    #[serde(default)]
     pub actions: Vec<Action>,
     #[serde(default)]
+    pub needs: Vec<Dependency>,
+    #[serde(default)]
+    pub parallel: bool,
+    pub max_parallel: Option<usize>,
+    #[serde(default)]
+    pub persistent_shell: bool,
+    pub max_output_bytes: Option<usize>,
+    #[serde(default)]
+    pub clean_env: bool,
+    #[serde(default)]
     pub params: IndexMap<String, Param>,
+    #[serde(default)]
+    pub env: IndexMap<String, String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Question asked before running the task, which only runs once the user agrees.
+    pub confirm: Option<String>,
+    /// Skip the task when it already succeeded with the same rendered commands, environment,
+    /// params and inputs. Tasks whose commands use captured output always run.
+    #[serde(default)]
+    pub cache: bool,
+    /// Files whose content is part of the cache fingerprint, relative to the config.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    #[serde(flatten)]
+    pub unknown: UnknownKeys,
+}
+
+/// A needed task, given by name or as a single key map from its name to its params.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum Dependency {
+    Task(String),
+    TaskWithParams(IndexMap<String, IndexMap<String, String>>),
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Param {
     pub default: Option<String>,
+    /// Command whose output is the value when none is given, such as a secret store client.
+    pub from_command: Option<String>,
+    /// File whose content is the value when none is given.
+    pub from_file: Option<String>,
+    #[serde(default)]
+    pub secret: bool,
+    #[serde(rename = "type")]
+    pub param_type: Option<ParamType>,
+    #[serde(default)]
+    pub choices: Vec<String>,
+    #[serde(flatten)]
+    pub unknown: UnknownKeys,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamType {
+    String,
+    Int,
+    Bool,
+    Enum,
 }
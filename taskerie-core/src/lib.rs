@@ -3,20 +3,963 @@ pub mod message;
 pub mod model;
 pub mod service;
 
-use std::{fs, path::Path};
+use std::{
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+};
 
+use anyhow::{Context, bail};
 use config::Root;
 use indexmap::IndexMap;
+use itertools::Itertools;
 use model::TaskerieContext;
+pub use subprocess::ExitStatus;
 
+/// Load a config file, its format being picked from the file extension.
 pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<TaskerieContext> {
-    let config = serde_norway::from_str::<Root>(&fs::read_to_string(path)?)?;
+    let mut config_tasks = IndexMap::new();
+    let path = path.as_ref();
+    let config = load_config(path, &mut Vec::new(), &mut config_tasks)?;
+    let base_dir = path
+        .canonicalize()?
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    build_context(config, config_tasks, base_dir)
+}
+
+/// Load a YAML config, included files being resolved relative to the current directory.
+pub fn load_from_str(content: &str) -> anyhow::Result<TaskerieContext> {
+    let mut config_tasks = IndexMap::new();
+    let config = add_config_tasks(
+        parse_yaml(content)?,
+        Path::new("<string>"),
+        Path::new(""),
+        &mut Vec::new(),
+        &mut config_tasks,
+    )?;
+    build_context(config, config_tasks, PathBuf::new())
+}
+
+/// Load a YAML config from a reader, see [`load_from_str`].
+pub fn load_from_reader<R: Read>(mut reader: R) -> anyhow::Result<TaskerieContext> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    load_from_str(&content)
+}
+
+fn build_context(
+    config: Root,
+    config_tasks: IndexMap<String, (config::Task, PathBuf)>,
+    base_dir: PathBuf,
+) -> anyhow::Result<TaskerieContext> {
+    let delimiters = match config.interpolation {
+        Some(config::Interpolation { open, close }) => {
+            if open.is_empty() || close.is_empty() {
+                bail!("Interpolation delimiters cannot be empty");
+            }
+            model::Delimiters { open, close }
+        }
+        None => model::Delimiters::default(),
+    };
 
-    let tasks = config
-        .tasks
+    let tasks = config_tasks
         .into_iter()
-        .map(|(name, task)| task.try_into().map(|t: model::task::Task| (name, t)))
+        .map(|(name, (task, _))| {
+            (task, &delimiters)
+                .try_into()
+                .map(|t: model::task::Task| (name, t))
+        })
         .collect::<anyhow::Result<IndexMap<_, _>>>()?;
 
-    Ok(TaskerieContext { tasks })
+    let env = config
+        .env
+        .into_iter()
+        .map(|(key, value)| {
+            model::InterpolatedString::parse_with_delimiters(&value, &delimiters)
+                .map(|value| (key, value))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let dotenv = match &config.env_file {
+        Some(env_file) => {
+            let path = base_dir.join(env_file);
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read env file {}", path.display()))?;
+            service::dotenv::parse(&content)
+                .with_context(|| format!("Could not parse env file {}", path.display()))?
+        }
+        None => IndexMap::new(),
+    };
+
+    let context = TaskerieContext {
+        tasks,
+        env,
+        vars: config.vars,
+        base_dir,
+        default_task: config.default,
+        delimiters,
+        dotenv,
+        max_output_bytes: config.max_output_bytes,
+    };
+    context.validate()?;
+
+    Ok(context)
+}
+
+/// Parse a config file and collect its tasks and the ones of the files it includes, along with the
+/// file defining each of them.
+///
+/// `include_stack` holds the files currently being loaded, to detect include cycles.
+fn load_config(
+    path: &Path,
+    include_stack: &mut Vec<PathBuf>,
+    tasks: &mut IndexMap<String, (config::Task, PathBuf)>,
+) -> anyhow::Result<Root> {
+    let canonical_path = path
+        .canonicalize()
+        .with_context(|| format!("Could not find config file {}", path.display()))?;
+    if let Some(position) = include_stack
+        .iter()
+        .position(|included| *included == canonical_path)
+    {
+        bail!(
+            "Cyclic include detected: {} -> {}",
+            include_stack[position..]
+                .iter()
+                .map(|included| included.display())
+                .join(" -> "),
+            canonical_path.display()
+        );
+    }
+
+    let config = parse_config(path, &fs::read_to_string(path)?)
+        .with_context(|| format!("Could not parse config file {}", path.display()))?;
+    let directory = canonical_path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+
+    add_config_tasks(config, &canonical_path, &directory, include_stack, tasks)
+}
+
+/// Collect the tasks of a parsed config, loading the files it includes from `directory`.
+fn add_config_tasks(
+    mut config: Root,
+    source: &Path,
+    directory: &Path,
+    include_stack: &mut Vec<PathBuf>,
+    tasks: &mut IndexMap<String, (config::Task, PathBuf)>,
+) -> anyhow::Result<Root> {
+    for key in config.unknown_keys() {
+        log::warn!("Ignoring unknown key '{key}' in {}", source.display());
+    }
+    for (name, task) in std::mem::take(&mut config.tasks) {
+        if let Some((_, first_source)) = tasks.get(&name) {
+            bail!(
+                "Task '{name}' is defined in both {} and {}",
+                first_source.display(),
+                source.display()
+            );
+        }
+        tasks.insert(name, (task, source.to_path_buf()));
+    }
+
+    include_stack.push(source.to_path_buf());
+    for include in &config.include {
+        load_config(&directory.join(include), include_stack, tasks)?;
+    }
+    include_stack.pop();
+
+    Ok(config)
+}
+
+/// Parse a config with the format matching the file extension.
+fn parse_config(path: &Path, content: &str) -> anyhow::Result<Root> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("yaml" | "yml") => parse_yaml(content),
+        Some("toml") => Ok(toml::from_str(content)?),
+        Some("json") => Ok(serde_json::from_str(content)?),
+        _ => bail!(
+            "Unsupported config file extension for {}, expected .yaml, .yml, .toml or .json",
+            path.display()
+        ),
+    }
+}
+
+/// Parse a YAML config, expanding the `<<` merge keys serde_norway leaves as regular keys.
+///
+/// A config with merge keys is parsed again once they are expanded, as scalars such as numbers
+/// only deserialize as strings from the YAML text.
+fn parse_yaml(content: &str) -> anyhow::Result<Root> {
+    let value: serde_norway::Value = serde_norway::from_str(content)?;
+    let mut merged = value.clone();
+    merged.apply_merge()?;
+    if merged == value {
+        Ok(serde_norway::from_str(content)?)
+    } else {
+        Ok(serde_norway::from_str(&serde_norway::to_string(&merged)?)?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn write(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_include_merges_tasks() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        let root = write(
+            dir.path(),
+            "taskerie.yaml",
+            "include: [nested/build.yaml]\ntasks:\n  root:\n    actions:\n      - echo root\n",
+        );
+        write(
+            &dir.path().join("nested"),
+            "build.yaml",
+            "include: [test.yaml]\ntasks:\n  build:\n    actions:\n      - echo build\n",
+        );
+        write(
+            &dir.path().join("nested"),
+            "test.yaml",
+            "tasks:\n  test:\n    actions:\n      - build: {}\n",
+        );
+
+        let context = load(root).unwrap();
+
+        assert_eq!(
+            context.tasks.keys().collect::<Vec<_>>(),
+            vec!["root", "build", "test"]
+        );
+    }
+
+    #[test]
+    fn test_relative_working_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let root = write(
+            dir.path(),
+            "taskerie.yaml",
+            "tasks:\n  build:\n    working_directory: sub\n    actions:\n      - echo build\n",
+        );
+
+        let context = load(root).unwrap();
+        assert_eq!(context.base_dir, dir.path().canonicalize().unwrap());
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let options = model::ExecutionOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        context
+            .run_task_by_name_with_options(
+                "build",
+                &mut model::ParamContext::default(),
+                &tx,
+                options,
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        let working_directory = dir
+            .path()
+            .join("sub")
+            .canonicalize()
+            .unwrap()
+            .display()
+            .to_string();
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            message::ExecutionMessage::TaskStarted {
+                name: "build".to_string(),
+            }
+        );
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            message::ExecutionMessage::WorkingDirectoryResolved {
+                working_directory: "sub".to_string(),
+                path: working_directory.clone(),
+            }
+        );
+        assert_eq!(
+            rx.try_recv().unwrap(),
+            message::ExecutionMessage::AboutToRunCommand {
+                command: "echo build".to_string(),
+                working_directory,
+                label: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_include_duplicate_task() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = write(
+            dir.path(),
+            "taskerie.yaml",
+            "include: [other.yaml]\ntasks:\n  build:\n    actions:\n      - echo root\n",
+        );
+        write(
+            dir.path(),
+            "other.yaml",
+            "tasks:\n  build:\n    actions:\n      - echo other\n",
+        );
+
+        let error = load(root).unwrap_err().to_string();
+
+        assert!(error.starts_with("Task 'build' is defined in both"));
+        assert!(error.contains("taskerie.yaml"));
+        assert!(error.contains("other.yaml"));
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = write(
+            dir.path(),
+            "a.yaml",
+            "include: [b.yaml]\ntasks:\n  a:\n    actions:\n      - echo a\n",
+        );
+        write(
+            dir.path(),
+            "b.yaml",
+            "include: [a.yaml]\ntasks:\n  b:\n    actions:\n      - echo b\n",
+        );
+
+        let error = load(root).unwrap_err().to_string();
+
+        assert!(error.starts_with("Cyclic include detected:"));
+        assert_eq!(error.matches("a.yaml").count(), 2);
+    }
+
+    #[test]
+    fn test_toml_and_yaml_are_equivalent() {
+        let dir = tempfile::tempdir().unwrap();
+        let yaml = write(
+            dir.path(),
+            "taskerie.yaml",
+            r#"
+env:
+  MODE: release
+tasks:
+  build:
+    description: Build the project
+    working_directory: "{{ path }}"
+    params:
+      path:
+        default: src
+      jobs:
+        type: int
+        default: "4"
+    actions:
+      - cargo build -j {{ jobs }}
+      - notify:
+          message: built {{ path }}
+  notify:
+    params:
+      message:
+    actions:
+      - echo {{ message }}
+"#,
+        );
+        let toml = write(
+            dir.path(),
+            "taskerie.toml",
+            r#"
+[env]
+MODE = "release"
+
+[tasks.build]
+description = "Build the project"
+working_directory = "{{ path }}"
+params = { path = { default = "src" }, jobs = { type = "int", default = "4" } }
+actions = [
+    "cargo build -j {{ jobs }}",
+    { notify = { message = "built {{ path }}" } },
+]
+
+[tasks.notify]
+params = { message = {} }
+actions = ["echo {{ message }}"]
+"#,
+        );
+
+        let from_yaml = load(yaml).unwrap();
+        let from_toml = load(toml).unwrap();
+
+        assert_eq!(from_yaml, from_toml);
+        assert!(matches!(
+            from_toml.tasks["build"].actions[..],
+            [model::Action::Command(_), model::Action::TaskCall(_)]
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(dir.path(), "taskerie.ini", "");
+
+        assert!(load(path).is_err());
+    }
+
+    #[test]
+    fn test_json_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(
+            dir.path(),
+            "taskerie.json",
+            r#"{
+                "tasks": {
+                    "main": {
+                        "actions": [
+                            "echo main",
+                            { "other_task": { "param": "v" } }
+                        ]
+                    },
+                    "other_task": {
+                        "params": { "param": {} },
+                        "actions": ["echo {{ param }}"]
+                    }
+                }
+            }"#,
+        );
+
+        let context = load(path).unwrap();
+
+        assert_eq!(
+            context.tasks["main"].actions,
+            vec![
+                model::Action::Command(Box::new("echo main".parse().unwrap())),
+                model::Action::TaskCall(model::action::TaskCall {
+                    name: "other_task".to_string(),
+                    params: [("param".to_string(), "v".parse().unwrap())]
+                        .into_iter()
+                        .collect(),
+                    continue_on_error: false,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_from_str() {
+        let context = load_from_str(
+            "tasks:\n  build:\n    description: Build\n    actions:\n      - cargo build\n",
+        )
+        .unwrap();
+
+        assert_eq!(context.tasks["build"].description.as_deref(), Some("Build"));
+        assert_eq!(
+            context.tasks["build"].actions,
+            vec![model::Action::Command(Box::new(
+                "cargo build".parse().unwrap()
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_load_from_reader() {
+        let content = "tasks:\n  build:\n    actions:\n      - cargo build\n";
+
+        assert_eq!(
+            load_from_reader(content.as_bytes()).unwrap(),
+            load_from_str(content).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_load_from_str_validates() {
+        assert!(load_from_str("tasks:\n  a:\n    actions:\n      - a: {}\n").is_err());
+    }
+
+    #[test]
+    fn test_command_with_options() {
+        let context = load_from_str(
+            "tasks:\n  a:\n    actions:\n      - capture: out\n        run: echo a\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            context.tasks["a"].actions,
+            vec![model::Action::Command(Box::new(model::action::Command {
+                command: "echo a".parse().unwrap(),
+                capture: Some("out".to_string()),
+                ..Default::default()
+            }))]
+        );
+    }
+
+    #[test]
+    fn test_command_with_label() {
+        let context = load_from_str(
+            r#"
+tasks:
+  a:
+    actions:
+      - echo plain
+      - { run: cargo build, label: Compile }
+      - b: {}
+  b:
+    actions:
+      - echo b
+"#,
+        )
+        .unwrap();
+
+        let actions = &context.tasks["a"].actions;
+        assert_eq!(
+            actions[..2],
+            [
+                model::Action::Command(Box::new("echo plain".parse().unwrap())),
+                model::Action::Command(Box::new(model::action::Command {
+                    command: "cargo build".parse().unwrap(),
+                    label: Some("Compile".to_string()),
+                    ..Default::default()
+                })),
+            ]
+        );
+        assert!(matches!(&actions[2], model::Action::TaskCall(task_call) if task_call.name == "b"));
+
+        assert!(
+            load_from_str(
+                "tasks:\n  a:\n    actions:\n      - b: {}\n        label: Call b\n  b:\n    actions:\n      - echo b\n",
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_capture_on_task_call_is_rejected() {
+        assert!(
+            load_from_str(
+                "tasks:\n  a:\n    actions:\n      - b: {}\n        capture: out\n  b:\n    actions:\n      - echo b\n",
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_interpolation_delimiters() {
+        let context = load_from_str(
+            r#"
+interpolation:
+  open: "[["
+  close: "]]"
+tasks:
+  template:
+    params:
+      name:
+        default: world
+    actions:
+      - echo '{{ .Greeting }} [[ name ]]'
+"#,
+        )
+        .unwrap();
+
+        let model::Action::Command(command) = &context.tasks["template"].actions[0] else {
+            panic!("Expected a command");
+        };
+        let mut param_context = model::ParamContext::default();
+        param_context.set("name", "you");
+        assert_eq!(
+            command.command.render(&param_context).unwrap(),
+            "echo '{{ .Greeting }} you'"
+        );
+
+        assert!(
+            load_from_str("interpolation:\n  open: ''\n  close: ']]'\ntasks:\n  a:\n    actions:\n      - echo a\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_unknown_keys() {
+        let content = r#"
+colour: true
+tasks:
+  build:
+    descripton: Build the project
+    actions:
+      - echo build
+    params:
+      target:
+        defualt: debug
+  test:
+    actions:
+      - echo test
+"#;
+        let config: Root = serde_norway::from_str(content).unwrap();
+        assert_eq!(
+            config.unknown_keys(),
+            vec![
+                "colour",
+                "tasks.build.descripton",
+                "tasks.build.params.target.defualt"
+            ]
+        );
+        // Unknown keys are only warned about
+        assert!(load_from_str(content).is_ok());
+    }
+
+    #[test]
+    fn test_env_file() {
+        let dir = tempfile::tempdir().unwrap();
+        write(
+            dir.path(),
+            ".env",
+            "# Loaded along the config\nTASKERIE_TEST_DOTENV=\"from dotenv\"\nPATH=/nonexistent\n",
+        );
+        let root = write(
+            dir.path(),
+            "taskerie.yaml",
+            r#"
+env_file: .env
+tasks:
+  show:
+    actions:
+      - run: printenv TASKERIE_TEST_DOTENV
+        capture: process_env
+      - run: echo '{{ env.TASKERIE_TEST_DOTENV }}' '{{ env.PATH }}'
+        capture: interpolated
+"#,
+        );
+
+        let context = load(root).unwrap();
+        let (tx, _rx) = std::sync::mpsc::channel();
+        let mut param_context = model::ParamContext::default();
+        let outcome = context
+            .run_task_by_name("show", &mut param_context, &tx)
+            .unwrap();
+
+        // The process environment takes precedence, PATH is not replaced
+        assert!(outcome.success());
+        assert_eq!(
+            param_context
+                .get("process_env")
+                .and_then(model::ParamValue::as_scalar),
+            Some("from dotenv")
+        );
+        assert_eq!(
+            param_context.get("interpolated").map(ToString::to_string),
+            Some(format!("from dotenv {}", std::env::var("PATH").unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_yaml_anchors() {
+        let context = load_from_str(
+            r#"
+tasks:
+  build:
+    params: &target_params
+      target:
+        default: debug
+    actions:
+      - &build_command cargo build --profile {{ target }}
+  release:
+    params: *target_params
+    actions:
+      - *build_command
+      - build: &release_args
+          target: release
+      - build: *release_args
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            context.tasks["release"].params,
+            context.tasks["build"].params
+        );
+        let release_call = || {
+            model::Action::TaskCall(model::action::TaskCall {
+                name: "build".to_string(),
+                params: [("target".to_string(), "release".parse().unwrap())].into(),
+                continue_on_error: false,
+            })
+        };
+        let actions = &context.tasks["release"].actions;
+        assert_eq!(actions[0], context.tasks["build"].actions[0]);
+        assert_eq!(actions[1..], [release_call(), release_call()]);
+    }
+
+    #[test]
+    fn test_yaml_merge_keys() {
+        let content = r#"
+tasks:
+  build: &build
+    description: Build
+    params:
+      version:
+        default: 1
+    actions:
+      - &options
+        retries: 2
+        run: cargo build
+  rebuild:
+    <<: *build
+    description: Build again
+    actions:
+      - <<: *options
+        run: cargo build --release
+      - build:
+          <<: {version: 2}
+"#;
+        let context = load_from_str(content).unwrap();
+
+        let rebuild = &context.tasks["rebuild"];
+        assert_eq!(rebuild.description.as_deref(), Some("Build again"));
+        assert_eq!(rebuild.params, context.tasks["build"].params);
+        assert_eq!(
+            rebuild.actions,
+            vec![
+                model::Action::Command(Box::new(model::action::Command {
+                    command: "cargo build --release".parse().unwrap(),
+                    retries: 2,
+                    ..Default::default()
+                })),
+                model::Action::TaskCall(model::action::TaskCall {
+                    name: "build".to_string(),
+                    params: [("version".to_string(), "2".parse().unwrap())].into(),
+                    continue_on_error: false,
+                }),
+            ]
+        );
+        assert!(parse_yaml(content).unwrap().unknown_keys().is_empty());
+    }
+
+    #[test]
+    fn test_action_working_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["sub", "other"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        let content = "tasks:\n  build:\n    working_directory: sub\n    actions:\n      - pwd\n      - run: pwd\n        working_directory: other\n";
+        let context = load(write(dir.path(), "taskerie.yaml", content)).unwrap();
+
+        let (tx, rx) = std::sync::mpsc::channel();
+        let outcome = context
+            .run_task_by_name("build", &mut model::ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(outcome.success());
+        let directory = |name| dir.path().join(name).canonicalize().unwrap();
+        assert_eq!(
+            rx.iter()
+                .filter_map(|message| match message {
+                    message::ExecutionMessage::CommandOutput { output } => Some(output.into()),
+                    _ => None,
+                })
+                .collect::<Vec<PathBuf>>(),
+            vec![directory("sub"), directory("other")]
+        );
+
+        let error = load(write(
+            dir.path(),
+            "shell.yaml",
+            &content.replace("    actions:", "    persistent_shell: true\n    actions:"),
+        ))
+        .unwrap_err();
+        assert!(format!("{error:#}").contains(
+            "Commands sharing a persistent shell cannot have their own working directory"
+        ));
+    }
+
+    #[test]
+    fn test_working_directory_without_canonicalization() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = "tasks:\n  build:\n    working_directory: later/../later\n    canonicalize_cwd: false\n    actions:\n      - pwd\n";
+        let context = load(write(dir.path(), "taskerie.yaml", content)).unwrap();
+        let run = || {
+            let (tx, rx) = std::sync::mpsc::channel();
+            let result =
+                context.run_task_by_name("build", &mut model::ParamContext::default(), &tx);
+            drop(tx);
+            (result, rx.iter().collect::<Vec<_>>())
+        };
+
+        // The missing directory is not reported beforehand, the command fails to start
+        let (result, messages) = run();
+        assert!(matches!(result, Err(model::RunError::Spawn { .. })));
+        assert!(!messages.iter().any(|message| matches!(
+            message,
+            message::ExecutionMessage::WorkingDirectoryNotFound { .. }
+        )));
+
+        // Created once the config is loaded, the directory is used as written
+        fs::create_dir(dir.path().join("later")).unwrap();
+        let (result, messages) = run();
+        assert!(result.unwrap().success());
+        let base_dir = dir.path().canonicalize().unwrap();
+        assert!(
+            messages.contains(&message::ExecutionMessage::WorkingDirectoryResolved {
+                working_directory: "later/../later".to_string(),
+                path: base_dir.join("later/../later").display().to_string(),
+            })
+        );
+        assert!(
+            messages.contains(&message::ExecutionMessage::CommandOutput {
+                output: base_dir.join("later").display().to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_params_read_from_file_and_command() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("token.txt"), "  s3cret\n").unwrap();
+        let content = r#"
+tasks:
+  deploy:
+    params:
+      user:
+        default: admin
+      token:
+        from_file: token.txt
+      key:
+        from_command: echo {{ user }}-key
+    actions:
+      - echo {{ token }} {{ key }}
+"#;
+        let context = load(write(dir.path(), "taskerie.yaml", content)).unwrap();
+        assert!(context.tasks["deploy"].is_standalone());
+
+        // The values are masked in the output, so they are checked in the filled params
+        let run = |mut param_context: model::ParamContext| {
+            let (tx, _rx) = std::sync::mpsc::channel();
+            context
+                .run_task_by_name("deploy", &mut param_context, &tx)
+                .map(|_| ["token", "key"].map(|name| param_context.get(name).unwrap().to_string()))
+        };
+        assert_eq!(
+            run(model::ParamContext::default()).unwrap(),
+            ["s3cret", "admin-key"]
+        );
+
+        // Given values are used instead of the sources
+        let mut param_context = model::ParamContext::default();
+        param_context.set("token", "given");
+        param_context.set("user", "root");
+        assert_eq!(run(param_context).unwrap(), ["given", "root-key"]);
+
+        fs::remove_file(dir.path().join("token.txt")).unwrap();
+        let error = run(model::ParamContext::default()).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Could not read the value of parameter 'token': ")
+        );
+    }
+
+    #[test]
+    fn test_cached_task() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("input.txt"), "v1").unwrap();
+        let content = r#"
+tasks:
+  build:
+    cache: true
+    inputs: [input.txt]
+    params:
+      mode:
+        default: debug
+    actions:
+      - echo building {{ mode }}
+"#;
+        let context = load(write(dir.path(), "taskerie.yaml", content)).unwrap();
+        let run = |mode: &str| {
+            let mut param_context = model::ParamContext::default();
+            param_context.set("mode", mode);
+            let (tx, rx) = std::sync::mpsc::channel();
+            let outcome = context
+                .run_task_by_name("build", &mut param_context, &tx)
+                .unwrap();
+            drop(tx);
+            assert!(outcome.success());
+            rx.iter().any(|message| {
+                matches!(message, message::ExecutionMessage::CacheHit { task_name } if task_name == "build")
+            })
+        };
+
+        assert!(!run("debug"));
+        assert!(run("debug"));
+        assert!(dir.path().join(service::cache::CACHE_DIRECTORY).is_dir());
+
+        // Changing a param or an input invalidates the entry
+        assert!(!run("release"));
+        assert!(run("release"));
+        fs::write(dir.path().join("input.txt"), "v2").unwrap();
+        assert!(!run("release"));
+    }
+
+    #[test]
+    fn test_cached_task_depends_on_vars_and_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = |version: &str, dotenv_value: &str| {
+            write(
+                dir.path(),
+                ".env",
+                &format!("TASKERIE_TEST_CACHE_ENV={dotenv_value}\n"),
+            );
+            let content = format!(
+                r#"
+env_file: .env
+vars:
+  version: "{version}"
+tasks:
+  prepare:
+    actions:
+      - echo preparing {{{{ env.TASKERIE_TEST_CACHE_ENV }}}}
+  build:
+    cache: true
+    needs: [prepare]
+    actions:
+      - echo building {{{{ version }}}}
+  captured:
+    cache: true
+    actions:
+      - run: echo captured
+        capture: output
+      - echo {{{{ output }}}}
+"#
+            );
+            load(write(dir.path(), "taskerie.yaml", &content)).unwrap()
+        };
+        let run = |context: &TaskerieContext, name: &str| {
+            let mut param_context = model::ParamContext::default();
+            let (tx, rx) = std::sync::mpsc::channel();
+            let outcome = context
+                .run_task_by_name(name, &mut param_context, &tx)
+                .unwrap();
+            drop(tx);
+            assert!(outcome.success());
+            rx.iter()
+                .any(|message| matches!(message, message::ExecutionMessage::CacheHit { .. }))
+        };
+
+        let context = config("1.0", "a");
+        assert!(!run(&context, "build"));
+        assert!(run(&context, "build"));
+
+        // Changing a var or an environment variable used by a needed task invalidates the entry
+        let context = config("2.0", "a");
+        assert!(!run(&context, "build"));
+        assert!(run(&context, "build"));
+        let context = config("2.0", "b");
+        assert!(!run(&context, "build"));
+        assert!(run(&context, "build"));
+
+        // Commands using captured output cannot be fingerprinted, the task always runs
+        assert!(!run(&context, "captured"));
+        assert!(!run(&context, "captured"));
+    }
 }
@@ -1,17 +1,183 @@
+use serde::Serialize;
+
+/// Event sent while running a task. Serialized with its snake case variant name as `type` field.
+#[derive(Serialize, PartialEq, Eq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum ExecutionMessage {
+    /// A task, the one that was run or one it calls or needs, is about to check its params and
+    /// run.
+    TaskStarted {
+        name: String,
+    },
+    /// A task is done running, `success` telling whether its outcome is a success.
+    TaskFinished {
+        name: String,
+        success: bool,
+    },
     MissingRequiredTaskParameter {
         parameter_name: String,
     },
+    ParameterValueRequired {
+        parameter_name: String,
+        /// Values the parameter is restricted to, empty when any value is accepted.
+        choices: Vec<String>,
+        /// Whether the value is masked, so it should not be shown as it is typed.
+        secret: bool,
+    },
+    InvalidParameterValue {
+        parameter_name: String,
+        reason: String,
+    },
+    /// A parameter of the task about to run got its value, given or from its default.
+    ParameterResolved {
+        parameter_name: String,
+        value: String,
+    },
+    TaskVetoed {
+        task_name: String,
+        reason: String,
+    },
+    /// A task asks for confirmation before running, awaited on
+    /// `ExecutionOptions::confirmations`.
+    ConfirmationRequired {
+        task_name: String,
+        prompt: String,
+    },
+    /// A task needed by the running one is about to run.
+    RunningDependency {
+        name: String,
+    },
+    /// A task needed by the running one is skipped as it already ran with the same params.
+    DependencyAlreadySatisfied {
+        name: String,
+    },
+    /// A cached task is skipped as a run with the same commands, params and inputs already
+    /// succeeded.
+    CacheHit {
+        task_name: String,
+    },
+    /// The working directory of the next command was found at `path`.
+    WorkingDirectoryResolved {
+        working_directory: String,
+        path: String,
+    },
     WorkingDirectoryNotFound {
         path: String,
     },
     AboutToRunCommand {
         command: String,
         working_directory: String,
+        /// Label of the command in the config, shown instead of the command.
+        label: Option<String>,
     },
     CommandOutput {
         output: String,
     },
-    CommandFailed,
+    /// A line written by the command to its error output.
+    CommandError {
+        output: String,
+    },
+    /// The process of the command exited after running for `duration_ms`, sent for every attempt.
+    CommandFinished {
+        duration_ms: u64,
+        /// `None` when the command did not exit normally, e.g. when killed by a signal.
+        exit_code: Option<u32>,
+    },
+    CommandFailed {
+        /// `None` when the command did not exit normally, e.g. when killed by a signal.
+        exit_code: Option<u32>,
+    },
+    /// The output of the command grew past `limit` bytes, its remaining lines are dropped.
+    OutputTruncated {
+        limit: usize,
+    },
+    /// The command failed and is about to run again, `attempt` being the 1-based retry number.
+    RetryingCommand {
+        attempt: u32,
+        max: u32,
+    },
     CommandSucceeded,
+    /// The run stopped as requested through its `Interrupter`.
+    Interrupted,
+    /// The previous action failed but the task keeps running as the action allows it.
+    FailureIgnored,
+    /// Message sent by an action run in parallel with the other actions of its task, `action_id`
+    /// being the position of the action in the task.
+    ParallelAction {
+        action_id: usize,
+        message: Box<ExecutionMessage>,
+    },
+    /// Message sent while running a task called by the running one, or needed by it.
+    SubTask {
+        task_name: String,
+        message: Box<ExecutionMessage>,
+    },
+}
+
+/// Where a message unwrapped by [`ExecutionMessage::into_origin_message`] comes from.
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct MessageOrigin {
+    /// Tasks called from the one that was run down to the one that sent the message, outermost
+    /// first.
+    pub task_path: Vec<String>,
+    /// Ids of the parallel actions the message comes from, outermost first.
+    pub action_path: Vec<usize>,
+}
+
+impl MessageOrigin {
+    /// Number of task calls between the task that was run and the one that sent the message.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.task_path.len()
+    }
+}
+
+impl ExecutionMessage {
+    /// Unwrap a message sent by parallel actions or sub-tasks, along with the ids of these
+    /// actions, outermost first. The ids are empty for other messages.
+    #[must_use]
+    pub fn into_action_message(self) -> (Vec<usize>, ExecutionMessage) {
+        let (origin, message) = self.into_origin_message();
+        (origin.action_path, message)
+    }
+
+    /// Unwrap a message sent by parallel actions or sub-tasks, along with where it comes from.
+    #[must_use]
+    pub fn into_origin_message(self) -> (MessageOrigin, ExecutionMessage) {
+        let mut origin = MessageOrigin::default();
+        let mut message = self;
+        loop {
+            message = match message {
+                ExecutionMessage::ParallelAction { action_id, message } => {
+                    origin.action_path.push(action_id);
+                    *message
+                }
+                ExecutionMessage::SubTask { task_name, message } => {
+                    origin.task_path.push(task_name);
+                    *message
+                }
+                message => return (origin, message),
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_serialize() {
+        assert_eq!(
+            serde_json::to_string(&ExecutionMessage::CommandOutput {
+                output: "hello".to_string()
+            })
+            .unwrap(),
+            r#"{"type":"command_output","output":"hello"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&ExecutionMessage::CommandSucceeded).unwrap(),
+            r#"{"type":"command_succeeded"}"#
+        );
+    }
 }
@@ -1,15 +1,87 @@
+use std::time::Duration;
+
 use indexmap::IndexMap;
 
 use crate::model::InterpolatedString;
 
-#[derive(PartialEq, Eq, Debug)]
+#[derive(Default, PartialEq, Eq, Debug)]
 pub struct TaskCall {
     pub name: String,
     pub params: IndexMap<String, InterpolatedString>,
+    /// Keep running the next actions even if the task fails.
+    pub continue_on_error: bool,
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
+pub struct Command {
+    /// Rendered as a whole and run by a single shell invocation, so it can be a multi-line
+    /// script.
+    pub command: InterpolatedString,
+    /// Text describing the command, shown instead of it when it runs.
+    pub label: Option<String>,
+    /// Keep running the next actions even if the command fails.
+    pub continue_on_error: bool,
+    /// Name of the param receiving the trimmed output of the command.
+    pub capture: Option<String>,
+    /// Number of times the command is run again after failing.
+    pub retries: u32,
+    /// Pause between a failure and the next attempt.
+    pub retry_delay: Duration,
+    /// Output lines are not sent, unlike errors, success and failure. They are still captured.
+    pub silent: bool,
+    /// Text written to the standard input of the command.
+    pub stdin: Option<InterpolatedString>,
+    /// Directory the command runs in instead of the working directory of the task, relative to
+    /// the base directory of the config.
+    pub working_directory: Option<InterpolatedString>,
+    /// Exit codes meaning success besides 0, such as 1 for `grep` finding no match. The command
+    /// then counts as having exited with 0.
+    pub allowed_exit_codes: Vec<u32>,
+    /// Program and arguments started without a shell instead of running `command` when not
+    /// empty, the value of each being passed as a single argument whatever it contains.
+    pub arguments: Vec<InterpolatedString>,
+    /// Parts of `command` split on its `&&` and `;` operators, run one after the other by
+    /// taskerie instead of `command` when not empty.
+    pub steps: Vec<(Sequencing, InterpolatedString)>,
+}
+
+impl Command {
+    /// Commands to run in order, with when they run.
+    pub fn commands(&self) -> impl Iterator<Item = (Sequencing, &InterpolatedString)> {
+        let whole_command = self
+            .steps
+            .is_empty()
+            .then_some((Sequencing::Always, &self.command));
+        whole_command.into_iter().chain(
+            self.steps
+                .iter()
+                .map(|(sequencing, command)| (*sequencing, command)),
+        )
+    }
+}
+
+/// When a part of a split command runs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sequencing {
+    /// First part, or after `;`.
+    Always,
+    /// After `&&`, when the part run before succeeded.
+    OnSuccess,
 }
 
 #[derive(PartialEq, Eq, Debug)]
 pub enum Action {
     TaskCall(TaskCall),
-    Command(InterpolatedString),
+    /// Boxed as its options make it much larger than a task call.
+    Command(Box<Command>),
+}
+
+impl Action {
+    #[must_use]
+    pub fn continue_on_error(&self) -> bool {
+        match self {
+            Action::TaskCall(task_call) => task_call.continue_on_error,
+            Action::Command(command) => command.continue_on_error,
+        }
+    }
 }
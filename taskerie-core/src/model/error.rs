@@ -0,0 +1,152 @@
+use std::{fmt, io};
+
+use subprocess::PopenError;
+
+use crate::service::interpolated_string::RenderError;
+
+/// Failure stopping a task run, as opposed to a task that ran and failed, see
+/// [`TaskOutcome`](super::TaskOutcome).
+#[derive(Debug)]
+pub enum RunError {
+    /// No task has this name, `suggestion` being the defined one it is most likely a typo of.
+    TaskNotFound {
+        name: String,
+        suggestion: Option<String>,
+    },
+    /// A task calls or needs itself, through the tasks listed from the outermost one to the task
+    /// called again.
+    CyclicCall(Vec<String>),
+    /// A command, an environment variable, a working directory or an input refers to a variable
+    /// without value.
+    Render(RenderError),
+    ParamDefault {
+        parameter: String,
+        source: RenderError,
+    },
+    /// The value of a parameter could not be read from its command or file.
+    ParamSource {
+        parameter: String,
+        reason: String,
+    },
+    TaskCallParam {
+        task: String,
+        parameter: String,
+        source: RenderError,
+    },
+    CreateWorkingDirectory {
+        path: String,
+        source: io::Error,
+    },
+    /// The shell running a command could not start.
+    Spawn {
+        command: String,
+        source: PopenError,
+    },
+    /// A running command could not be waited for or stopped.
+    Process(PopenError),
+    ReadOutput {
+        command: String,
+        source: io::Error,
+    },
+    /// A command could not be sent to the persistent shell of its task.
+    ShellInput {
+        command: String,
+        source: io::Error,
+    },
+    /// The value of the `TASKERIE_SHELL` environment variable could not be split into a program
+    /// and its arguments.
+    InvalidShell(String),
+    /// A needed task that another action started running stopped with an error.
+    DependencyNotRun(String),
+    ParallelActionPanicked,
+    /// The receiver of the execution messages was dropped during the run.
+    MessageChannelClosed,
+}
+
+impl fmt::Display for RunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RunError::TaskNotFound {
+                name,
+                suggestion: Some(suggestion),
+            } => write!(f, "Task '{name}' not found. Did you mean '{suggestion}'?"),
+            RunError::TaskNotFound {
+                name,
+                suggestion: None,
+            } => write!(f, "Task '{name}' not found"),
+            RunError::CyclicCall(call_stack) => {
+                write!(f, "Cyclic task call detected: {}", call_stack.join(" -> "))
+            }
+            RunError::Render(source) => source.fmt(f),
+            RunError::ParamDefault { parameter, .. } => write!(
+                f,
+                "Could not render the default value of parameter '{parameter}'"
+            ),
+            RunError::ParamSource { parameter, reason } => write!(
+                f,
+                "Could not read the value of parameter '{parameter}': {reason}"
+            ),
+            RunError::TaskCallParam {
+                task, parameter, ..
+            } => write!(
+                f,
+                "While preparing call to task '{task}', parameter '{parameter}'"
+            ),
+            RunError::CreateWorkingDirectory { path, .. } => {
+                write!(f, "Could not create working directory {path}")
+            }
+            RunError::Spawn { command, .. } => write!(f, "Could not start command {command}"),
+            RunError::Process(_) => f.write_str("Could not wait for the command to exit"),
+            RunError::ReadOutput { command, .. } => {
+                write!(f, "Could not read the output of command {command}")
+            }
+            RunError::ShellInput { command, .. } => {
+                write!(f, "Could not send command to the shell: {command}")
+            }
+            RunError::InvalidShell(reason) => {
+                write!(f, "Invalid shell in TASKERIE_SHELL: {reason}")
+            }
+            RunError::DependencyNotRun(name) => write!(f, "Dependency {name} could not run"),
+            RunError::ParallelActionPanicked => {
+                f.write_str("An action running in parallel panicked")
+            }
+            RunError::MessageChannelClosed => {
+                f.write_str("The receiver of the execution messages is gone")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RunError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RunError::Render(source) => source.source(),
+            RunError::ParamDefault { source, .. } | RunError::TaskCallParam { source, .. } => {
+                Some(source)
+            }
+            RunError::CreateWorkingDirectory { source, .. }
+            | RunError::ReadOutput { source, .. }
+            | RunError::ShellInput { source, .. } => Some(source),
+            RunError::Spawn { source, .. } | RunError::Process(source) => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<RenderError> for RunError {
+    fn from(error: RenderError) -> Self {
+        RunError::Render(error)
+    }
+}
+
+impl<T> From<std::sync::mpsc::SendError<T>> for RunError {
+    fn from(_: std::sync::mpsc::SendError<T>) -> Self {
+        RunError::MessageChannelClosed
+    }
+}
+
+impl From<PopenError> for RunError {
+    fn from(error: PopenError) -> Self {
+        RunError::Process(error)
+    }
+}
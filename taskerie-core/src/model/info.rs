@@ -0,0 +1,21 @@
+use super::task::ParamType;
+
+/// Description of a task, for listings and tooling.
+#[derive(PartialEq, Eq, Debug)]
+pub struct TaskInfo {
+    pub name: String,
+    pub description: Option<String>,
+    /// Whether the task can run without additional parameters.
+    pub standalone: bool,
+    pub params: Vec<ParamInfo>,
+    pub tags: Vec<String>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct ParamInfo {
+    pub name: String,
+    pub default: Option<String>,
+    /// Whether a value must be given, the param having no default.
+    pub required: bool,
+    pub param_type: ParamType,
+}
@@ -1,33 +1,199 @@
+use std::{
+    fmt,
+    path::PathBuf,
+    sync::{Arc, Mutex, atomic::AtomicUsize, mpsc},
+};
+
 use indexmap::IndexMap;
 
 pub use action::Action;
+pub use error::RunError;
+pub use info::{ParamInfo, TaskInfo};
+pub use plan::{ExecutionPlan, PlannedAction};
+pub use report::{CommandReport, TaskOutcome, TaskRunReport};
 pub use task::Task;
 
 use crate::model;
 
 pub mod action;
+pub mod error;
+pub mod info;
+pub mod plan;
+pub mod report;
 pub mod task;
 
-#[derive(Debug)]
+#[derive(Default, PartialEq, Eq, Debug)]
 pub struct TaskerieContext {
     pub tasks: IndexMap<String, model::Task>,
+    /// Environment variables set for every command, overridden by the task ones.
+    pub env: IndexMap<String, InterpolatedString>,
+    /// Param values every task gets, unless given by the caller or by a default of the task.
+    pub vars: IndexMap<String, String>,
+    /// Directory relative working directories are resolved against, the one of the loaded config
+    /// file. Empty for the current directory.
+    pub base_dir: PathBuf,
+    /// Task run when none is given on the command line.
+    pub default_task: Option<String>,
+    /// Delimiters of the variables in the config strings.
+    pub delimiters: Delimiters,
+    /// Variables of the dotenv file, set for the commands and resolving `{{ env.NAME }}` unless
+    /// the process environment defines them.
+    pub dotenv: IndexMap<String, String>,
+    /// Size of the output of a command after which it is dropped, unless its task sets its own.
+    pub max_output_bytes: Option<usize>,
+}
+
+/// Markers around the variables of an interpolated string.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Delimiters {
+    pub open: String,
+    pub close: String,
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self {
+            open: "{{".to_string(),
+            close: "}}".to_string(),
+        }
+    }
+}
+
+/// Settings controlling how a task run behaves.
+#[derive(Default, Debug)]
+pub struct ExecutionOptions {
+    /// Render and report every command without actually running it.
+    pub dry_run: bool,
+    /// When set, a required parameter without value is requested through
+    /// `ExecutionMessage::ParameterValueRequired` and its value awaited on this receiver
+    /// instead of aborting the task. Receiving `None` aborts the task.
+    pub parameter_values: Option<mpsc::Receiver<Option<String>>>,
+    /// When set, a task with a `confirm` prompt sends `ExecutionMessage::ConfirmationRequired`
+    /// and only runs if `true` is received on this receiver. Without it, such tasks do not run.
+    pub confirmations: Option<mpsc::Receiver<bool>>,
+    /// Run the tasks with a `confirm` prompt without asking for confirmation.
+    pub assume_yes: bool,
+    /// Handle a caller keeps to stop the run from another thread.
+    pub interrupter: Interrupter,
+    /// Shell running the commands, given as a program followed by its arguments like
+    /// [`SHELL_VARIABLE`](crate::service::SHELL_VARIABLE), which it takes precedence over.
+    pub shell: Option<String>,
 }
 
-#[derive(Default)]
+/// Handle to interrupt a task run from another thread, such as a Ctrl-C handler.
+#[derive(Default, Clone, Debug)]
+pub struct Interrupter {
+    pub(crate) state: Arc<InterrupterState>,
+}
+
+#[derive(Default, Debug)]
+pub(crate) struct InterrupterState {
+    /// Number of interruptions requested since the last reset.
+    pub(crate) interruptions: AtomicUsize,
+    /// Commands currently running.
+    pub(crate) processes: Mutex<Vec<Arc<Mutex<subprocess::Popen>>>>,
+}
+
+#[derive(Default, Clone, Debug)]
 pub struct ParamContext {
-    pub params: IndexMap<String, String>,
+    pub params: IndexMap<String, ParamValue>,
+}
+
+/// Value of a param, a list being interpolated item by item with `{{ name.0 }}` or as a whole
+/// with `{{ name | join: "," }}`.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum ParamValue {
+    Scalar(String),
+    List(Vec<String>),
+}
+
+impl ParamValue {
+    #[must_use]
+    pub fn as_scalar(&self) -> Option<&str> {
+        match self {
+            ParamValue::Scalar(value) => Some(value),
+            ParamValue::List(_) => None,
+        }
+    }
+
+    /// Items of the value, a scalar being a list of one item.
+    #[must_use]
+    pub fn items(&self) -> &[String] {
+        match self {
+            ParamValue::Scalar(value) => std::slice::from_ref(value),
+            ParamValue::List(items) => items,
+        }
+    }
+}
+
+/// Lists are written with their items separated by spaces, as when interpolated without index or
+/// filter.
+impl fmt::Display for ParamValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamValue::Scalar(value) => f.write_str(value),
+            ParamValue::List(items) => f.write_str(&items.join(" ")),
+        }
+    }
+}
+
+impl From<&str> for ParamValue {
+    fn from(value: &str) -> Self {
+        ParamValue::Scalar(value.to_string())
+    }
+}
+
+impl From<String> for ParamValue {
+    fn from(value: String) -> Self {
+        ParamValue::Scalar(value)
+    }
+}
+
+impl From<Vec<String>> for ParamValue {
+    fn from(items: Vec<String>) -> Self {
+        ParamValue::List(items)
+    }
+}
+
+impl PartialEq<str> for ParamValue {
+    fn eq(&self, other: &str) -> bool {
+        matches!(self, ParamValue::Scalar(value) if value == other)
+    }
+}
+
+impl PartialEq<&str> for ParamValue {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
 }
 
 #[derive(PartialEq, Eq, Debug)]
 pub struct InterpolatedVariable {
     pub name: String,
+    /// Byte offset in `InterpolatedString::value` where the variable value is inserted.
     pub start: usize,
+    /// Value used when the param is not defined.
+    pub default: Option<String>,
+    /// Item or items of a list value that are rendered, all of them separated by spaces when
+    /// `None`.
+    pub selector: Option<ListSelector>,
 }
 
-#[derive(PartialEq, Eq, Debug)]
+/// Part of a list param an interpolation renders, a scalar being a list of one item.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ListSelector {
+    /// `{{ name.0 }}`, the item at this index.
+    Index(usize),
+    /// `{{ name | join: "," }}`, every item separated by this text.
+    Join(String),
+}
+
+#[derive(Default, PartialEq, Eq, Debug)]
 pub struct InterpolatedString {
     pub value: String,
     pub parts: Vec<InterpolatedVariable>,
+    /// String as written in the config, shown by the errors.
+    pub source: String,
 }
 
 impl ParamContext {
@@ -37,12 +203,70 @@ impl ParamContext {
     }
 
     pub fn set(&mut self, param_name: &str, value: &str) {
-        self.params
-            .insert(param_name.to_string(), value.to_string());
+        self.set_value(param_name, value);
+    }
+
+    /// Set a param to a scalar or a list.
+    pub fn set_value(&mut self, param_name: &str, value: impl Into<ParamValue>) {
+        self.params.insert(param_name.to_string(), value.into());
     }
 
     #[must_use]
-    pub fn get(&self, param_name: &str) -> Option<&String> {
+    pub fn get(&self, param_name: &str) -> Option<&ParamValue> {
         self.params.get(param_name)
     }
+
+    /// Overlay the params of `other`, its values replacing the ones already set.
+    pub fn merge(&mut self, other: &ParamContext) {
+        self.params.extend(
+            other
+                .params
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
+    }
+}
+
+impl FromIterator<(String, String)> for ParamContext {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        ParamContext {
+            params: iter
+                .into_iter()
+                .map(|(name, value)| (name, ParamValue::Scalar(value)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn param_context(params: &[(&str, &str)]) -> ParamContext {
+        params
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_from_iter() {
+        let param_context = param_context(&[("name", "john"), ("age", "30")]);
+        assert_eq!(param_context.get("name").unwrap(), "john");
+        assert_eq!(param_context.get("age").unwrap(), "30");
+    }
+
+    #[test]
+    fn test_merge_overrides() {
+        let mut base = param_context(&[("name", "john"), ("age", "30")]);
+        base.merge(&param_context(&[("age", "31"), ("city", "Paris")]));
+
+        assert_eq!(
+            base.params,
+            [("name", "john"), ("age", "31"), ("city", "Paris")]
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), ParamValue::from(value)))
+                .collect::<IndexMap<_, _>>()
+        );
+    }
 }
@@ -0,0 +1,26 @@
+/// What running a task would do, worked out without running anything.
+#[derive(PartialEq, Eq, Debug)]
+pub struct ExecutionPlan {
+    pub task_name: String,
+    /// Value of every param of the task, `<name?>` when it would have to be asked for.
+    pub params: Vec<(String, String)>,
+    pub working_directory: Option<String>,
+    /// Whether the actions run in parallel.
+    pub parallel: bool,
+    /// Plans of the needed tasks, run before the actions.
+    pub needs: Vec<ExecutionPlan>,
+    pub actions: Vec<PlannedAction>,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum PlannedAction {
+    Command {
+        /// Rendered command, unknown values showing as `<name?>`.
+        command: String,
+        continue_on_error: bool,
+    },
+    TaskCall {
+        plan: ExecutionPlan,
+        continue_on_error: bool,
+    },
+}
@@ -0,0 +1,45 @@
+use subprocess::ExitStatus;
+
+/// How a task run ended.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TaskOutcome {
+    /// The task ran, with the status of the command that made it fail or `Exited(0)`.
+    Completed(ExitStatus),
+    /// The task could not start: a parameter is missing or invalid, it was vetoed or its working
+    /// directory does not exist.
+    PreconditionFailed(String),
+    /// The run was interrupted before the task completed.
+    Interrupted,
+}
+
+impl TaskOutcome {
+    #[must_use]
+    pub fn success(&self) -> bool {
+        matches!(self, Self::Completed(status) if status.success())
+    }
+}
+
+/// Outcome of a task run, assembled from the messages it sent.
+#[derive(PartialEq, Eq, Debug)]
+pub struct TaskRunReport {
+    pub outcome: TaskOutcome,
+    /// Every command that was about to run, in order, including the ones of sub-tasks.
+    pub commands: Vec<CommandReport>,
+    /// Output and error lines of every command, in the order they were received.
+    pub output: String,
+}
+
+impl TaskRunReport {
+    #[must_use]
+    pub fn success(&self) -> bool {
+        self.outcome.success()
+    }
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct CommandReport {
+    pub command: String,
+    pub working_directory: String,
+    /// `None` when the command did not exit normally.
+    pub exit_code: Option<u32>,
+}
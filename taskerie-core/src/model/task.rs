@@ -1,25 +1,211 @@
+use std::fmt;
+
 use indexmap::IndexMap;
 
 use crate::model::InterpolatedString;
 
-use super::action::Action;
+use super::action::{Action, TaskCall};
 
-#[derive(Debug)]
+#[derive(Default, PartialEq, Eq, Debug)]
 pub struct Task {
+    pub description: Option<String>,
     pub working_directory: Option<InterpolatedString>,
+    /// Create the working directory when it is missing instead of failing.
+    pub create_working_directory: bool,
+    /// Give the working directory to the commands as is, without checking it exists nor
+    /// resolving it, for paths only the shell or a virtual filesystem can resolve.
+    pub raw_working_directory: bool,
     pub actions: Vec<Action>,
+    /// Tasks that must succeed before the actions run. Each of them runs once per execution and
+    /// set of params.
+    pub needs: Vec<TaskCall>,
+    /// Run the actions concurrently instead of one after the other.
+    pub parallel: bool,
+    /// Maximum number of actions running at once when they run in parallel.
+    pub max_parallel: Option<usize>,
+    /// Run the commands in a single shell instead of one per command, so that they share its
+    /// state such as the current directory and variables.
+    pub persistent_shell: bool,
+    /// Size of the output of a command after which it is dropped, overriding the global one.
+    pub max_output_bytes: Option<usize>,
+    /// Run the commands with only the declared environment variables and `PATH`, instead of the
+    /// environment of taskerie.
+    pub clean_env: bool,
     pub params: IndexMap<String, Param>,
+    pub env: IndexMap<String, InterpolatedString>,
+    /// Free-form labels grouping tasks in listings.
+    pub tags: Vec<String>,
+    /// Question the user has to agree to before the task runs, for destructive tasks.
+    pub confirm: Option<String>,
+    /// Skip the task when a run with the same fingerprint already succeeded, in which case its
+    /// commands capture nothing.
+    pub cache: bool,
+    /// Files whose content is part of the cache fingerprint, relative to the base directory.
+    pub inputs: Vec<String>,
 }
 
 impl Task {
     /// Check whether the task can be executed without any additional parameters.
     #[must_use]
     pub fn is_standalone(&self) -> bool {
-        self.params.is_empty() || self.params.values().all(|param| param.default.is_some())
+        self.params.values().all(|param| !param.is_required())
+    }
+
+    /// Tasks called by the actions, then the needed tasks.
+    pub fn task_calls(&self) -> impl Iterator<Item = &TaskCall> {
+        self.actions
+            .iter()
+            .filter_map(|action| match action {
+                Action::TaskCall(task_call) => Some(task_call),
+                Action::Command(_) => None,
+            })
+            .chain(&self.needs)
+    }
+
+    /// Every interpolated string of the task, which are rendered with its params.
+    pub fn templates(&self) -> impl Iterator<Item = &InterpolatedString> {
+        let action_templates = self.actions.iter().flat_map(|action| match action {
+            Action::Command(command) => [&command.stdin, &command.working_directory]
+                .into_iter()
+                .flatten()
+                .chain([&command.command])
+                .collect::<Vec<_>>(),
+            Action::TaskCall(task_call) => task_call.params.values().collect(),
+        });
+        self.working_directory
+            .iter()
+            .chain(self.params.values().flat_map(|param| {
+                let source = param.source.as_ref().map(|source| match source {
+                    ParamSource::Command(template) | ParamSource::File(template) => template,
+                });
+                param.default.as_ref().into_iter().chain(source)
+            }))
+            .chain(self.env.values())
+            .chain(action_templates)
+            .chain(
+                self.needs
+                    .iter()
+                    .flat_map(|dependency| dependency.params.values()),
+            )
     }
 }
 
-#[derive(Debug)]
+#[derive(Default, PartialEq, Eq, Debug)]
 pub struct Param {
-    pub default: Option<String>,
+    /// Value used when none is given, which can refer to the params declared before this one.
+    pub default: Option<InterpolatedString>,
+    /// Where the value is read from when none is given, instead of a default.
+    pub source: Option<ParamSource>,
+    /// Mask the value in the commands and output sent while running.
+    pub secret: bool,
+    pub param_type: ParamType,
+}
+
+impl Param {
+    /// Whether a value has to be given, the param having neither a default nor a source.
+    #[must_use]
+    pub fn is_required(&self) -> bool {
+        self.default.is_none() && self.source.is_none()
+    }
+
+    /// Whether the value is masked, which is always the case when it is read from a command or a
+    /// file.
+    #[must_use]
+    pub fn is_secret(&self) -> bool {
+        self.secret || self.source.is_some()
+    }
+}
+
+/// Place the value of a param is read from when it runs, such as a secret store, the value being
+/// trimmed.
+#[derive(PartialEq, Eq, Debug)]
+pub enum ParamSource {
+    /// Output of a command run by the shell in the base directory.
+    Command(InterpolatedString),
+    /// Content of a file, relative to the base directory.
+    File(InterpolatedString),
+}
+
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub enum ParamType {
+    #[default]
+    String,
+    Int,
+    Bool,
+    /// One of the listed choices.
+    Enum(Vec<String>),
+}
+
+/// Written as in the config, the choices of an enum being separated by `|`.
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParamType::String => f.write_str("string"),
+            ParamType::Int => f.write_str("int"),
+            ParamType::Bool => f.write_str("bool"),
+            ParamType::Enum(choices) => f.write_str(&choices.join("|")),
+        }
+    }
+}
+
+impl ParamType {
+    /// Check whether the value is acceptable for this type, returning the reason if it is not.
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        match self {
+            ParamType::String => Ok(()),
+            ParamType::Int => value
+                .parse::<i64>()
+                .map(|_| ())
+                .map_err(|_| format!("'{value}' is not an integer")),
+            ParamType::Bool => match value {
+                "true" | "false" => Ok(()),
+                _ => Err(format!(
+                    "'{value}' is not a boolean, expected true or false"
+                )),
+            },
+            ParamType::Enum(choices) => {
+                if choices.iter().any(|choice| choice == value) {
+                    Ok(())
+                } else {
+                    Err(format!("'{value}' is not one of {}", choices.join(", ")))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_string() {
+        assert!(ParamType::String.validate("anything").is_ok());
+    }
+
+    #[test]
+    fn test_validate_int() {
+        assert!(ParamType::Int.validate("-42").is_ok());
+        assert_eq!(
+            ParamType::Int.validate("abc"),
+            Err("'abc' is not an integer".to_string())
+        );
+    }
+
+    #[test]
+    fn test_validate_bool() {
+        assert!(ParamType::Bool.validate("true").is_ok());
+        assert!(ParamType::Bool.validate("false").is_ok());
+        assert!(ParamType::Bool.validate("yes").is_err());
+    }
+
+    #[test]
+    fn test_validate_enum() {
+        let param_type = ParamType::Enum(vec!["dev".to_string(), "prod".to_string()]);
+        assert!(param_type.validate("prod").is_ok());
+        assert_eq!(
+            param_type.validate("staging"),
+            Err("'staging' is not one of dev, prod".to_string())
+        );
+    }
 }
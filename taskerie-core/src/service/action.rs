@@ -1,23 +1,277 @@
+use std::{str::FromStr, time::Duration};
+
+use anyhow::bail;
 use indexmap::IndexMap;
 
 use crate::{
     config,
-    model::{self},
+    model::{self, Delimiters, InterpolatedString, action::Sequencing},
+    service::interpolated_string::ParseError,
 };
 
-impl TryFrom<config::Action> for model::Action {
+impl TryFrom<(config::Action, &Delimiters)> for model::Action {
     type Error = anyhow::Error;
 
-    fn try_from(action: config::Action) -> Result<Self, Self::Error> {
+    fn try_from((action, delimiters): (config::Action, &Delimiters)) -> Result<Self, Self::Error> {
+        let parse = |value: &str| InterpolatedString::parse_with_delimiters(value, delimiters);
         Ok(match action {
-            config::Action::TaskCall { name, params } => Self::TaskCall(model::action::TaskCall {
+            config::Action::TaskCall {
+                name,
+                params,
+                continue_on_error,
+            } => Self::TaskCall(model::action::TaskCall {
                 name,
+                continue_on_error,
                 params: params
                     .into_iter()
-                    .map(|(key, value)| value.parse().map(|value| (key, value)))
+                    .map(|(key, value)| parse(&value).map(|value| (key, value)))
                     .collect::<Result<IndexMap<_, _>, _>>()?,
             }),
-            config::Action::Command(command) => Self::Command(command.parse()?),
+            config::Action::Command(command) => Self::Command(Box::new(model::action::Command {
+                arguments: if command.shell == Some(false) {
+                    if command.split_operators {
+                        bail!("A command run without a shell cannot be split on its operators");
+                    }
+                    split_arguments(&command.run, delimiters)?
+                        .iter()
+                        .map(|argument| parse(argument))
+                        .collect::<Result<_, _>>()?
+                } else {
+                    Vec::new()
+                },
+                steps: if command.split_operators {
+                    if command.stdin.is_some() {
+                        bail!("A command split on its operators cannot be given an input");
+                    }
+                    split_operators(&command.run, delimiters)?
+                        .into_iter()
+                        .map(|(sequencing, step)| parse(step).map(|step| (sequencing, step)))
+                        .collect::<Result<_, _>>()?
+                } else {
+                    Vec::new()
+                },
+                command: parse(&command.run)?,
+                label: command.label,
+                continue_on_error: command.continue_on_error,
+                capture: command.capture,
+                retries: command.retries,
+                retry_delay: Duration::from_millis(command.retry_delay_ms),
+                silent: command.silent,
+                stdin: command.stdin.as_deref().map(parse).transpose()?,
+                working_directory: command
+                    .working_directory
+                    .as_deref()
+                    .map(parse)
+                    .transpose()?,
+                allowed_exit_codes: command.allowed_exit_codes,
+            })),
         })
     }
 }
+
+/// Split a command on its `&&` and `;` operators that are neither quoted, escaped by a backslash
+/// nor inside an interpolation.
+fn split_operators<'a>(
+    command: &'a str,
+    delimiters: &Delimiters,
+) -> anyhow::Result<Vec<(Sequencing, &'a str)>> {
+    let mut steps = Vec::new();
+    let mut sequencing = Sequencing::Always;
+    let mut step_start = 0;
+    let mut quote = None;
+    let mut chars = command.char_indices();
+    while let Some((index, c)) = chars.next() {
+        let rest = &command[index..];
+        let operator = match (quote, c) {
+            (Some('"') | None, '\\') => {
+                chars.next();
+                None
+            }
+            (Some(quote_char), _) => {
+                if c == quote_char {
+                    quote = None;
+                }
+                None
+            }
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                None
+            }
+            (None, _) if rest.starts_with(&delimiters.open) => {
+                // Skip to the end of the interpolation, the parser reports it if unclosed
+                let end = rest
+                    .find(&delimiters.close)
+                    .map_or(rest.len(), |end| end + delimiters.close.len());
+                let skipped_until = index + end;
+                while chars.as_str().len() > command.len() - skipped_until {
+                    chars.next();
+                }
+                None
+            }
+            (None, ';') => Some((Sequencing::Always, 1)),
+            (None, '&') if rest.starts_with("&&") => Some((Sequencing::OnSuccess, 2)),
+            (None, _) => None,
+        };
+        if let Some((next_sequencing, operator_length)) = operator {
+            steps.push((sequencing, command[step_start..index].trim()));
+            sequencing = next_sequencing;
+            step_start = index + operator_length;
+            if operator_length > 1 {
+                chars.next();
+            }
+        }
+    }
+    let last_step = command[step_start..].trim();
+    // A trailing `;` only ends the last command
+    if !(last_step.is_empty() && sequencing == Sequencing::Always && !steps.is_empty()) {
+        steps.push((sequencing, last_step));
+    }
+    if steps.iter().any(|(_, step)| step.is_empty()) {
+        bail!("Missing command around an operator in '{command}'");
+    }
+    Ok(steps)
+}
+
+/// Split a command run without a shell into its program and arguments, on the whitespace that is
+/// neither quoted nor inside an interpolation, and remove the quotes.
+///
+/// Outside quotes, a backslash escapes a quote, a whitespace or a backslash. Inside double quotes,
+/// it only escapes a double quote or a backslash. It is kept before any other character, such as
+/// in escaped delimiters or Windows paths.
+pub(super) fn split_arguments(
+    command: &str,
+    delimiters: &Delimiters,
+) -> anyhow::Result<Vec<String>> {
+    let mut arguments = Vec::new();
+    // Argument being read, `None` between two arguments
+    let mut argument: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command.char_indices();
+    while let Some((index, c)) = chars.next() {
+        let rest = &command[index..];
+        let next = rest[c.len_utf8()..].chars().next();
+        match (quote, c) {
+            (Some(quote_char), _) if c == quote_char => quote = None,
+            (Some('"'), '\\') if matches!(next, Some('"' | '\\')) => {
+                chars.next();
+                argument.get_or_insert_default().extend(next);
+            }
+            (None, '\\')
+                if next.is_some_and(|next| {
+                    matches!(next, '"' | '\'' | '\\') || next.is_whitespace()
+                }) =>
+            {
+                chars.next();
+                argument.get_or_insert_default().extend(next);
+            }
+            (Some(_), _) => argument.get_or_insert_default().push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                argument.get_or_insert_default();
+            }
+            (None, _) if c.is_whitespace() => arguments.extend(argument.take()),
+            (None, _) if rest.starts_with(&delimiters.open) => {
+                // Kept whole, the parser reports it if unclosed
+                let end = rest
+                    .find(&delimiters.close)
+                    .map_or(rest.len(), |end| end + delimiters.close.len());
+                argument.get_or_insert_default().push_str(&rest[..end]);
+                let skipped_until = index + end;
+                while chars.as_str().len() > command.len() - skipped_until {
+                    chars.next();
+                }
+            }
+            (None, _) => argument.get_or_insert_default().push(c),
+        }
+    }
+    if quote.is_some() {
+        bail!("Unclosed quote in '{command}'");
+    }
+    arguments.extend(argument);
+    if arguments.is_empty() {
+        bail!("Missing program to run in '{command}'");
+    }
+    Ok(arguments)
+}
+
+impl FromStr for model::action::Command {
+    type Err = ParseError;
+
+    fn from_str(command: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            command: command.parse()?,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_operators() {
+        let delimiters = Delimiters::default();
+        assert_eq!(
+            split_operators(
+                r#"echo "a && b" && echo 'c; d'; find . -exec ls {} \; && echo {{ x | default: "e;f" }};"#,
+                &delimiters
+            )
+            .unwrap(),
+            vec![
+                (Sequencing::Always, r#"echo "a && b""#),
+                (Sequencing::OnSuccess, "echo 'c; d'"),
+                (Sequencing::Always, r"find . -exec ls {} \;"),
+                (Sequencing::OnSuccess, r#"echo {{ x | default: "e;f" }}"#),
+            ]
+        );
+        assert_eq!(
+            split_operators("echo a", &delimiters).unwrap(),
+            vec![(Sequencing::Always, "echo a")]
+        );
+    }
+
+    #[test]
+    fn test_split_operators_missing_command() {
+        for command in ["echo a &&", "; echo a", "echo a;; echo b"] {
+            assert_eq!(
+                split_operators(command, &Delimiters::default())
+                    .unwrap_err()
+                    .to_string(),
+                format!("Missing command around an operator in '{command}'")
+            );
+        }
+    }
+
+    #[test]
+    fn test_split_arguments() {
+        let delimiters = Delimiters::default();
+        assert_eq!(
+            split_arguments(
+                r#"  printf '%s $HOME\n' "a \"b\" \c" {{ x | default: "y z" }}-{{ y }} \{\{ C:\dir a\ b "" "#,
+                &delimiters
+            )
+            .unwrap(),
+            vec![
+                "printf",
+                r"%s $HOME\n",
+                r#"a "b" \c"#,
+                r#"{{ x | default: "y z" }}-{{ y }}"#,
+                r"\{\{",
+                r"C:\dir",
+                "a b",
+                "",
+            ]
+        );
+        assert_eq!(
+            split_arguments("echo 'unclosed", &delimiters)
+                .unwrap_err()
+                .to_string(),
+            "Unclosed quote in 'echo 'unclosed'"
+        );
+        assert_eq!(
+            split_arguments("  ", &delimiters).unwrap_err().to_string(),
+            "Missing program to run in '  '"
+        );
+    }
+}
@@ -0,0 +1,345 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, bail};
+use indexmap::IndexMap;
+
+use crate::model::{
+    Action, InterpolatedString, Task, TaskerieContext,
+    action::{Command, TaskCall},
+    task::{Param, ParamType},
+};
+
+/// Assembles a [`TaskerieContext`] in code instead of loading a config.
+#[derive(Default, Debug)]
+pub struct TaskerieContextBuilder {
+    tasks: Vec<(String, Task)>,
+    env: Vec<(String, String)>,
+    vars: IndexMap<String, String>,
+    base_dir: PathBuf,
+    default_task: Option<String>,
+}
+
+impl TaskerieContext {
+    #[must_use]
+    pub fn builder() -> TaskerieContextBuilder {
+        TaskerieContextBuilder::default()
+    }
+}
+
+impl TaskerieContextBuilder {
+    #[must_use]
+    pub fn task(mut self, name: &str, task: Task) -> Self {
+        self.tasks.push((name.to_string(), task));
+        self
+    }
+
+    /// Environment variable set for every command, which can refer to params.
+    #[must_use]
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    #[must_use]
+    pub fn var(mut self, name: &str, value: &str) -> Self {
+        self.vars.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    /// Directory relative working directories are resolved against, the current one by default.
+    #[must_use]
+    pub fn base_dir(mut self, base_dir: impl Into<PathBuf>) -> Self {
+        self.base_dir = base_dir.into();
+        self
+    }
+
+    #[must_use]
+    pub fn default_task(mut self, name: &str) -> Self {
+        self.default_task = Some(name.to_string());
+        self
+    }
+
+    /// Check the tasks as done when loading a config, their names having to be unique.
+    pub fn build(self) -> anyhow::Result<TaskerieContext> {
+        let mut tasks = IndexMap::new();
+        for (name, task) in self.tasks {
+            if tasks.contains_key(&name) {
+                bail!("Task '{name}' is defined twice");
+            }
+            tasks.insert(name, task);
+        }
+        let env = self
+            .env
+            .into_iter()
+            .map(|(key, value)| {
+                let value = value.parse().with_context(|| {
+                    format!("Invalid value '{value}' of environment variable {key}")
+                })?;
+                anyhow::Ok((key, value))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let context = TaskerieContext {
+            tasks,
+            env,
+            vars: self.vars,
+            base_dir: self.base_dir,
+            default_task: self.default_task,
+            ..Default::default()
+        };
+        context.validate()?;
+        Ok(context)
+    }
+}
+
+/// Assembles a [`Task`] in code. The strings are parsed for interpolations when building it.
+#[derive(Default, Debug)]
+pub struct TaskBuilder {
+    description: Option<String>,
+    working_directory: Option<String>,
+    actions: Vec<ActionDefinition>,
+    needs: Vec<String>,
+    parallel: bool,
+    params: Vec<(String, ParamType, Option<String>)>,
+    env: Vec<(String, String)>,
+    tags: Vec<String>,
+}
+
+#[derive(Debug)]
+enum ActionDefinition {
+    Command(String),
+    TaskCall(String, Vec<(String, String)>),
+    Action(Action),
+}
+
+impl TaskBuilder {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn description(mut self, description: &str) -> Self {
+        self.description = Some(description.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn working_directory(mut self, working_directory: &str) -> Self {
+        self.working_directory = Some(working_directory.to_string());
+        self
+    }
+
+    /// Run a command without options.
+    #[must_use]
+    pub fn command(mut self, command: &str) -> Self {
+        self.actions
+            .push(ActionDefinition::Command(command.to_string()));
+        self
+    }
+
+    /// Call a task with the given params, whose values can refer to the params of this task.
+    #[must_use]
+    pub fn task_call<'a>(
+        mut self,
+        name: &str,
+        params: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Self {
+        self.actions.push(ActionDefinition::TaskCall(
+            name.to_string(),
+            params
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        ));
+        self
+    }
+
+    /// Add an action built beforehand, such as a command with options.
+    #[must_use]
+    pub fn action(mut self, action: Action) -> Self {
+        self.actions.push(ActionDefinition::Action(action));
+        self
+    }
+
+    /// Need a task, without params.
+    #[must_use]
+    pub fn need(mut self, name: &str) -> Self {
+        self.needs.push(name.to_string());
+        self
+    }
+
+    #[must_use]
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Declare a string param, required unless it has a default.
+    #[must_use]
+    pub fn param(self, name: &str, default: Option<&str>) -> Self {
+        self.typed_param(name, ParamType::String, default)
+    }
+
+    #[must_use]
+    pub fn typed_param(mut self, name: &str, param_type: ParamType, default: Option<&str>) -> Self {
+        self.params.push((
+            name.to_string(),
+            param_type,
+            default.map(ToString::to_string),
+        ));
+        self
+    }
+
+    #[must_use]
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    #[must_use]
+    pub fn tag(mut self, tag: &str) -> Self {
+        self.tags.push(tag.to_string());
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<Task> {
+        let parse = |value: &str| {
+            value
+                .parse::<InterpolatedString>()
+                .with_context(|| format!("Invalid interpolation in '{value}'"))
+        };
+        Ok(Task {
+            description: self.description,
+            working_directory: self.working_directory.as_deref().map(parse).transpose()?,
+            actions: self
+                .actions
+                .into_iter()
+                .map(|action| {
+                    Ok(match action {
+                        ActionDefinition::Command(command) => Action::Command(Box::new(Command {
+                            command: parse(&command)?,
+                            ..Default::default()
+                        })),
+                        ActionDefinition::TaskCall(name, params) => Action::TaskCall(TaskCall {
+                            name,
+                            params: params
+                                .iter()
+                                .map(|(name, value)| Ok((name.clone(), parse(value)?)))
+                                .collect::<anyhow::Result<_>>()?,
+                            continue_on_error: false,
+                        }),
+                        ActionDefinition::Action(action) => action,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?,
+            needs: self
+                .needs
+                .into_iter()
+                .map(|name| TaskCall {
+                    name,
+                    ..Default::default()
+                })
+                .collect(),
+            parallel: self.parallel,
+            params: self
+                .params
+                .into_iter()
+                .map(|(name, param_type, default)| {
+                    let default = default.as_deref().map(parse).transpose()?;
+                    Ok((
+                        name,
+                        Param {
+                            default,
+                            source: None,
+                            secret: false,
+                            param_type,
+                        },
+                    ))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            env: self
+                .env
+                .iter()
+                .map(|(key, value)| Ok((key.clone(), parse(value)?)))
+                .collect::<anyhow::Result<_>>()?,
+            tags: self.tags,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+
+    use super::*;
+    use crate::{message::ExecutionMessage, model::ParamContext};
+
+    #[test]
+    fn test_build_and_run() {
+        let context = TaskerieContext::builder()
+            .task(
+                "greet",
+                TaskBuilder::new()
+                    .param("name", Some("world"))
+                    .command("echo hello {{ name }}")
+                    .build()
+                    .unwrap(),
+            )
+            .task(
+                "build",
+                TaskBuilder::new()
+                    .need("greet")
+                    .task_call("greet", [("name", "{{ who }}")])
+                    .build()
+                    .unwrap(),
+            )
+            .var("who", "builder")
+            .build()
+            .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name("build", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(outcome.success());
+        assert_eq!(
+            rx.iter()
+                .filter_map(|message| match message.into_action_message().1 {
+                    ExecutionMessage::CommandOutput { output } => Some(output),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            vec!["hello world", "hello builder"]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_task_names() {
+        let task = || TaskBuilder::new().command("echo").build().unwrap();
+        let error = TaskerieContext::builder()
+            .task("build", task())
+            .task("build", task())
+            .build()
+            .unwrap_err();
+
+        assert_eq!(error.to_string(), "Task 'build' is defined twice");
+    }
+
+    #[test]
+    fn test_build_validates_tasks() {
+        let error = TaskerieContext::builder()
+            .task(
+                "build",
+                TaskBuilder::new().task_call("missing", []).build().unwrap(),
+            )
+            .build()
+            .unwrap_err();
+
+        assert!(error.to_string().contains("'build' calls 'missing'"));
+        assert!(TaskBuilder::new().command("echo {{ }}").build().is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+
+use super::{Execution, RenderedCommand, cyclic_call, render_env, render_task_call_params};
+use crate::{
+    message::ExecutionMessage,
+    model::{
+        ParamContext, RunError, TaskOutcome, TaskerieContext,
+        action::{Action, TaskCall},
+        task::Task,
+    },
+};
+
+/// Directory of the cache entries, relative to the base directory of the config.
+pub const CACHE_DIRECTORY: &str = ".taskerie/cache";
+
+/// Encoding of the fingerprints, changing it leaves the existing entries unused.
+const FINGERPRINT_VERSION: &str = "taskerie-cache-1";
+
+/// Whether a task about to run can be skipped.
+pub(super) enum CacheLookup {
+    /// The task is not cached, or nothing actually runs.
+    Uncached,
+    /// No run with this fingerprint succeeded yet.
+    Miss(String),
+    /// A run with the same fingerprint succeeded, [`ExecutionMessage::CacheHit`] is sent.
+    Hit,
+}
+
+impl TaskerieContext {
+    /// Look up a task about to run in the cache, if it is cached.
+    pub(super) fn lookup_cache(
+        &self,
+        name: &str,
+        task: &Task,
+        param_context: &ParamContext,
+        execution: &Execution,
+    ) -> Result<CacheLookup, RunError> {
+        if !task.cache || execution.dry_run {
+            return Ok(CacheLookup::Uncached);
+        }
+        let fingerprint = match self.fingerprint(name, task, param_context, execution) {
+            Ok(fingerprint) => fingerprint,
+            // Reported when the task runs
+            Err(error @ (RunError::CyclicCall(_) | RunError::TaskNotFound { .. })) => {
+                return Err(error);
+            }
+            Err(error) => {
+                // Such as a command using the output captured by a previous one
+                log::warn!("Task '{name}' is not cached for this run: {error}");
+                return Ok(CacheLookup::Uncached);
+            }
+        };
+        if !self.is_cached(&fingerprint) {
+            return Ok(CacheLookup::Miss(fingerprint));
+        }
+        execution.sender.send(ExecutionMessage::CacheHit {
+            task_name: name.to_string(),
+        })?;
+        Ok(CacheLookup::Hit)
+    }
+
+    /// Record the outcome of a cached task that ran, when it succeeded without being interrupted.
+    pub(super) fn store_cache_result(
+        &self,
+        name: &str,
+        fingerprint: &str,
+        outcome: &TaskOutcome,
+        execution: &Execution,
+    ) {
+        if outcome.success()
+            && !execution.interrupter.is_interrupted()
+            && let Err(error) = self.store_cache_entry(name, fingerprint)
+        {
+            // The task only runs again next time
+            log::warn!("Could not cache the result of task '{name}': {error}");
+        }
+    }
+
+    /// Fingerprint of what running a cached task depends on: the rendered commands, working
+    /// directories and environments of the commands it and the tasks it needs or calls would run,
+    /// the values of its params and the content of its input files.
+    ///
+    /// Fails when a command cannot be rendered before the task runs.
+    fn fingerprint(
+        &self,
+        name: &str,
+        task: &Task,
+        param_context: &ParamContext,
+        execution: &Execution,
+    ) -> Result<String, RunError> {
+        let mut fingerprint = Fingerprint::default();
+        fingerprint.add(FINGERPRINT_VERSION);
+        fingerprint.add_count(task.params.len());
+        for param_name in task.params.keys() {
+            fingerprint.add(param_name);
+            fingerprint.add_optional(param_context.get(param_name).map(ToString::to_string));
+        }
+        self.add_task_commands(
+            name,
+            task,
+            param_context,
+            execution,
+            &mut Vec::new(),
+            &mut fingerprint,
+        )?;
+        fingerprint.add_count(task.inputs.len());
+        for input in &task.inputs {
+            fingerprint.add(input);
+            // A missing input is part of the fingerprint too
+            fingerprint.add_optional(fs::read(self.base_dir.join(input)).ok());
+        }
+        Ok(fingerprint.finish())
+    }
+
+    /// Add the commands `task` would run to `fingerprint`, after the ones of the tasks it needs,
+    /// and the ones of the tasks it calls in place.
+    fn add_task_commands(
+        &self,
+        name: &str,
+        task: &Task,
+        param_context: &ParamContext,
+        execution: &Execution,
+        call_stack: &mut Vec<String>,
+        fingerprint: &mut Fingerprint,
+    ) -> Result<(), RunError> {
+        call_stack.push(name.to_string());
+        fingerprint.add(name);
+        for dependency in &task.needs {
+            self.add_called_task_commands(
+                dependency,
+                param_context,
+                execution,
+                call_stack,
+                fingerprint,
+            )?;
+        }
+        fingerprint.add_count(task.actions.len());
+        for action in &task.actions {
+            match action {
+                Action::Command(command) => {
+                    let rendered = RenderedCommand::new(command, param_context, execution.shell)?;
+                    fingerprint.add_count(rendered.commands.len());
+                    for (sequencing, part) in &rendered.commands {
+                        fingerprint.add(format!("{sequencing:?}"));
+                        fingerprint.add(part);
+                    }
+                    fingerprint.add_count(rendered.arguments.len());
+                    for argument in &rendered.arguments {
+                        fingerprint.add(argument);
+                    }
+                    fingerprint.add_optional(rendered.stdin);
+                    fingerprint.add_count(rendered.shell.len());
+                    for shell_part in &rendered.shell {
+                        fingerprint.add(shell_part);
+                    }
+
+                    let working_directory = command
+                        .working_directory
+                        .as_ref()
+                        .or(task.working_directory.as_ref())
+                        .map(|directory| directory.render(param_context))
+                        .transpose()?
+                        .unwrap_or_else(|| "./".into());
+                    // Not resolved, the directory may only be created when the task runs
+                    fingerprint.add(
+                        self.base_dir
+                            .join(&*working_directory)
+                            .to_string_lossy()
+                            .as_bytes(),
+                    );
+
+                    let env = render_env(
+                        &self.dotenv,
+                        &self.env,
+                        &task.env,
+                        param_context,
+                        task.clean_env,
+                    )?;
+                    fingerprint.add([u8::from(task.clean_env)]);
+                    fingerprint.add_count(env.len());
+                    for (key, value) in &env {
+                        fingerprint.add(key);
+                        fingerprint.add(value);
+                    }
+                }
+                Action::TaskCall(task_call) => {
+                    self.add_called_task_commands(
+                        task_call,
+                        param_context,
+                        execution,
+                        call_stack,
+                        fingerprint,
+                    )?;
+                }
+            }
+        }
+        call_stack.pop();
+        Ok(())
+    }
+
+    /// Add the commands of a task called or needed by a task running with `param_context` to
+    /// `fingerprint`, with the params it would run with.
+    fn add_called_task_commands(
+        &self,
+        task_call: &TaskCall,
+        param_context: &ParamContext,
+        execution: &Execution,
+        call_stack: &mut Vec<String>,
+        fingerprint: &mut Fingerprint,
+    ) -> Result<(), RunError> {
+        if call_stack.contains(&task_call.name) {
+            return Err(cyclic_call(call_stack, &task_call.name));
+        }
+        let task = self
+            .get_task_by_name(&task_call.name)
+            .ok_or_else(|| self.task_not_found(&task_call.name))?;
+        let mut task_param_context = render_task_call_params(task_call, param_context)?;
+        self.set_context_values(task, &mut task_param_context);
+        for (param_name, param) in &task.params {
+            // Params read from their source or asked for stay without value, rendering the
+            // commands using them fails
+            self.resolve_param(param_name, param, &mut task_param_context, execution, false)?;
+        }
+        self.add_task_commands(
+            &task_call.name,
+            task,
+            &task_param_context,
+            execution,
+            call_stack,
+            fingerprint,
+        )
+    }
+
+    /// Whether a run of a task with this fingerprint already succeeded.
+    fn is_cached(&self, fingerprint: &str) -> bool {
+        self.cache_entry(fingerprint).is_file()
+    }
+
+    /// Record that a run of the task `name` with this fingerprint succeeded.
+    fn store_cache_entry(&self, name: &str, fingerprint: &str) -> io::Result<()> {
+        let path = self.cache_entry(fingerprint);
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        // The content is only informative, the entry existing is what counts
+        fs::write(path, format!("task: {name}\n"))
+    }
+
+    fn cache_entry(&self, fingerprint: &str) -> PathBuf {
+        self.base_dir
+            .join(Path::new(CACHE_DIRECTORY))
+            .join(fingerprint)
+    }
+}
+
+/// SHA-256 digest of values each prefixed by their length, so that consecutive values cannot be
+/// mistaken for other ones.
+#[derive(Default)]
+struct Fingerprint(Sha256);
+
+impl Fingerprint {
+    fn add(&mut self, value: impl AsRef<[u8]>) {
+        let value = value.as_ref();
+        self.add_count(value.len());
+        self.0.update(value);
+    }
+
+    /// Add the number of values that follow, or of bytes of a value.
+    fn add_count(&mut self, count: usize) {
+        self.0.update((count as u64).to_le_bytes());
+    }
+
+    fn add_optional(&mut self, value: Option<impl AsRef<[u8]>>) {
+        match value {
+            Some(value) => {
+                self.0.update([1]);
+                self.add(value);
+            }
+            None => self.0.update([0]),
+        }
+    }
+
+    /// Hexadecimal digest of the values added.
+    fn finish(self) -> String {
+        self.0
+            .finalize()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+}
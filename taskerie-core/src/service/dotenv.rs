@@ -0,0 +1,102 @@
+use anyhow::{anyhow, bail};
+use indexmap::IndexMap;
+
+/// Parse the content of a dotenv file made of `KEY=value` lines.
+///
+/// Blank lines and lines starting with `#` are skipped, as well as an `export` prefix. Values can
+/// be quoted: double quoted ones support `\n`, `\"` and `\\` escapes, single quoted ones are used
+/// as is. Unquoted values end at a ` #` comment.
+pub fn parse(content: &str) -> anyhow::Result<IndexMap<String, String>> {
+    let mut variables = IndexMap::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Expected KEY=value on line {}", index + 1))?;
+        let key = key.trim();
+        if key.is_empty() {
+            bail!("Missing variable name on line {}", index + 1);
+        }
+        let value = parse_value(value.trim())
+            .ok_or_else(|| anyhow!("Unclosed quote on line {}", index + 1))?;
+        variables.insert(key.to_string(), value);
+    }
+    Ok(variables)
+}
+
+/// Unquote a value, returning `None` when a quote is not closed.
+fn parse_value(value: &str) -> Option<String> {
+    if let Some(quoted) = value.strip_prefix('\'') {
+        return quoted.split_once('\'').map(|(value, _)| value.to_string());
+    }
+    let Some(quoted) = value.strip_prefix('"') else {
+        let value = value.split_once(" #").map_or(value, |(value, _)| value);
+        return Some(value.trim_end().to_string());
+    };
+
+    let mut unquoted = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(unquoted),
+            '\\' => match chars.next()? {
+                'n' => unquoted.push('\n'),
+                escaped => unquoted.push(escaped),
+            },
+            c => unquoted.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let variables = parse(
+            r#"
+# Comment
+KEY=value
+export EXPORTED = spaced # trailing comment
+DOUBLE="quoted # not a comment\nwith \"escapes\""
+SINGLE='as \n is'
+EMPTY=
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            variables.into_iter().collect::<Vec<_>>(),
+            [
+                ("KEY", "value"),
+                ("EXPORTED", "spaced"),
+                ("DOUBLE", "quoted # not a comment\nwith \"escapes\""),
+                ("SINGLE", "as \\n is"),
+                ("EMPTY", ""),
+            ]
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("KEY=value\nINVALID").unwrap_err().to_string(),
+            "Expected KEY=value on line 2"
+        );
+        assert_eq!(
+            parse("KEY=\"unclosed").unwrap_err().to_string(),
+            "Unclosed quote on line 1"
+        );
+        assert_eq!(
+            parse("=value").unwrap_err().to_string(),
+            "Missing variable name on line 1"
+        );
+    }
+}
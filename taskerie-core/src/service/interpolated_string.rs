@@ -1,70 +1,411 @@
-use std::{borrow::Cow, str::FromStr};
+use std::{borrow::Cow, fmt, str::FromStr};
 
 use itertools::Itertools;
 
-use crate::model::{InterpolatedString, InterpolatedVariable, ParamContext};
+use crate::model::{
+    Delimiters, InterpolatedString, InterpolatedVariable, ListSelector, ParamContext, ParamValue,
+};
+
+/// Error found in the interpolations of a string, located by the byte offset of the interpolation.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum ParseError {
+    /// An interpolation without variable name, such as `{{ }}`.
+    EmptyInterpolation {
+        interpolation: String,
+        position: usize,
+    },
+    /// An interpolation using a filter other than `default` and `join`.
+    UnknownFilter {
+        filter: String,
+        interpolation: String,
+        position: usize,
+    },
+    /// An interpolation chaining filters, such as `{{ name | join: "," | default: "none" }}`.
+    ChainedFilters {
+        interpolation: String,
+        position: usize,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::EmptyInterpolation {
+                interpolation,
+                position,
+            } => write!(
+                f,
+                "Missing variable name in '{interpolation}' at byte {position}"
+            ),
+            ParseError::UnknownFilter {
+                filter,
+                interpolation,
+                position,
+            } => write!(
+                f,
+                "Unknown interpolation filter '{filter}' in '{interpolation}' at byte {position}"
+            ),
+            ParseError::ChainedFilters {
+                interpolation,
+                position,
+            } => write!(
+                f,
+                "Only one filter can be applied in '{interpolation}' at byte {position}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Variable of a string that has no value when rendering it, along with the string as written in
+/// the config to locate it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct RenderError {
+    pub kind: RenderErrorKind,
+    pub template: String,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum RenderErrorKind {
+    /// A param without value or default.
+    MissingParam(String),
+    /// An `env.NAME` variable defined neither by the process environment nor by the dotenv file,
+    /// without default.
+    MissingEnvironmentVariable(String),
+    /// A `name.N` variable whose value has `len` items only.
+    IndexOutOfRange {
+        name: String,
+        index: usize,
+        len: usize,
+    },
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} while rendering '{}'", self.kind, self.template)
+    }
+}
+
+impl fmt::Display for RenderErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderErrorKind::MissingParam(name) => {
+                write!(f, "Could not find value for param '{name}'")
+            }
+            RenderErrorKind::MissingEnvironmentVariable(name) => {
+                write!(f, "Could not find environment variable '{name}'")
+            }
+            RenderErrorKind::IndexOutOfRange { name, index, len } => write!(
+                f,
+                "Index {index} is out of range for param '{name}', which has {len} items"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
 
 impl FromStr for InterpolatedString {
-    type Err = anyhow::Error;
+    type Err = ParseError;
 
     fn from_str(val: &str) -> Result<Self, Self::Err> {
-        let interpolated_variable_regex = regex::Regex::new(r"\{\{\s*(.*?)\s*\}\}")?;
-        let mut acc = 0;
-
-        let parts = interpolated_variable_regex
-            .captures_iter(val)
-            .map(|captures| {
-                let value = captures
-                    .get(1)
-                    .ok_or_else(|| anyhow::anyhow!("Could not find captured variable name"))?
-                    .as_str();
-                let whole = captures
-                    .get(0)
-                    .ok_or_else(|| anyhow::anyhow!("Could not find whole interpolated variable"))?;
-                let start = whole.start() - acc;
-                let end = whole.end() - acc;
-                acc += whole.len();
-                anyhow::Ok((
-                    InterpolatedVariable {
-                        name: value.to_string(),
-                        start,
-                    },
-                    end,
-                ))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
-        let mut value = val.to_owned();
-        for (variable, span_end) in &parts {
-            value.replace_range(variable.start..*span_end, "");
+        Self::parse_with_delimiters(val, &Delimiters::default())
+    }
+}
+
+/// Write a delimiter with every character escaped, such as `\{\{` for `{{`.
+fn escaped(delimiter: &str) -> String {
+    delimiter.chars().map(|c| format!("\\{c}")).collect()
+}
+
+impl InterpolatedString {
+    /// Parse a string whose variables are surrounded by the given delimiters instead of `{{` and
+    /// `}}`.
+    pub fn parse_with_delimiters(val: &str, delimiters: &Delimiters) -> Result<Self, ParseError> {
+        // Escaped delimiters (`\{\{` and `\}\}` by default) are matched first so they are never interpolated
+        let interpolated_variable_regex = regex::Regex::new(&format!(
+            r"{}|{}|{}\s*(.*?)\s*{}",
+            regex::escape(&escaped(&delimiters.open)),
+            regex::escape(&escaped(&delimiters.close)),
+            regex::escape(&delimiters.open),
+            regex::escape(&delimiters.close),
+        ))
+        .expect("Escaped delimiters always form a valid regex");
+        let mut value = String::with_capacity(val.len());
+        let mut parts = Vec::new();
+        // Byte offset in `val` right after the previous interpolated variable or escape
+        let mut literal_start = 0;
+
+        for captures in interpolated_variable_regex.captures_iter(val) {
+            let whole = captures
+                .get(0)
+                .expect("Regex captures always include the whole match");
+            value.push_str(&val[literal_start..whole.start()]);
+            literal_start = whole.end();
+
+            let Some(name) = captures.get(1) else {
+                value.push_str(&whole.as_str().replace('\\', ""));
+                continue;
+            };
+            parts.push(parse_variable(name.as_str(), &whole, value.len())?);
         }
-        let parts = parts
-            .into_iter()
-            .map(|(variable, _)| variable)
-            .collect_vec();
-        Ok(Self { value, parts })
+        value.push_str(&val[literal_start..]);
+
+        Ok(Self {
+            value,
+            parts,
+            source: val.to_string(),
+        })
     }
 }
 
+/// Parse the content of an interpolation, such as `name`, `name.0`, `name | default: "value"` or
+/// `name | join: ","`, the whole `interpolation` locating the errors.
+///
+/// A `join` filter applies to the whole list, the name is then never read as indexed. Filters
+/// cannot be chained.
+fn parse_variable(
+    expression: &str,
+    interpolation: &regex::Match,
+    start: usize,
+) -> Result<InterpolatedVariable, ParseError> {
+    let (name, filter) = expression
+        .split_once('|')
+        .map_or((expression, None), |(name, filter)| (name, Some(filter)));
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(ParseError::EmptyInterpolation {
+            interpolation: interpolation.as_str().to_string(),
+            position: interpolation.start(),
+        });
+    }
+    let mut variable = InterpolatedVariable {
+        name: name.to_string(),
+        start,
+        default: None,
+        selector: None,
+    };
+    if let Some(filter) = filter.map(str::trim) {
+        if has_unquoted_pipe(filter) {
+            return Err(ParseError::ChainedFilters {
+                interpolation: interpolation.as_str().to_string(),
+                position: interpolation.start(),
+            });
+        }
+        if let Some(default) = filter.strip_prefix("default:") {
+            variable.default = Some(unquote(default.trim()).to_string());
+        } else if let Some(separator) = filter.strip_prefix("join:") {
+            variable.selector = Some(ListSelector::Join(unquote(separator.trim()).to_string()));
+            return Ok(variable);
+        } else {
+            return Err(ParseError::UnknownFilter {
+                filter: filter.to_string(),
+                interpolation: interpolation.as_str().to_string(),
+                position: interpolation.start(),
+            });
+        }
+    }
+    if let Some((list_name, index)) = name.rsplit_once('.')
+        && !list_name.is_empty()
+        && let Ok(index) = index.parse()
+    {
+        variable.name = list_name.to_string();
+        variable.selector = Some(ListSelector::Index(index));
+    }
+    Ok(variable)
+}
+
+/// Whether a filter is followed by another one, a `|` in a quoted argument being part of it.
+fn has_unquoted_pipe(filter: &str) -> bool {
+    let mut quote = None;
+    filter.chars().any(|c| match quote {
+        Some(open) => {
+            if c == open {
+                quote = None;
+            }
+            false
+        }
+        None if c == '"' || c == '\'' => {
+            quote = Some(c);
+            false
+        }
+        None => c == '|',
+    })
+}
+
+/// Filter argument without its surrounding quotes, if any.
+fn unquote(argument: &str) -> &str {
+    ['"', '\'']
+        .into_iter()
+        .find_map(|quote| {
+            argument
+                .strip_prefix(quote)
+                .and_then(|argument| argument.strip_suffix(quote))
+        })
+        .unwrap_or(argument)
+}
+
 impl InterpolatedString {
-    pub fn render(&self, param_context: &ParamContext) -> anyhow::Result<Cow<'_, str>> {
+    /// Render the string with the given params, the error telling the first variable without
+    /// value.
+    pub fn render(&self, param_context: &ParamContext) -> Result<Cow<'_, str>, RenderError> {
         if self.parts.is_empty() {
             Ok(Cow::Borrowed(&self.value))
         } else {
-            let mut rendered = self.value.clone();
-            let mut acc = 0;
+            let mut rendered = String::with_capacity(self.value.len());
+            // Byte offset in `self.value` of the literal text not rendered yet
+            let mut literal_start = 0;
             for part in &self.parts {
-                let value = param_context.get(&part.name).ok_or_else(|| {
-                    anyhow::anyhow!(
-                        "Could not find value for param {} during string interpolation",
-                        part.name
-                    )
+                rendered.push_str(&self.value[literal_start..part.start]);
+                let value = part.resolve(param_context).map_err(|kind| RenderError {
+                    kind,
+                    template: self.source.clone(),
                 })?;
-                rendered.insert_str(part.start + acc, value);
-                acc += value.len();
+                rendered.push_str(&value);
+                literal_start = part.start;
             }
+            rendered.push_str(&self.value[literal_start..]);
             Ok(Cow::Owned(rendered))
         }
     }
+
+    /// Render the string, showing the variables that cannot be resolved as `<name?>`.
+    #[must_use]
+    pub fn render_with_placeholders(&self, param_context: &ParamContext) -> String {
+        let mut rendered = String::with_capacity(self.value.len());
+        let mut literal_start = 0;
+        for part in &self.parts {
+            rendered.push_str(&self.value[literal_start..part.start]);
+            match part.resolve(param_context) {
+                Ok(value) => rendered.push_str(&value),
+                Err(_) => rendered.push_str(&format!("<{}?>", part.name)),
+            }
+            literal_start = part.start;
+        }
+        rendered.push_str(&self.value[literal_start..]);
+        rendered
+    }
+
+    /// Write the string back in the config syntax, escaping literal delimiters.
+    #[must_use]
+    pub fn template(&self) -> String {
+        self.template_with_delimiters(&Delimiters::default())
+    }
+
+    /// Write the string back in the config syntax using the given delimiters.
+    #[must_use]
+    pub fn template_with_delimiters(&self, delimiters: &Delimiters) -> String {
+        let Delimiters { open, close } = delimiters;
+        let escape = |literal: &str| {
+            literal
+                .replace(open, &escaped(open))
+                .replace(close, &escaped(close))
+        };
+        let mut template = String::with_capacity(self.value.len());
+        let mut literal_start = 0;
+        for part in &self.parts {
+            template.push_str(&escape(&self.value[literal_start..part.start]));
+            let mut expression = part.name.clone();
+            match &part.selector {
+                Some(ListSelector::Index(index)) => expression.push_str(&format!(".{index}")),
+                Some(ListSelector::Join(separator)) => {
+                    expression.push_str(&format!(" | join: \"{separator}\""));
+                }
+                None => {}
+            }
+            if let Some(default) = &part.default {
+                expression.push_str(&format!(" | default: \"{default}\""));
+            }
+            template.push_str(&format!("{open} {expression} {close}"));
+            literal_start = part.start;
+        }
+        template.push_str(&escape(&self.value[literal_start..]));
+        template
+    }
+
+    /// Names of the variables the string refers to, each listed once in order of appearance.
+    #[must_use]
+    pub fn variable_names(&self) -> Vec<&str> {
+        self.parts
+            .iter()
+            .map(|part| part.name.as_str())
+            .unique()
+            .collect()
+    }
+
+    /// Names of all the variables that cannot be resolved with the given params, as opposed to
+    /// the ones whose value has no item at their index.
+    #[must_use]
+    pub fn missing_variables(&self, param_context: &ParamContext) -> Vec<&str> {
+        self.parts
+            .iter()
+            .filter(|part| {
+                matches!(
+                    part.resolve(param_context),
+                    Err(RenderErrorKind::MissingParam(_)
+                        | RenderErrorKind::MissingEnvironmentVariable(_))
+                )
+            })
+            .map(|part| part.name.as_str())
+            .collect()
+    }
+}
+
+/// Prefix of variables resolved from the process environment instead of the params.
+pub(crate) const ENV_VARIABLE_PREFIX: &str = "env.";
+
+impl InterpolatedVariable {
+    /// Find the value of the variable, falling back to its default. Environment variables missing
+    /// from the process are looked up in the params, where the dotenv ones are set.
+    ///
+    /// The value is used as is, it is never interpolated itself.
+    fn resolve<'a>(
+        &'a self,
+        param_context: &'a ParamContext,
+    ) -> Result<Cow<'a, str>, RenderErrorKind> {
+        let env_name = self.name.strip_prefix(ENV_VARIABLE_PREFIX);
+        if let Some(env_name) = env_name
+            && let Ok(value) = std::env::var(env_name)
+        {
+            return Ok(Cow::Owned(
+                self.select(&ParamValue::Scalar(value))?.into_owned(),
+            ));
+        }
+        if let Some(value) = param_context.get(&self.name) {
+            return self.select(value);
+        }
+        match (&self.default, env_name) {
+            (Some(default), _) => Ok(Cow::Owned(
+                self.select(&ParamValue::Scalar(default.clone()))?
+                    .into_owned(),
+            )),
+            (None, Some(env_name)) => Err(RenderErrorKind::MissingEnvironmentVariable(
+                env_name.to_string(),
+            )),
+            (None, None) => Err(RenderErrorKind::MissingParam(self.name.clone())),
+        }
+    }
+
+    /// Render the items of `value` picked by the selector of the variable.
+    fn select<'v>(&self, value: &'v ParamValue) -> Result<Cow<'v, str>, RenderErrorKind> {
+        match (&self.selector, value) {
+            (None, ParamValue::Scalar(value)) => Ok(Cow::Borrowed(value)),
+            (None, ParamValue::List(items)) => Ok(Cow::Owned(items.join(" "))),
+            (Some(ListSelector::Index(index)), value) => value
+                .items()
+                .get(*index)
+                .map(|item| Cow::Borrowed(item.as_str()))
+                .ok_or_else(|| RenderErrorKind::IndexOutOfRange {
+                    name: self.name.clone(),
+                    index: *index,
+                    len: value.items().len(),
+                }),
+            (Some(ListSelector::Join(separator)), value) => {
+                Ok(Cow::Owned(value.items().join(separator)))
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -75,6 +416,7 @@ mod test {
     fn test_empty_string() {
         let input = "";
         let expected = InterpolatedString {
+            source: input.to_string(),
             value: String::new(),
             parts: vec![],
         };
@@ -85,10 +427,13 @@ mod test {
     fn test_single_variable() {
         let input = "{{name}}";
         let expected = InterpolatedString {
+            source: input.to_string(),
             value: String::new(),
             parts: vec![InterpolatedVariable {
                 name: "name".to_string(),
                 start: 0,
+                default: None,
+                selector: None,
             }],
         };
         assert_eq!(expected, InterpolatedString::from_str(input).unwrap());
@@ -98,15 +443,20 @@ mod test {
     fn test_multiple_variables() {
         let input = "{{name}} is {{age}} years old";
         let expected = InterpolatedString {
+            source: input.to_string(),
             value: " is  years old".to_string(),
             parts: vec![
                 InterpolatedVariable {
                     name: "name".to_string(),
                     start: 0,
+                    default: None,
+                    selector: None,
                 },
                 InterpolatedVariable {
                     name: "age".to_string(),
                     start: 4,
+                    default: None,
+                    selector: None,
                 },
             ],
         };
@@ -117,15 +467,20 @@ mod test {
     fn test_multiple_variables_with_inner_spaces() {
         let input = "{{ name }} is {{ age  }} years old";
         let expected = InterpolatedString {
+            source: input.to_string(),
             value: " is  years old".to_string(),
             parts: vec![
                 InterpolatedVariable {
                     name: "name".to_string(),
                     start: 0,
+                    default: None,
+                    selector: None,
                 },
                 InterpolatedVariable {
                     name: "age".to_string(),
                     start: 4,
+                    default: None,
+                    selector: None,
                 },
             ],
         };
@@ -136,6 +491,7 @@ mod test {
     fn test_no_variables() {
         let input = "Hello, world!";
         let expected = InterpolatedString {
+            source: input.to_string(),
             value: "Hello, world!".to_string(),
             parts: vec![],
         };
@@ -146,10 +502,13 @@ mod test {
     fn test_single_variable_with_whitespace() {
         let input = " {{name}} ";
         let expected = InterpolatedString {
+            source: input.to_string(),
             value: "  ".to_string(),
             parts: vec![InterpolatedVariable {
                 name: "name".to_string(),
                 start: 1,
+                default: None,
+                selector: None,
             }],
         };
         assert_eq!(expected, InterpolatedString::from_str(input).unwrap());
@@ -158,6 +517,7 @@ mod test {
     #[test]
     fn test_render_empty_string() {
         let interpolated = InterpolatedString {
+            source: String::new(),
             value: String::new(),
             parts: vec![],
         };
@@ -168,6 +528,7 @@ mod test {
     #[test]
     fn test_render_no_variables() {
         let interpolated = InterpolatedString {
+            source: "Hello, world!".to_string(),
             value: "Hello, world!".to_string(),
             parts: vec![],
         };
@@ -178,10 +539,13 @@ mod test {
     #[test]
     fn test_render_single_variable() {
         let interpolated = InterpolatedString {
+            source: "Hello, {{name}}!".to_string(),
             value: "Hello, !".to_string(),
             parts: vec![InterpolatedVariable {
                 name: "name".to_string(),
                 start: 7,
+                default: None,
+                selector: None,
             }],
         };
         let mut context = ParamContext::default();
@@ -192,15 +556,20 @@ mod test {
     #[test]
     fn test_render_multiple_variables() {
         let interpolated = InterpolatedString {
+            source: "{{name}} is {{age}} years old".to_string(),
             value: " is  years old".to_string(),
             parts: vec![
                 InterpolatedVariable {
                     name: "name".to_string(),
                     start: 0,
+                    default: None,
+                    selector: None,
                 },
                 InterpolatedVariable {
                     name: "age".to_string(),
                     start: 4,
+                    default: None,
+                    selector: None,
                 },
             ],
         };
@@ -216,13 +585,402 @@ mod test {
     #[test]
     fn test_render_missing_variable() {
         let interpolated = InterpolatedString {
+            source: "Hello, {{name}}!".to_string(),
             value: "Hello, !".to_string(),
             parts: vec![InterpolatedVariable {
                 name: "name".to_string(),
                 start: 7,
+                default: None,
+                selector: None,
             }],
         };
-        let context = ParamContext::default();
-        assert!(interpolated.render(&context).is_err());
+        let error = interpolated.render(&ParamContext::default()).unwrap_err();
+        assert_eq!(
+            error.kind,
+            RenderErrorKind::MissingParam("name".to_string())
+        );
+        assert_eq!(
+            error.to_string(),
+            "Could not find value for param 'name' while rendering 'Hello, {{name}}!'"
+        );
+    }
+
+    #[test]
+    fn test_render_adjacent_variables() {
+        let mut context = ParamContext::default();
+        context.set("first", "1st");
+        context.set("second", "2nd");
+        context.set("third", "");
+        for (template, expected) in [
+            ("{{first}}{{second}}", "1st2nd"),
+            ("{{second}}{{first}}", "2nd1st"),
+            ("{{ first }}{{ third }}{{ second }}", "1st2nd"),
+            ("{{first}}{{second}} at start", "1st2nd at start"),
+            ("at end {{first}}{{second}}", "at end 1st2nd"),
+            ("{{first}}-{{second}}", "1st-2nd"),
+            ("{{first}}{{first}}", "1st1st"),
+        ] {
+            let interpolated = InterpolatedString::from_str(template).unwrap();
+            assert_eq!(interpolated.render(&context).unwrap(), expected);
+            assert_eq!(interpolated.render_with_placeholders(&context), expected);
+        }
+    }
+
+    #[test]
+    fn test_adjacent_variables_share_position() {
+        let interpolated = InterpolatedString::from_str("a{{first}}{{second}}").unwrap();
+        assert_eq!(interpolated.value, "a");
+        assert_eq!(
+            interpolated
+                .parts
+                .iter()
+                .map(|part| (part.name.as_str(), part.start))
+                .collect::<Vec<_>>(),
+            [("first", 1), ("second", 1)]
+        );
+        assert_eq!(interpolated.template(), "a{{ first }}{{ second }}");
+    }
+
+    #[test]
+    fn test_multibyte_prefix() {
+        let input = "café {{name}}!";
+        let expected = InterpolatedString {
+            source: input.to_string(),
+            value: "café !".to_string(),
+            parts: vec![InterpolatedVariable {
+                name: "name".to_string(),
+                start: 6,
+                default: None,
+                selector: None,
+            }],
+        };
+        assert_eq!(expected, InterpolatedString::from_str(input).unwrap());
+    }
+
+    #[test]
+    fn test_render_multibyte_prefix() {
+        let interpolated = InterpolatedString::from_str("café {{name}}!").unwrap();
+        let mut context = ParamContext::default();
+        context.set("name", "john");
+        assert_eq!(interpolated.render(&context).unwrap(), "café john!");
+    }
+
+    #[test]
+    fn test_render_multibyte_values_and_separators() {
+        let interpolated = InterpolatedString::from_str("🎉 {{first}} → {{second}} ✅").unwrap();
+        let mut context = ParamContext::default();
+        context.set("first", "été");
+        context.set("second", "日本");
+        assert_eq!(interpolated.render(&context).unwrap(), "🎉 été → 日本 ✅");
+    }
+
+    #[test]
+    fn test_escaped_delimiters() {
+        let input = r"echo \{\{ not a var \}\}";
+        let expected = InterpolatedString {
+            source: input.to_string(),
+            value: "echo {{ not a var }}".to_string(),
+            parts: vec![],
+        };
+        assert_eq!(expected, InterpolatedString::from_str(input).unwrap());
+    }
+
+    #[test]
+    fn test_render_escaped_delimiters() {
+        let interpolated = InterpolatedString::from_str(r"echo \{\{ not a var \}\}").unwrap();
+        assert_eq!(
+            interpolated.render(&ParamContext::default()).unwrap(),
+            "echo {{ not a var }}"
+        );
+    }
+
+    #[test]
+    fn test_render_escaped_delimiters_around_variable() {
+        let interpolated = InterpolatedString::from_str(r"\{\{{{ name }}\}\}").unwrap();
+        let mut context = ParamContext::default();
+        context.set("name", "john");
+        assert_eq!(interpolated.render(&context).unwrap(), "{{john}}");
+    }
+
+    #[test]
+    fn test_variable_with_default() {
+        let input = r#"hello {{ name | default: "world" }}"#;
+        let expected = InterpolatedString {
+            source: input.to_string(),
+            value: "hello ".to_string(),
+            parts: vec![InterpolatedVariable {
+                name: "name".to_string(),
+                start: 6,
+                default: Some("world".to_string()),
+                selector: None,
+            }],
+        };
+        assert_eq!(expected, InterpolatedString::from_str(input).unwrap());
+    }
+
+    #[test]
+    fn test_variable_with_unquoted_default() {
+        let input = "{{name|default:8080}}";
+        let expected = InterpolatedString {
+            source: input.to_string(),
+            value: String::new(),
+            parts: vec![InterpolatedVariable {
+                name: "name".to_string(),
+                start: 0,
+                default: Some("8080".to_string()),
+                selector: None,
+            }],
+        };
+        assert_eq!(expected, InterpolatedString::from_str(input).unwrap());
+    }
+
+    #[test]
+    fn test_variable_with_unknown_filter() {
+        assert_eq!(
+            InterpolatedString::from_str("echo {{ name | upper }}")
+                .unwrap_err()
+                .to_string(),
+            "Unknown interpolation filter 'upper' in '{{ name | upper }}' at byte 5"
+        );
+    }
+
+    #[test]
+    fn test_variable_without_name() {
+        assert_eq!(
+            InterpolatedString::from_str("echo {{ name }} {{ }}")
+                .unwrap_err()
+                .to_string(),
+            "Missing variable name in '{{ }}' at byte 16"
+        );
+    }
+
+    #[test]
+    fn test_render_default_when_missing() {
+        let interpolated =
+            InterpolatedString::from_str(r#"hello {{ name | default: "world" }}"#).unwrap();
+        assert_eq!(
+            interpolated.render(&ParamContext::default()).unwrap(),
+            "hello world"
+        );
+    }
+
+    #[test]
+    fn test_render_value_over_default() {
+        let interpolated =
+            InterpolatedString::from_str(r#"hello {{ name | default: "world" }}"#).unwrap();
+        let mut context = ParamContext::default();
+        context.set("name", "john");
+        assert_eq!(interpolated.render(&context).unwrap(), "hello john");
+    }
+
+    fn list_context() -> ParamContext {
+        let mut context = ParamContext::default();
+        context.set_value("items", vec!["a".to_string(), "b".to_string()]);
+        context.set("name", "john");
+        context
+    }
+
+    #[test]
+    fn test_list_index() {
+        let interpolated = InterpolatedString::from_str("{{ items.1 }} {{ name.0 }}").unwrap();
+        assert_eq!(interpolated.parts[0].name, "items");
+        assert_eq!(interpolated.parts[0].selector, Some(ListSelector::Index(1)));
+        assert_eq!(interpolated.render(&list_context()).unwrap(), "b john");
+    }
+
+    #[test]
+    fn test_list_index_out_of_range() {
+        let interpolated = InterpolatedString::from_str("{{ items.2 }}").unwrap();
+        let context = list_context();
+        assert_eq!(
+            interpolated.render(&context),
+            Err(RenderError {
+                kind: RenderErrorKind::IndexOutOfRange {
+                    name: "items".to_string(),
+                    index: 2,
+                    len: 2,
+                },
+                template: "{{ items.2 }}".to_string(),
+            })
+        );
+        // The param has a value, it is not reported as missing
+        assert!(interpolated.missing_variables(&context).is_empty());
+    }
+
+    #[test]
+    fn test_list_join() {
+        let interpolated = InterpolatedString::from_str(
+            "{{ items | join: ',' }} {{ items }} {{ name | join:'-' }}",
+        )
+        .unwrap();
+        assert_eq!(
+            interpolated.render(&list_context()).unwrap(),
+            "a,b a b john"
+        );
+    }
+
+    #[test]
+    fn test_env_variable_name_is_preserved() {
+        let interpolated = InterpolatedString::from_str("{{ env.HOME }}").unwrap();
+        assert_eq!(interpolated.parts[0].name, "env.HOME");
+    }
+
+    #[test]
+    fn test_render_env_variable() {
+        let interpolated = InterpolatedString::from_str("path: {{ env.PATH }}").unwrap();
+        assert_eq!(
+            interpolated.render(&ParamContext::default()).unwrap(),
+            format!("path: {}", std::env::var("PATH").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_render_env_variable_over_param() {
+        let interpolated = InterpolatedString::from_str("{{ env.PATH }}").unwrap();
+        let mut context = ParamContext::default();
+        context.set("env.PATH", "param");
+        assert_ne!(interpolated.render(&context).unwrap(), "param");
+    }
+
+    #[test]
+    fn test_render_missing_env_variable() {
+        let interpolated =
+            InterpolatedString::from_str("{{ env.TASKERIE_TEST_UNDEFINED_VARIABLE }}").unwrap();
+        assert_eq!(
+            interpolated
+                .render(&ParamContext::default())
+                .unwrap_err()
+                .to_string(),
+            "Could not find environment variable 'TASKERIE_TEST_UNDEFINED_VARIABLE' while rendering \
+             '{{ env.TASKERIE_TEST_UNDEFINED_VARIABLE }}'"
+        );
+    }
+
+    #[test]
+    fn test_render_env_variable_is_not_interpolated() {
+        let interpolated = InterpolatedString::from_str(
+            "{{ env.PATH }} {{ env.TASKERIE_TEST_TEMPLATE_VARIABLE }}",
+        )
+        .unwrap();
+        let mut context = ParamContext::default();
+        context.set("name", "john");
+        // Such as a dotenv variable missing from the process environment
+        context.set("env.TASKERIE_TEST_TEMPLATE_VARIABLE", "{{ name }}");
+        assert_eq!(
+            interpolated.render(&context).unwrap(),
+            format!("{} {{{{ name }}}}", std::env::var("PATH").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_missing_variables() {
+        let interpolated =
+            InterpolatedString::from_str(r#"{{ a }} {{ b }} {{ c | default: "c" }} {{ d }}"#)
+                .unwrap();
+        let mut context = ParamContext::default();
+        context.set("b", "b");
+        assert_eq!(interpolated.missing_variables(&context), vec!["a", "d"]);
+    }
+
+    #[test]
+    fn test_no_missing_variables() {
+        let interpolated = InterpolatedString::from_str("{{ a }}").unwrap();
+        let mut context = ParamContext::default();
+        context.set("a", "a");
+        assert!(interpolated.missing_variables(&context).is_empty());
+    }
+
+    #[test]
+    fn test_template() {
+        for template in [
+            "",
+            "no variables",
+            "{{ env }}-build",
+            r#"hello {{ name | default: "world" }}, {{ greeting }}!"#,
+            r"\{\{ literal \}\} {{ name }}",
+            r#"{{ items.1 }} {{ items | join: ", " }} {{ items.0 | default: "none" }}"#,
+        ] {
+            assert_eq!(
+                InterpolatedString::from_str(template).unwrap().template(),
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn test_alternate_delimiters() {
+        let delimiters = Delimiters {
+            open: "<<".to_string(),
+            close: ">>".to_string(),
+        };
+        let interpolated = InterpolatedString::parse_with_delimiters(
+            r#"echo {{ .Name }} << name >> << tag | default: "latest" >> \<\< literal \>\>"#,
+            &delimiters,
+        )
+        .unwrap();
+        let mut context = ParamContext::default();
+        context.set("name", "app");
+        assert_eq!(
+            interpolated.render(&context).unwrap(),
+            "echo {{ .Name }} app latest << literal >>"
+        );
+        assert_eq!(
+            interpolated.template_with_delimiters(&delimiters),
+            r#"echo {{ .Name }} << name >> << tag | default: "latest" >> \<\< literal \>\>"#
+        );
+    }
+
+    #[test]
+    fn test_parse_error_kinds() {
+        assert_eq!(
+            InterpolatedString::from_str("echo {{ name }} {{ }}").unwrap_err(),
+            ParseError::EmptyInterpolation {
+                interpolation: "{{ }}".to_string(),
+                position: 16,
+            }
+        );
+        assert_eq!(
+            "echo {{ name | upper }}"
+                .parse::<crate::model::action::Command>()
+                .unwrap_err(),
+            ParseError::UnknownFilter {
+                filter: "upper".to_string(),
+                interpolation: "{{ name | upper }}".to_string(),
+                position: 5,
+            }
+        );
+    }
+
+    #[test]
+    fn test_chained_filters() {
+        for (template, interpolation) in [
+            (
+                r#"[{{ xs | join: "," | default: "none" }}]"#,
+                r#"{{ xs | join: "," | default: "none" }}"#,
+            ),
+            (
+                r#"[{{ xs | default: "none" | join: "," }}]"#,
+                r#"{{ xs | default: "none" | join: "," }}"#,
+            ),
+        ] {
+            assert_eq!(
+                InterpolatedString::from_str(template).unwrap_err(),
+                ParseError::ChainedFilters {
+                    interpolation: interpolation.to_string(),
+                    position: 1,
+                }
+            );
+        }
+
+        // A quoted `|` is an argument
+        let interpolated = InterpolatedString::from_str(r#"{{ items | join: "|" }}"#).unwrap();
+        assert_eq!(interpolated.render(&list_context()).unwrap(), "a|b");
+    }
+
+    #[test]
+    fn test_variable_names() {
+        let string: InterpolatedString =
+            "{{ b }} {{ a.0 }} {{ b }} {{ env.HOME }}".parse().unwrap();
+        assert_eq!(string.variable_names(), ["b", "a", "env.HOME"]);
     }
 }
@@ -0,0 +1,94 @@
+use std::sync::{Arc, Mutex, PoisonError, atomic::Ordering};
+
+use subprocess::Popen;
+
+use crate::model::Interrupter;
+
+impl Interrupter {
+    /// Stop the run: running commands are terminated and no other action starts. Interrupting
+    /// again kills the commands instead.
+    pub fn interrupt(&self) {
+        let interruptions = self.state.interruptions.fetch_add(1, Ordering::SeqCst) + 1;
+        for process in self
+            .state
+            .processes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            stop(process, interruptions);
+        }
+    }
+
+    #[must_use]
+    pub fn is_interrupted(&self) -> bool {
+        self.state.interruptions.load(Ordering::SeqCst) > 0
+    }
+
+    /// Forget the previous interruptions, to use the interrupter for another run.
+    pub fn reset(&self) {
+        self.state.interruptions.store(0, Ordering::SeqCst);
+    }
+
+    /// Track a running command until the returned guard is dropped, stopping it right away if
+    /// the run is already interrupted.
+    pub(crate) fn register(&self, process: &Arc<Mutex<Popen>>) -> RegisteredProcess<'_> {
+        self.state
+            .processes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(process.clone());
+        let interruptions = self.state.interruptions.load(Ordering::SeqCst);
+        if interruptions > 0 {
+            stop(process, interruptions);
+        }
+        RegisteredProcess {
+            interrupter: self,
+            process: process.clone(),
+        }
+    }
+}
+
+/// Terminate a process, or kill it after several interruptions.
+fn stop(process: &Mutex<Popen>, interruptions: usize) {
+    let mut process = process.lock().unwrap_or_else(PoisonError::into_inner);
+    // An error means the process is already gone
+    let _ = if interruptions > 1 {
+        process.kill()
+    } else {
+        process.terminate()
+    };
+}
+
+pub(crate) struct RegisteredProcess<'a> {
+    interrupter: &'a Interrupter,
+    process: Arc<Mutex<Popen>>,
+}
+
+impl Drop for RegisteredProcess<'_> {
+    fn drop(&mut self) {
+        self.interrupter
+            .state
+            .processes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|process| !Arc::ptr_eq(process, &self.process));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_interrupt_and_reset() {
+        let interrupter = Interrupter::default();
+        assert!(!interrupter.is_interrupted());
+
+        interrupter.clone().interrupt();
+        assert!(interrupter.is_interrupted());
+
+        interrupter.reset();
+        assert!(!interrupter.is_interrupted());
+    }
+}
@@ -1,20 +1,46 @@
 use std::{
-    io::{BufRead, BufReader},
-    path::PathBuf,
-    sync::mpsc,
+    borrow::Cow,
+    collections::{HashMap, hash_map::Entry},
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, OnceLock, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
 };
 
-use anyhow::{anyhow, bail};
-use subprocess::{Exec, ExitStatus, Redirection};
+use indexmap::IndexMap;
+use itertools::Itertools;
+use subprocess::{Exec, ExitStatus, PopenError, Redirection};
 
 use crate::{
     message::ExecutionMessage,
-    model::{self, InterpolatedString, ParamContext, TaskerieContext},
+    model::{
+        self, CommandReport, Delimiters, ExecutionOptions, InterpolatedString, Interrupter,
+        ParamContext, ParamInfo, ParamValue, RunError, TaskInfo, TaskOutcome, TaskRunReport,
+        TaskerieContext, action::Sequencing,
+    },
 };
 
+use self::{cache::CacheLookup, persistent_shell::PersistentShell};
+
 pub mod action;
+pub mod builder;
+pub mod cache;
+pub mod dotenv;
 pub mod interpolated_string;
+pub mod interrupter;
+mod persistent_shell;
+pub mod plan;
+#[cfg(feature = "async")]
+pub mod run_async;
+pub mod suggestion;
 pub mod task_parser;
+pub mod validation;
 
 impl TaskerieContext {
     #[must_use]
@@ -26,8 +52,110 @@ impl TaskerieContext {
             .collect()
     }
 
+    /// Names and descriptions of the tasks that can run without additional parameters.
+    #[must_use]
+    pub fn get_all_standalone_tasks(&self) -> Vec<(&str, Option<&str>)> {
+        self.tasks
+            .iter()
+            .filter(|task| task.1.is_standalone())
+            .map(|task| (task.0.as_str(), task.1.description.as_deref()))
+            .collect()
+    }
+
+    /// Run a task to completion and gather what happened, for callers not interested in the
+    /// messages as they come. Parameters without value make the task fail.
+    pub fn run_task_collect<S: AsRef<str>>(
+        &self,
+        name: S,
+        mut param_context: ParamContext,
+    ) -> Result<TaskRunReport, RunError> {
+        let (tx, rx) = mpsc::channel();
+        let outcome = self.run_task_by_name(name, &mut param_context, &tx)?;
+        drop(tx);
+
+        let mut report = TaskRunReport {
+            outcome,
+            commands: Vec::new(),
+            output: String::new(),
+        };
+        // Index in `report.commands` of the last command of every parallel action
+        let mut running_commands = HashMap::new();
+        for message in rx {
+            let (action_path, message) = message.into_action_message();
+            match message {
+                ExecutionMessage::AboutToRunCommand {
+                    command,
+                    working_directory,
+                    ..
+                } => {
+                    running_commands.insert(action_path, report.commands.len());
+                    report.commands.push(CommandReport {
+                        command,
+                        working_directory,
+                        exit_code: None,
+                    });
+                }
+                ExecutionMessage::CommandOutput { output }
+                | ExecutionMessage::CommandError { output } => {
+                    report.output.push_str(&output);
+                    report.output.push('\n');
+                }
+                ExecutionMessage::CommandSucceeded => {
+                    if let Some(index) = running_commands.get(&action_path) {
+                        report.commands[*index].exit_code = Some(0);
+                    }
+                }
+                ExecutionMessage::CommandFailed { exit_code } => {
+                    if let Some(index) = running_commands.get(&action_path) {
+                        report.commands[*index].exit_code = exit_code;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(report)
+    }
+
+    /// Describe every task, in definition order.
+    #[must_use]
+    pub fn list_tasks(&self) -> Vec<TaskInfo> {
+        self.tasks
+            .iter()
+            .map(|(name, task)| TaskInfo {
+                name: name.clone(),
+                description: task.description.clone(),
+                standalone: task.is_standalone(),
+                params: task
+                    .params
+                    .iter()
+                    .map(|(name, param)| ParamInfo {
+                        name: name.clone(),
+                        default: param
+                            .default
+                            .as_ref()
+                            .map(|default| default.template_with_delimiters(&self.delimiters)),
+                        required: param.is_required(),
+                        param_type: param.param_type.clone(),
+                    })
+                    .collect(),
+                tags: task.tags.clone(),
+            })
+            .collect()
+    }
+
+    /// Tasks bearing `tag`, in definition order.
+    pub fn tasks_with_tag<'a>(
+        &'a self,
+        tag: &'a str,
+    ) -> impl Iterator<Item = (&'a str, &'a model::Task)> {
+        self.tasks
+            .iter()
+            .filter(move |(_, task)| task.tags.iter().any(|task_tag| task_tag == tag))
+            .map(|(name, task)| (name.as_str(), task))
+    }
+
     #[must_use]
-    fn get_task_by_name<S: AsRef<str>>(&self, name: S) -> Option<&model::Task> {
+    pub fn get_task_by_name<S: AsRef<str>>(&self, name: S) -> Option<&model::Task> {
         self.tasks.get(name.as_ref())
     }
 
@@ -36,139 +164,3923 @@ impl TaskerieContext {
         name: S,
         param_context: &mut ParamContext,
         execution_message_sender: &mpsc::Sender<ExecutionMessage>,
-    ) -> anyhow::Result<ExitStatus> {
-        if let Some(task) = self.get_task_by_name(name) {
-            self.run_task(task, param_context, execution_message_sender)
-        } else {
-            bail!("Task not found");
-        }
+    ) -> Result<TaskOutcome, RunError> {
+        self.run_task_by_name_with_hook(
+            name,
+            param_context,
+            execution_message_sender,
+            |_, _| Ok(()),
+        )
+    }
+
+    /// Run a task, calling `pre_run_hook` with the name and parameters of the task and every
+    /// sub-task right before they run. Returning `Err(reason)` from the hook vetoes the task.
+    pub fn run_task_by_name_with_hook<S, H>(
+        &self,
+        name: S,
+        param_context: &mut ParamContext,
+        execution_message_sender: &mpsc::Sender<ExecutionMessage>,
+        pre_run_hook: H,
+    ) -> Result<TaskOutcome, RunError>
+    where
+        S: AsRef<str>,
+        H: FnMut(&str, &ParamContext) -> Result<(), String> + Send,
+    {
+        self.run_task_by_name_with_options(
+            name,
+            param_context,
+            execution_message_sender,
+            ExecutionOptions::default(),
+            pre_run_hook,
+        )
+    }
+
+    pub fn run_task_by_name_with_options<S, H>(
+        &self,
+        name: S,
+        param_context: &mut ParamContext,
+        execution_message_sender: &mpsc::Sender<ExecutionMessage>,
+        options: ExecutionOptions,
+        pre_run_hook: H,
+    ) -> Result<TaskOutcome, RunError>
+    where
+        S: AsRef<str>,
+        H: FnMut(&str, &ParamContext) -> Result<(), String> + Send,
+    {
+        self.run_task_with_options(
+            name.as_ref(),
+            param_context,
+            Some(execution_message_sender),
+            options,
+            pre_run_hook,
+        )
+    }
+
+    /// Run a task, sending its messages only when a sender is given. Without one, nothing has to
+    /// be drained and only the outcome tells how the run went.
+    pub fn run_task_by_name_with_sender<S: AsRef<str>>(
+        &self,
+        name: S,
+        param_context: &mut ParamContext,
+        execution_message_sender: Option<&mpsc::Sender<ExecutionMessage>>,
+    ) -> Result<TaskOutcome, RunError> {
+        self.run_task_with_options(
+            name.as_ref(),
+            param_context,
+            execution_message_sender,
+            ExecutionOptions::default(),
+            |_, _| Ok(()),
+        )
+    }
+
+    fn run_task_with_options<H>(
+        &self,
+        name: &str,
+        param_context: &mut ParamContext,
+        execution_message_sender: Option<&mpsc::Sender<ExecutionMessage>>,
+        options: ExecutionOptions,
+        pre_run_hook: H,
+    ) -> Result<TaskOutcome, RunError>
+    where
+        H: FnMut(&str, &ParamContext) -> Result<(), String> + Send,
+    {
+        let parameter_values = options.parameter_values.map(Mutex::new);
+        let confirmations = options.confirmations.map(Mutex::new);
+        let pre_run_hook = Mutex::new(pre_run_hook);
+        let dependencies = Mutex::default();
+        let secrets = Mutex::default();
+        let shell = options.shell.or_else(shell_variable);
+        let mut execution = Execution {
+            sender: MessageSender {
+                sender: execution_message_sender.map(|sender| sender as &dyn MessageSink),
+                scopes: Vec::new(),
+                secrets: &secrets,
+            },
+            dry_run: options.dry_run,
+            parameter_values: parameter_values.as_ref(),
+            confirmations: confirmations.as_ref(),
+            assume_yes: options.assume_yes,
+            pre_run_hook: &pre_run_hook,
+            call_stack: Vec::new(),
+            dependencies: &dependencies,
+            interrupter: options.interrupter,
+            shell: shell.as_deref(),
+        };
+        let Some(task) = self.get_task_by_name(name) else {
+            return Err(self.task_not_found(name));
+        };
+        let outcome = self.run_task(name, task, param_context, &mut execution);
+        execution.finish(outcome)
     }
 
+    /// Run a task between [`ExecutionMessage::TaskStarted`] and
+    /// [`ExecutionMessage::TaskFinished`] messages, the latter not being sent on error.
     fn run_task(
         &self,
+        name: &str,
         task: &model::task::Task,
         param_context: &mut ParamContext,
-        execution_message_sender: &mpsc::Sender<ExecutionMessage>,
-    ) -> anyhow::Result<ExitStatus> {
-        for (name, param) in &task.params {
-            if param_context.has(name) {
+        execution: &mut Execution,
+    ) -> Result<TaskOutcome, RunError> {
+        if execution.call_stack.iter().any(|called| called == name) {
+            return Err(cyclic_call(&execution.call_stack, name));
+        }
+
+        execution.sender.send(ExecutionMessage::TaskStarted {
+            name: name.to_string(),
+        })?;
+        let outcome = self.run_started_task(name, task, param_context, execution)?;
+        execution.sender.send(ExecutionMessage::TaskFinished {
+            name: name.to_string(),
+            success: outcome.success(),
+        })?;
+        Ok(outcome)
+    }
+
+    fn run_started_task(
+        &self,
+        name: &str,
+        task: &model::task::Task,
+        param_context: &mut ParamContext,
+        execution: &mut Execution,
+    ) -> Result<TaskOutcome, RunError> {
+        self.set_context_values(task, param_context);
+        let mut missing_params = Vec::new();
+        for (param_name, param) in &task.params {
+            let ask = missing_params.is_empty();
+            match self.resolve_param(param_name, param, param_context, execution, ask)? {
+                ParamResolution::Resolved => {}
+                ParamResolution::Source(source) => {
+                    let value = read_param_source(
+                        &self.base_dir,
+                        param_name,
+                        source,
+                        param_context,
+                        execution.shell,
+                    )?;
+                    param_context.set(param_name, &value);
+                }
+                ParamResolution::Missing => missing_params.push(param_name.as_str()),
+            }
+            execution
+                .sender
+                .add_param_secret(param, param_context.get(param_name));
+        }
+        if let Some(outcome) =
+            self.check_preconditions(name, task, param_context, &missing_params, execution)?
+        {
+            return Ok(outcome);
+        }
+        let fingerprint = match self.lookup_cache(name, task, param_context, execution)? {
+            CacheLookup::Hit => return Ok(TaskOutcome::Completed(ExitStatus::Exited(0))),
+            CacheLookup::Miss(fingerprint) => Some(fingerprint),
+            CacheLookup::Uncached => None,
+        };
+
+        execution.call_stack.push(name.to_string());
+        let outcome = match self.run_dependencies(task, param_context, execution) {
+            Ok(Some(failed_outcome)) => Ok(failed_outcome),
+            Ok(None) if task.parallel => {
+                self.run_actions_in_parallel(task, param_context, execution)
+            }
+            Ok(None) => self.run_actions(task, param_context, execution),
+            Err(error) => Err(error),
+        };
+        execution.call_stack.pop();
+
+        if let (Some(fingerprint), Ok(outcome)) = (&fingerprint, &outcome) {
+            self.store_cache_result(name, fingerprint, outcome, execution);
+        }
+        outcome
+    }
+
+    /// Make the vars and the dotenv variables available to a task about to run. Vars are also
+    /// available to the tasks that do not declare them as params, the declared ones only fall back
+    /// to them after their default.
+    fn set_context_values(&self, task: &model::task::Task, param_context: &mut ParamContext) {
+        for (name, value) in &self.vars {
+            if !task.params.contains_key(name) && !param_context.has(name) {
+                param_context.set(name, value);
+            }
+        }
+        for (name, value) in &self.dotenv {
+            let name = format!("{}{name}", interpolated_string::ENV_VARIABLE_PREFIX);
+            if !param_context.has(&name) {
+                param_context.set(&name, value);
+            }
+        }
+    }
+
+    /// Give a param without value the one of its default or var, or else ask the caller for it
+    /// if `ask` is set. Reading its source is left to the caller.
+    fn resolve_param<'t>(
+        &self,
+        param_name: &str,
+        param: &'t model::task::Param,
+        param_context: &mut ParamContext,
+        execution: &Execution,
+        ask: bool,
+    ) -> Result<ParamResolution<'t>, RunError> {
+        if param_context.has(param_name) {
+            // Given by the caller or the task call
+        } else if let Some(default_value) = &param.default {
+            let default_value =
+                default_value
+                    .render(param_context)
+                    .map_err(|source| RunError::ParamDefault {
+                        parameter: param_name.to_string(),
+                        source,
+                    })?;
+            param_context.set(param_name, &default_value);
+        } else if let Some(source) = &param.source {
+            return Ok(ParamResolution::Source(source));
+        } else if let Some(value) = self.vars.get(param_name) {
+            param_context.set(param_name, value);
+        } else if ask && let Some(value) = execution.request_parameter_value(param_name, param)? {
+            param_context.set(param_name, &value);
+        } else {
+            return Ok(ParamResolution::Missing);
+        }
+        Ok(ParamResolution::Resolved)
+    }
+
+    /// Check that a task can run once its params are resolved: none is missing or invalid, it is
+    /// confirmed and the pre-run hook does not veto it. Returns the outcome of the task otherwise.
+    fn check_preconditions(
+        &self,
+        name: &str,
+        task: &model::task::Task,
+        param_context: &ParamContext,
+        missing_params: &[&str],
+        execution: &Execution,
+    ) -> Result<Option<TaskOutcome>, RunError> {
+        if !missing_params.is_empty() {
+            for param_name in missing_params {
+                execution
+                    .sender
+                    .send(ExecutionMessage::MissingRequiredTaskParameter {
+                        parameter_name: (*param_name).to_string(),
+                    })?;
+            }
+            return Ok(Some(TaskOutcome::PreconditionFailed(format!(
+                "Task '{name}' is missing required parameters: {}",
+                missing_params.iter().join(", ")
+            ))));
+        }
+
+        let mut invalid_params = Vec::new();
+        for (param_name, param) in &task.params {
+            let Some(value) = param_context.get(param_name) else {
                 continue;
+            };
+            // Every item of a list has to be valid
+            if let Err(reason) = value
+                .items()
+                .iter()
+                .try_for_each(|item| param.param_type.validate(item))
+            {
+                execution
+                    .sender
+                    .send(ExecutionMessage::InvalidParameterValue {
+                        parameter_name: param_name.clone(),
+                        reason,
+                    })?;
+                invalid_params.push(param_name);
             }
-            if let Some(default_value) = &param.default {
-                param_context.set(name, default_value);
-            } else {
-                execution_message_sender.send(ExecutionMessage::MissingRequiredTaskParameter {
-                    parameter_name: name.clone(),
+        }
+        if !invalid_params.is_empty() {
+            return Ok(Some(TaskOutcome::PreconditionFailed(format!(
+                "Task '{name}' has invalid parameter values: {}",
+                invalid_params.iter().join(", ")
+            ))));
+        }
+        for (param_name, _) in &task.params {
+            if let Some(value) = param_context.get(param_name) {
+                execution.sender.send(ExecutionMessage::ParameterResolved {
+                    parameter_name: param_name.clone(),
+                    value: value.to_string(),
                 })?;
-                return Ok(ExitStatus::Undetermined);
             }
         }
 
+        let hook_result = execution.confirm(name, task)?.and_then(|()| {
+            (execution
+                .pre_run_hook
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner))(name, param_context)
+        });
+        if let Err(reason) = hook_result {
+            execution.sender.send(ExecutionMessage::TaskVetoed {
+                task_name: name.to_string(),
+                reason: reason.clone(),
+            })?;
+            return Ok(Some(TaskOutcome::PreconditionFailed(format!(
+                "Task '{name}' was vetoed: {reason}"
+            ))));
+        }
+        Ok(None)
+    }
+
+    /// Run the tasks needed by `task`, their own dependencies first, skipping the ones that
+    /// already ran with the same params during this execution.
+    ///
+    /// Returns the outcome of the first dependency that failed.
+    fn run_dependencies(
+        &self,
+        task: &model::task::Task,
+        param_context: &ParamContext,
+        execution: &mut Execution,
+    ) -> Result<Option<TaskOutcome>, RunError> {
+        for task_call in &task.needs {
+            let name = &task_call.name;
+            if execution.call_stack.contains(name) {
+                return Err(cyclic_call(&execution.call_stack, name));
+            }
+            let dependency = self
+                .get_task_by_name(name)
+                .ok_or_else(|| self.task_not_found(name))?;
+            let mut dependency_param_context = render_task_call_params(task_call, param_context)?;
+
+            let mut params = dependency_param_context
+                .params
+                .clone()
+                .into_iter()
+                .collect_vec();
+            params.sort();
+            let (outcome, already_run) = match execution
+                .dependencies
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry((name.clone(), params))
+            {
+                Entry::Occupied(entry) => (entry.get().clone(), true),
+                Entry::Vacant(entry) => (entry.insert(Arc::default()).clone(), false),
+            };
+
+            // Parallel actions needing the same task wait for the first one to run it
+            let mut error = None;
+            let dependency_outcome = outcome.get_or_init(|| {
+                let result = execution
+                    .sender
+                    .send(ExecutionMessage::RunningDependency { name: name.clone() })
+                    .map_err(RunError::from)
+                    .and_then(|()| {
+                        execution.in_sub_task(name, |execution| {
+                            self.run_task(
+                                name,
+                                dependency,
+                                &mut dependency_param_context,
+                                execution,
+                            )
+                        })
+                    });
+                result.map_err(|e| error = Some(e)).ok()
+            });
+            if let Some(error) = error {
+                return Err(error);
+            }
+            let dependency_outcome = dependency_outcome
+                .clone()
+                .ok_or_else(|| RunError::DependencyNotRun(name.clone()))?;
+            if !dependency_outcome.success() {
+                return Ok(Some(dependency_outcome));
+            }
+            if already_run {
+                execution
+                    .sender
+                    .send(ExecutionMessage::DependencyAlreadySatisfied { name: name.clone() })?;
+            }
+        }
+        Ok(None)
+    }
+
+    fn run_actions(
+        &self,
+        task: &model::task::Task,
+        param_context: &mut ParamContext,
+        execution: &mut Execution,
+    ) -> Result<TaskOutcome, RunError> {
+        let mut shell = task.persistent_shell.then(PersistentShell::default);
         for action in &task.actions {
-            let status = self.run_action(
-                action,
-                task.working_directory.as_ref(),
-                param_context,
-                execution_message_sender,
-            )?;
+            if execution.interrupter.is_interrupted() {
+                return Ok(TaskOutcome::Interrupted);
+            }
+            let outcome =
+                self.run_action(action, task, param_context, execution, shell.as_mut())?;
 
-            if !status.success() {
-                break;
+            if !outcome.success() {
+                if action.continue_on_error() {
+                    execution.sender.send(ExecutionMessage::FailureIgnored)?;
+                    continue;
+                }
+                return Ok(outcome);
             }
         }
+        Ok(TaskOutcome::Completed(ExitStatus::Exited(0)))
+    }
+
+    /// Run the actions of a task concurrently, at most `max_parallel` at once, each on its own
+    /// copy of the params. Values captured by the actions are then set in `param_context`.
+    ///
+    /// Every action runs even if another fails; the outcome is the one of the first failed
+    /// action.
+    fn run_actions_in_parallel(
+        &self,
+        task: &model::task::Task,
+        param_context: &mut ParamContext,
+        execution: &Execution,
+    ) -> Result<TaskOutcome, RunError> {
+        let worker_count = task
+            .max_parallel
+            .unwrap_or(task.actions.len())
+            .clamp(1, task.actions.len().max(1));
+        let next_action_id = AtomicUsize::new(0);
+        let shared_param_context: &ParamContext = param_context;
 
-        Ok(ExitStatus::Exited(0))
+        let results = thread::scope(|scope| {
+            let workers = (0..worker_count)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut results = Vec::new();
+                        while !execution.interrupter.is_interrupted() {
+                            let action_id = next_action_id.fetch_add(1, Ordering::Relaxed);
+                            let Some(action) = task.actions.get(action_id) else {
+                                return Ok(results);
+                            };
+                            let mut action_param_context = shared_param_context.clone();
+                            let mut action_execution = execution.fork(action_id);
+                            let outcome = self.run_action(
+                                action,
+                                task,
+                                &mut action_param_context,
+                                &mut action_execution,
+                                None,
+                            )?;
+                            if !outcome.success() && action.continue_on_error() {
+                                action_execution
+                                    .sender
+                                    .send(ExecutionMessage::FailureIgnored)?;
+                            }
+                            results.push((action_id, outcome, action_param_context));
+                        }
+                        Ok(results)
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .map(|worker| {
+                    worker
+                        .join()
+                        .map_err(|_| RunError::ParallelActionPanicked)?
+                })
+                .flatten_ok()
+                .collect::<Result<Vec<_>, RunError>>()
+        })?;
+        Ok(parallel_outcome(task, param_context, results))
     }
 
+    /// Run an action, its commands in `shell` when given.
     fn run_action(
         &self,
         action: &model::action::Action,
-        working_directory: Option<&InterpolatedString>,
-        param_context: &ParamContext,
-        execution_message_sender: &mpsc::Sender<ExecutionMessage>,
-    ) -> anyhow::Result<ExitStatus> {
+        task: &model::task::Task,
+        param_context: &mut ParamContext,
+        execution: &mut Execution,
+        shell: Option<&mut PersistentShell>,
+    ) -> Result<TaskOutcome, RunError> {
         match action {
-            model::action::Action::Command(command) => run_command(
-                command,
-                working_directory,
-                param_context,
-                execution_message_sender,
-            ),
+            model::action::Action::Command(command) => {
+                let (current_dir, env) =
+                    match self.command_context(command, task, param_context, execution)? {
+                        Ok(command_context) => command_context,
+                        Err(reason) => return Ok(TaskOutcome::PreconditionFailed(reason)),
+                    };
+                run_command(
+                    command,
+                    &current_dir,
+                    &env,
+                    param_context,
+                    execution,
+                    shell,
+                    task.max_output_bytes.or(self.max_output_bytes),
+                )
+                .map(TaskOutcome::Completed)
+            }
             model::action::Action::TaskCall(task_call) => {
-                self.run_task_from_action(task_call, param_context, execution_message_sender)
+                self.run_task_from_action(task_call, param_context, execution)
             }
         }
     }
 
+    /// Working directory and environment of a command of `task`. Returns the reason after
+    /// reporting it when the directory does not exist.
+    fn command_context(
+        &self,
+        command: &model::action::Command,
+        task: &model::task::Task,
+        param_context: &ParamContext,
+        execution: &Execution,
+    ) -> Result<Result<(PathBuf, CommandEnv), String>, RunError> {
+        let current_dir = match resolve_working_directory(
+            &self.base_dir,
+            command
+                .working_directory
+                .as_ref()
+                .or(task.working_directory.as_ref()),
+            task.create_working_directory,
+            !task.raw_working_directory,
+            param_context,
+            execution,
+        )? {
+            Ok(current_dir) => current_dir,
+            Err(reason) => return Ok(Err(reason)),
+        };
+        let env = CommandEnv {
+            vars: render_env(
+                &self.dotenv,
+                &self.env,
+                &task.env,
+                param_context,
+                task.clean_env,
+            )?,
+            clean: task.clean_env,
+        };
+        Ok(Ok((current_dir, env)))
+    }
+
     fn run_task_from_action(
         &self,
         task_call: &model::action::TaskCall,
         param_context: &ParamContext,
-        execution_message_sender: &mpsc::Sender<ExecutionMessage>,
-    ) -> anyhow::Result<ExitStatus> {
+        execution: &mut Execution,
+    ) -> Result<TaskOutcome, RunError> {
         let task = self
             .get_task_by_name(&task_call.name)
-            .ok_or_else(|| anyhow!("Task {} is not defined", task_call.name))?;
-        let mut task_param_context = ParamContext::default();
-        for (param_name, param_value) in &task_call.params {
-            task_param_context.set(param_name, &param_value.render(param_context)?);
+            .ok_or_else(|| self.task_not_found(&task_call.name))?;
+        let mut task_param_context = render_task_call_params(task_call, param_context)?;
+        execution.in_sub_task(&task_call.name, |execution| {
+            self.run_task(&task_call.name, task, &mut task_param_context, execution)
+        })
+    }
+}
+
+/// Error for a call to `name` from the tasks of `call_stack`, which already run it.
+fn cyclic_call(call_stack: &[String], name: &str) -> RunError {
+    let mut cycle = call_stack.to_vec();
+    cycle.push(name.to_string());
+    RunError::CyclicCall(cycle)
+}
+
+/// Outcome of the actions of `task` run in parallel, from their id, outcome and params, setting
+/// the values they captured in `param_context`.
+fn parallel_outcome(
+    task: &model::task::Task,
+    param_context: &mut ParamContext,
+    mut results: Vec<(usize, TaskOutcome, ParamContext)>,
+) -> TaskOutcome {
+    results.sort_by_key(|(action_id, ..)| *action_id);
+
+    let mut outcome = TaskOutcome::Completed(ExitStatus::Exited(0));
+    for (action_id, action_outcome, action_param_context) in results {
+        let action = &task.actions[action_id];
+        if let model::action::Action::Command(command) = action
+            && let Some(capture) = &command.capture
+            && let Some(value) = action_param_context.get(capture)
+        {
+            param_context.set_value(capture, value.clone());
+        }
+        if outcome.success() && !action_outcome.success() && !action.continue_on_error() {
+            outcome = action_outcome;
         }
-        self.run_task(task, &mut task_param_context, execution_message_sender)
     }
+    outcome
 }
 
-fn run_command(
-    command: &InterpolatedString,
-    working_directory: Option<&InterpolatedString>,
+/// How a param got its value, see [`TaskerieContext::resolve_param`].
+enum ParamResolution<'t> {
+    /// It already had one or got it from its default, a var or the caller.
+    Resolved,
+    /// It has to be read from its command or file.
+    Source(&'t model::task::ParamSource),
+    Missing,
+}
+
+/// Render the params given by a task call.
+fn render_task_call_params(
+    task_call: &model::action::TaskCall,
+    param_context: &ParamContext,
+) -> Result<ParamContext, RunError> {
+    let mut task_param_context = ParamContext::default();
+    for (param_name, param_value) in &task_call.params {
+        let value =
+            param_value
+                .render(param_context)
+                .map_err(|source| RunError::TaskCallParam {
+                    task: task_call.name.clone(),
+                    parameter: param_name.clone(),
+                    source,
+                })?;
+        task_param_context.set(param_name, &value);
+    }
+    Ok(task_param_context)
+}
+
+type DependencyKey = (String, Vec<(String, ParamValue)>);
+
+type PreRunHook<'a> = dyn FnMut(&str, &ParamContext) -> Result<(), String> + Send + 'a;
+
+/// State shared by every task and action of a single run.
+struct Execution<'a> {
+    sender: MessageSender<'a>,
+    dry_run: bool,
+    /// Locked while a value is requested so that parallel actions do not mix up the answers.
+    parameter_values: Option<&'a Mutex<mpsc::Receiver<Option<String>>>>,
+    confirmations: Option<&'a Mutex<mpsc::Receiver<bool>>>,
+    assume_yes: bool,
+    pre_run_hook: &'a Mutex<PreRunHook<'a>>,
+    /// Names of the tasks currently running, outermost first.
+    call_stack: Vec<String>,
+    /// Status of the dependencies run so far by name and sorted params, `None` when they could
+    /// not run.
+    dependencies: &'a Mutex<HashMap<DependencyKey, Arc<OnceLock<Option<TaskOutcome>>>>>,
+    interrupter: Interrupter,
+    /// Shell overriding PowerShell, from the options or else [`SHELL_VARIABLE`].
+    shell: Option<&'a str>,
+}
+
+impl<'a> Execution<'a> {
+    /// Copy of the execution for an action run in parallel with the other ones of its task.
+    fn fork(&self, action_id: usize) -> Execution<'a> {
+        let mut sender = self.sender.clone();
+        sender.scopes.push(MessageScope::Action(action_id));
+        Execution {
+            sender,
+            dry_run: self.dry_run,
+            parameter_values: self.parameter_values,
+            confirmations: self.confirmations,
+            assume_yes: self.assume_yes,
+            pre_run_hook: self.pre_run_hook,
+            call_stack: self.call_stack.clone(),
+            dependencies: self.dependencies,
+            interrupter: self.interrupter.clone(),
+            shell: self.shell,
+        }
+    }
+
+    /// Run `f` with the messages it sends wrapped as the ones of the task `task_name`.
+    fn in_sub_task<T>(&mut self, task_name: &str, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.sender
+            .scopes
+            .push(MessageScope::Task(task_name.to_string()));
+        let result = f(self);
+        self.sender.scopes.pop();
+        result
+    }
+
+    /// Ask the caller to confirm running a task that has a `confirm` prompt, unless confirmations
+    /// are assumed or nothing actually runs. Returns why the task must not run otherwise.
+    fn confirm(
+        &self,
+        task_name: &str,
+        task: &model::task::Task,
+    ) -> Result<Result<(), String>, RunError> {
+        let Some(prompt) = &task.confirm else {
+            return Ok(Ok(()));
+        };
+        if self.assume_yes || self.dry_run {
+            return Ok(Ok(()));
+        }
+        let Some(confirmations) = self.confirmations else {
+            return Ok(Err(
+                "confirmation required but it cannot be asked for".to_string()
+            ));
+        };
+        let confirmations = confirmations.lock().unwrap_or_else(PoisonError::into_inner);
+        self.sender.send(ExecutionMessage::ConfirmationRequired {
+            task_name: task_name.to_string(),
+            prompt: prompt.clone(),
+        })?;
+        if confirmations.recv().unwrap_or(false) {
+            Ok(Ok(()))
+        } else {
+            Ok(Err("confirmation declined".to_string()))
+        }
+    }
+
+    /// Outcome of the run once the outermost task is done, with the secrets masked in the reasons
+    /// and errors, which are shown as is.
+    fn finish(&self, outcome: Result<TaskOutcome, RunError>) -> Result<TaskOutcome, RunError> {
+        let outcome = outcome.map_err(|mut error| {
+            mask_error_secrets(&mut error, &lock_secrets(self.sender.secrets));
+            error
+        })?;
+        if self.interrupter.is_interrupted() {
+            self.sender.send(ExecutionMessage::Interrupted)?;
+            return Ok(TaskOutcome::Interrupted);
+        }
+        match outcome {
+            TaskOutcome::PreconditionFailed(mut reason) => {
+                mask_secrets(&mut reason, &lock_secrets(self.sender.secrets));
+                Ok(TaskOutcome::PreconditionFailed(reason))
+            }
+            outcome => Ok(outcome),
+        }
+    }
+
+    /// Ask the caller for the value of a missing parameter, if it accepts such requests.
+    fn request_parameter_value(
+        &self,
+        parameter_name: &str,
+        param: &model::task::Param,
+    ) -> Result<Option<String>, RunError> {
+        let Some(parameter_values) = self.parameter_values else {
+            return Ok(None);
+        };
+        let parameter_values = parameter_values
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let choices = match &param.param_type {
+            model::task::ParamType::Enum(choices) => choices.clone(),
+            _ => Vec::new(),
+        };
+        self.sender.send(ExecutionMessage::ParameterValueRequired {
+            parameter_name: parameter_name.to_string(),
+            choices,
+            secret: param.is_secret(),
+        })?;
+        Ok(parameter_values.recv().ok().flatten())
+    }
+}
+
+/// Sends the messages of a run, wrapping the ones of parallel actions and sub-tasks so they can be
+/// told apart. Messages are dropped when there is no sender.
+#[derive(Clone)]
+struct MessageSender<'a> {
+    sender: Option<&'a dyn MessageSink>,
+    /// Parallel actions and sub-tasks the messages come from, outermost first.
+    scopes: Vec<MessageScope>,
+    /// Values of the secret params of every task run so far, longest first, replaced by
+    /// [`SECRET_MASK`] in the commands, output, paths and param values sent.
+    secrets: &'a Mutex<Vec<String>>,
+}
+
+/// Where the messages of a run end up.
+trait MessageSink: Sync {
+    fn send(&self, message: ExecutionMessage) -> Result<(), mpsc::SendError<ExecutionMessage>>;
+}
+
+impl MessageSink for mpsc::Sender<ExecutionMessage> {
+    fn send(&self, message: ExecutionMessage) -> Result<(), mpsc::SendError<ExecutionMessage>> {
+        mpsc::Sender::send(self, message)
+    }
+}
+
+/// Text shown instead of the value of a secret param.
+pub const SECRET_MASK: &str = "****";
+
+#[derive(Clone)]
+enum MessageScope {
+    Action(usize),
+    Task(String),
+}
+
+impl MessageSender<'_> {
+    fn send(&self, mut message: ExecutionMessage) -> Result<(), mpsc::SendError<ExecutionMessage>> {
+        let Some(sender) = self.sender else {
+            return Ok(());
+        };
+        match &mut message {
+            ExecutionMessage::AboutToRunCommand { command: text, .. }
+            | ExecutionMessage::CommandOutput { output: text }
+            | ExecutionMessage::CommandError { output: text }
+            | ExecutionMessage::ParameterResolved { value: text, .. }
+            | ExecutionMessage::InvalidParameterValue { reason: text, .. }
+            | ExecutionMessage::WorkingDirectoryNotFound { path: text } => {
+                mask_secrets(text, &lock_secrets(self.secrets));
+            }
+            ExecutionMessage::WorkingDirectoryResolved {
+                working_directory,
+                path,
+            } => {
+                let secrets = lock_secrets(self.secrets);
+                mask_secrets(working_directory, &secrets);
+                mask_secrets(path, &secrets);
+            }
+            _ => {}
+        }
+        let message = self
+            .scopes
+            .iter()
+            .rev()
+            .fold(message, |message, scope| match scope {
+                MessageScope::Action(action_id) => ExecutionMessage::ParallelAction {
+                    action_id: *action_id,
+                    message: Box::new(message),
+                },
+                MessageScope::Task(task_name) => ExecutionMessage::SubTask {
+                    task_name: task_name.clone(),
+                    message: Box::new(message),
+                },
+            });
+        sender.send(message)
+    }
+
+    /// Mask the value of `param` from now on if it is secret. Registered as soon as the value is
+    /// known, it is also masked in the sources of the next params and the errors.
+    fn add_param_secret(&self, param: &model::task::Param, value: Option<&ParamValue>) {
+        if param.is_secret()
+            && let Some(value) = value
+        {
+            self.add_secret(value);
+        }
+    }
+
+    /// Mask `value` in the messages sent from now on, along with each of its items and lines so
+    /// that it is also masked when a list is rendered differently or output line by line.
+    fn add_secret(&self, value: &ParamValue) {
+        let mut secrets = lock_secrets(self.secrets);
+        let whole_value = value.to_string();
+        let parts = std::iter::once(whole_value.as_str())
+            .chain(value.items().iter().map(String::as_str))
+            .flat_map(|part| std::iter::once(part).chain(part.lines()));
+        for part in parts {
+            let part = part.trim();
+            if !part.is_empty() && !secrets.iter().any(|secret| secret == part) {
+                secrets.push(part.to_string());
+            }
+        }
+        // A secret containing another one is masked whole
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+    }
+}
+
+/// Replace every occurrence of the secrets in `text` by [`SECRET_MASK`].
+///
+/// Masking the text as sent, rather than the values of the interpolations, also masks secrets
+/// written next to other text or interpolations. Secrets short enough to appear by chance in
+/// other text are masked there as well, hiding a secret taking precedence over readability.
+fn mask_secrets(text: &mut String, secrets: &[String]) {
+    for secret in secrets {
+        if text.contains(secret.as_str()) {
+            *text = text.replace(secret.as_str(), SECRET_MASK);
+        }
+    }
+}
+
+/// Secrets registered so far, even if a thread panicked while registering some.
+fn lock_secrets(secrets: &Mutex<Vec<String>>) -> std::sync::MutexGuard<'_, Vec<String>> {
+    secrets.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Replace the secrets in the rendered commands, paths and reasons an error tells, which are
+/// shown as is unlike the messages.
+fn mask_error_secrets(error: &mut RunError, secrets: &[String]) {
+    match error {
+        RunError::Spawn { command: text, .. }
+        | RunError::ReadOutput { command: text, .. }
+        | RunError::ShellInput { command: text, .. }
+        | RunError::ParamSource { reason: text, .. }
+        | RunError::CreateWorkingDirectory { path: text, .. } => mask_secrets(text, secrets),
+        _ => {}
+    }
+}
+
+/// Render the global and task environment variables, task ones taking precedence, after the dotenv
+/// ones missing from the process environment, or all of them when it is not inherited.
+fn render_env(
+    dotenv: &IndexMap<String, String>,
+    global_env: &IndexMap<String, InterpolatedString>,
+    task_env: &IndexMap<String, InterpolatedString>,
+    param_context: &ParamContext,
+    clean_env: bool,
+) -> Result<Vec<(String, String)>, RunError> {
+    let mut env = dotenv
+        .iter()
+        .filter(|(key, _)| clean_env || std::env::var_os(key).is_none())
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect::<IndexMap<_, _>>();
+    for (key, value) in global_env.iter().chain(task_env) {
+        env.insert(key.clone(), value.render(param_context)?.into_owned());
+    }
+    Ok(env.into_iter().collect())
+}
+
+/// Environment variables set for a command.
+struct CommandEnv {
+    vars: Vec<(String, String)>,
+    /// Start from an empty environment instead of the one of taskerie, only keeping
+    /// [`CLEAN_ENV_KEPT_VARIABLES`].
+    clean: bool,
+}
+
+/// Variables of the process environment kept in a clean environment, for the shell to find the
+/// programs and, on Windows, to start at all.
+const CLEAN_ENV_KEPT_VARIABLES: [&str; 2] = ["PATH", "SYSTEMROOT"];
+
+impl CommandEnv {
+    fn apply(&self, exec: Exec) -> Exec {
+        let exec = if self.clean {
+            let kept = CLEAN_ENV_KEPT_VARIABLES
+                .into_iter()
+                .filter_map(|key| Some((key, std::env::var_os(key)?)))
+                .collect::<Vec<_>>();
+            exec.env_clear().env_extend(&kept)
+        } else {
+            exec
+        };
+        exec.env_extend(&self.vars)
+    }
+}
+
+/// Read the value of the param `param_name` from its command or file, relative to `base_dir`.
+/// The command runs in `shell_override` when given, like the other ones.
+fn read_param_source(
+    base_dir: &Path,
+    param_name: &str,
+    source: &model::task::ParamSource,
+    param_context: &ParamContext,
+    shell_override: Option<&str>,
+) -> Result<String, RunError> {
+    let error = |reason: String| RunError::ParamSource {
+        parameter: param_name.to_string(),
+        reason,
+    };
+    let value = match source {
+        model::task::ParamSource::File(path) => {
+            let path = base_dir.join(&*path.render(param_context)?);
+            fs::read_to_string(&path)
+                .map_err(|source| error(format!("{}: {source}", display_path(&path))))?
+        }
+        model::task::ParamSource::Command(command) => {
+            let command = command.render(param_context)?;
+            let shell = resolve_shell(shell_override)?;
+            let capture = program(&command, &[], &shell)
+                .cwd(base_dir.join("./"))
+                .stdout(Redirection::Pipe)
+                .capture()
+                .map_err(|source| error(format!("{command}: {source}")))?;
+            if !capture.success() {
+                return Err(error(format!("{command} failed")));
+            }
+            capture.stdout_str()
+        }
+    };
+    Ok(value.trim().to_string())
+}
+
+/// Render the working directory of a command relative to `base_dir`, creating it if `create` is
+/// set.
+///
+/// Returns the reason after reporting it when the directory does not exist. Without
+/// `canonicalize`, the directory is neither checked nor resolved, a missing one failing to spawn
+/// the command instead.
+fn resolve_working_directory(
+    base_dir: &Path,
+    configured_directory: Option<&InterpolatedString>,
+    create: bool,
+    canonicalize: bool,
     param_context: &ParamContext,
-    execution_message_sender: &mpsc::Sender<ExecutionMessage>,
-) -> anyhow::Result<ExitStatus> {
-    let current_dir = working_directory
+    execution: &Execution,
+) -> Result<Result<PathBuf, String>, RunError> {
+    let execution_message_sender = &execution.sender;
+    let working_directory = configured_directory
         .map(|dir| dir.render(param_context))
         .transpose()?
         .unwrap_or_else(|| "./".into());
+    // Joining an absolute path replaces the base directory
+    let path = base_dir.join(&*working_directory);
+
+    if create && !path.exists() {
+        if execution.dry_run {
+            return Ok(Ok(path));
+        }
+        fs::create_dir_all(&path).map_err(|source| RunError::CreateWorkingDirectory {
+            path: working_directory.to_string(),
+            source,
+        })?;
+    }
 
-    let Ok(current_dir) = PathBuf::from(&*current_dir).canonicalize() else {
+    let canonical_path = if canonicalize {
+        path.canonicalize()
+    } else {
+        Ok(path)
+    };
+    let Ok(path) = canonical_path else {
         execution_message_sender.send(ExecutionMessage::WorkingDirectoryNotFound {
-            path: current_dir.clone().into_owned(),
+            path: display_path(&base_dir.join(&*working_directory)),
         })?;
-        return Ok(ExitStatus::Undetermined);
+        return Ok(Err(format!(
+            "Working directory '{working_directory}' not found"
+        )));
     };
-    let current_dir_str = current_dir.display().to_string();
-    let command = command.render(param_context)?;
+    if configured_directory.is_some() {
+        execution_message_sender.send(ExecutionMessage::WorkingDirectoryResolved {
+            working_directory: working_directory.into_owned(),
+            path: display_path(&path),
+        })?;
+    }
+    Ok(Ok(path))
+}
 
-    execution_message_sender.send(ExecutionMessage::AboutToRunCommand {
-        command: command.clone().into_owned(),
-        working_directory: current_dir_str,
-    })?;
+/// Path as shown in the messages, without the `\\?\` prefix canonical paths get on Windows.
+fn display_path(path: &Path) -> String {
+    let path = path.display().to_string();
+    if cfg!(windows) {
+        strip_verbatim_prefix(&path).into_owned()
+    } else {
+        path
+    }
+}
 
-    let mut process = Exec::cmd("pwsh")
-        .arg("-NonInteractive")
-        .arg("-Command")
-        .arg(command.clone().into_owned())
-        .cwd(current_dir)
-        .stdout(Redirection::Pipe)
-        .stderr(Redirection::Merge)
-        .popen()?;
+/// Turn a verbatim Windows path into the usual form, `\\?\C:\dir` into `C:\dir` and
+/// `\\?\UNC\server\share` into `\\server\share`.
+fn strip_verbatim_prefix(path: &str) -> Cow<'_, str> {
+    if let Some(unc_path) = path.strip_prefix(r"\\?\UNC\") {
+        Cow::Owned(format!(r"\\{unc_path}"))
+    } else {
+        Cow::Borrowed(path.strip_prefix(r"\\?\").unwrap_or(path))
+    }
+}
+
+/// Run a command with its retries, in `shell` when given unless it reads an input. Only the first
+/// `max_output_bytes` of the output of each attempt are forwarded when given.
+///
+/// The parts of a split command run one after the other, the status being the one of the last
+/// part that ran.
+fn run_command(
+    command: &model::action::Command,
+    current_dir: &Path,
+    env: &CommandEnv,
+    param_context: &mut ParamContext,
+    execution: &Execution,
+    mut shell: Option<&mut PersistentShell>,
+    max_output_bytes: Option<usize>,
+) -> Result<ExitStatus, RunError> {
+    let execution_message_sender = &execution.sender;
+    let current_dir_str = display_path(current_dir);
+    let capture = command.capture.as_deref();
+    let rendered = RenderedCommand::new(command, param_context, execution.shell)?;
+
+    let mut captured_output = capture.map(|_| String::new());
+    let mut status = ExitStatus::Exited(0);
+    for (sequencing, part) in &rendered.commands {
+        if *sequencing == Sequencing::OnSuccess && !status.success() {
+            continue;
+        }
+        execution_message_sender.send(ExecutionMessage::AboutToRunCommand {
+            command: part.clone(),
+            working_directory: current_dir_str.clone(),
+            label: command.label.clone(),
+        })?;
 
-    let output_reader = BufReader::new(
-        process
-            .stdout
-            .as_mut()
-            .ok_or_else(|| anyhow!("Could not get powershell stdout {}", command))?,
-    );
+        if execution.dry_run {
+            execution_message_sender.send(ExecutionMessage::CommandSucceeded)?;
+            continue;
+        }
 
-    for line in output_reader.lines() {
-        execution_message_sender.send(ExecutionMessage::CommandOutput { output: line? })?;
+        let mut attempt = 0;
+        status = loop {
+            let mut output = OutputForwarder::new(command, execution, max_output_bytes);
+            let (status, duration) = match shell.as_deref_mut() {
+                Some(shell) if rendered.runs_in_shell() => shell.run(
+                    part,
+                    rendered.persistent_shell_program(),
+                    current_dir,
+                    env,
+                    execution,
+                    &mut output,
+                )?,
+                _ => spawn_command(
+                    part,
+                    program(part, &rendered.arguments, &rendered.shell),
+                    current_dir,
+                    env,
+                    execution,
+                    rendered.stdin.as_deref(),
+                    &mut output,
+                )?,
+            };
+            if retry_attempt(command, status, duration, &mut attempt, execution)? {
+                thread::sleep(command.retry_delay);
+                continue;
+            }
+            if let (Some(captured_output), Some(output)) =
+                (captured_output.as_mut(), output.captured_output)
+            {
+                captured_output.push_str(&output);
+            }
+            break command_finished(command, status, execution)?;
+        };
     }
 
-    if process.wait()?.success() {
-        execution_message_sender.send(ExecutionMessage::CommandSucceeded)?;
-    } else {
-        execution_message_sender.send(ExecutionMessage::CommandFailed)?;
+    if let (Some(capture), Some(captured_output)) = (capture, captured_output) {
+        param_context.set(capture, captured_output.trim());
     }
+    Ok(status)
+}
 
-    Ok(process
-        .exit_status()
-        .expect("Exit status is available because the process is done already"))
+/// A command rendered with the values of its params, along with the shell running it.
+struct RenderedCommand {
+    stdin: Option<String>,
+    arguments: Vec<String>,
+    /// Parts of the command to run in order, with when they run.
+    commands: Vec<(Sequencing, String)>,
+    /// Whether the shell overrides PowerShell.
+    shell_overridden: bool,
+    /// Program and arguments running each part.
+    shell: Vec<String>,
+}
+
+impl RenderedCommand {
+    /// Render `command`, run by `shell_override` when given and PowerShell otherwise.
+    fn new(
+        command: &model::action::Command,
+        param_context: &ParamContext,
+        shell_override: Option<&str>,
+    ) -> Result<RenderedCommand, RunError> {
+        let stdin = command
+            .stdin
+            .as_ref()
+            .map(|stdin| stdin.render(param_context))
+            .transpose()?
+            .map(Cow::into_owned);
+        let arguments = command
+            .arguments
+            .iter()
+            .map(|argument| Ok(argument.render(param_context)?.into_owned()))
+            .collect::<Result<Vec<_>, RunError>>()?;
+        let commands = command
+            .commands()
+            .map(|(sequencing, command)| {
+                Ok((sequencing, command.render(param_context)?.into_owned()))
+            })
+            .collect::<Result<Vec<_>, RunError>>()?;
+        let shell = resolve_shell(shell_override)?;
+        Ok(RenderedCommand {
+            stdin,
+            arguments,
+            commands,
+            shell_overridden: shell_override.is_some(),
+            shell,
+        })
+    }
+
+    /// Whether the command can run in a persistent shell, which it cannot when it reads an input
+    /// or runs without shell.
+    fn runs_in_shell(&self) -> bool {
+        self.stdin.is_none() && self.arguments.is_empty()
+    }
+
+    /// Program a persistent shell is started as, PowerShell when `None`.
+    fn persistent_shell_program(&self) -> Option<&str> {
+        self.shell_overridden.then(|| self.shell[0].as_str())
+    }
+}
+
+fn succeeded(command: &model::action::Command, status: ExitStatus) -> bool {
+    status.success()
+        || exit_code(status).is_some_and(|code| command.allowed_exit_codes.contains(&code))
+}
+
+/// Report the end of an attempt of `command`, with its status and the time it ran for. Returns
+/// whether it has to be retried, after reporting the retry the caller waits
+/// [`retry_delay`](model::action::Command::retry_delay) before.
+fn retry_attempt(
+    command: &model::action::Command,
+    status: ExitStatus,
+    duration: Duration,
+    attempt: &mut u32,
+    execution: &Execution,
+) -> Result<bool, RunError> {
+    execution.sender.send(ExecutionMessage::CommandFinished {
+        duration_ms: duration.as_millis().try_into().unwrap_or(u64::MAX),
+        exit_code: exit_code(status),
+    })?;
+    if succeeded(command, status)
+        || *attempt >= command.retries
+        || execution.interrupter.is_interrupted()
+    {
+        return Ok(false);
+    }
+    *attempt += 1;
+    execution.sender.send(ExecutionMessage::RetryingCommand {
+        attempt: *attempt,
+        max: command.retries,
+    })?;
+    Ok(true)
+}
+
+/// Report whether `command` succeeded once it is not retried anymore, returning the status it
+/// counts as having exited with.
+fn command_finished(
+    command: &model::action::Command,
+    status: ExitStatus,
+    execution: &Execution,
+) -> Result<ExitStatus, RunError> {
+    if succeeded(command, status) {
+        execution.sender.send(ExecutionMessage::CommandSucceeded)?;
+        return Ok(ExitStatus::Exited(0));
+    }
+    execution.sender.send(ExecutionMessage::CommandFailed {
+        exit_code: exit_code(status),
+    })?;
+    Ok(status)
+}
+
+fn exit_code(status: ExitStatus) -> Option<u32> {
+    match status {
+        ExitStatus::Exited(code) => Some(code),
+        _ => None,
+    }
+}
+
+/// Environment variable overriding the shell running the commands, given as a program followed by
+/// its arguments such as `bash -c`, each command being passed after them. Persistent shells start
+/// the program alone, reading the commands from their input.
+pub const SHELL_VARIABLE: &str = "TASKERIE_SHELL";
+
+/// Value of [`SHELL_VARIABLE`], when set and not empty.
+fn shell_variable() -> Option<String> {
+    std::env::var(SHELL_VARIABLE)
+        .ok()
+        .filter(|value| !value.is_empty())
+}
+
+/// Program and arguments running a command passed after them, split from `shell_override`, the
+/// value of [`SHELL_VARIABLE`], when given. PowerShell runs the commands otherwise.
+fn resolve_shell(shell_override: Option<&str>) -> Result<Vec<String>, RunError> {
+    match shell_override {
+        Some(shell) => action::split_arguments(shell, &Delimiters::default())
+            .map_err(|error| RunError::InvalidShell(error.to_string())),
+        None => Ok(["pwsh", "-NonInteractive", "-Command"]
+            .map(ToString::to_string)
+            .to_vec()),
+    }
+}
+
+/// Process running `command` through `shell`, or else `arguments` directly when there are some.
+fn program(command: &str, arguments: &[String], shell: &[String]) -> Exec {
+    match arguments.split_first() {
+        Some((program, arguments)) => Exec::cmd(program).args(arguments),
+        None => Exec::cmd(&shell[0]).args(&shell[1..]).arg(command),
+    }
+}
+
+/// Run a single attempt of `command` with `program`, writing `stdin` to its input and forwarding
+/// its output until it exits. Returns its status along with the time it ran for.
+fn spawn_command(
+    command: &str,
+    program: Exec,
+    current_dir: &Path,
+    env: &CommandEnv,
+    execution: &Execution,
+    stdin: Option<&str>,
+    output: &mut OutputForwarder,
+) -> Result<(ExitStatus, Duration), RunError> {
+    let interrupter = &execution.interrupter;
+    let started_at = Instant::now();
+    let mut exec = env
+        .apply(program)
+        .cwd(current_dir)
+        .stdout(Redirection::Pipe)
+        .stderr(Redirection::Pipe);
+    if stdin.is_some() {
+        exec = exec.stdin(Redirection::Pipe);
+    }
+    let mut process = exec.popen().map_err(|source| RunError::Spawn {
+        command: command.to_string(),
+        source,
+    })?;
+
+    if let (Some(input), Some(mut process_stdin)) = (stdin, process.stdin.take()) {
+        // Written on its own thread so that a process writing its output before reading all its
+        // input does not block on a full pipe. Closing the input once written lets it finish.
+        let input = input.to_string();
+        thread::spawn(move || {
+            // The process may exit without reading everything, which is not an error
+            let _ = process_stdin.write_all(input.as_bytes());
+        });
+    }
+
+    let (Some(stdout), Some(stderr)) = (process.stdout.take(), process.stderr.take()) else {
+        unreachable!("The output of the command is piped");
+    };
+    let process = Arc::new(Mutex::new(process));
+    let _registration = interrupter.register(&process);
+    let lock_process = || process.lock().unwrap_or_else(PoisonError::into_inner);
+
+    // Both pipes are drained concurrently so the process never blocks on a full one
+    let (line_tx, line_rx) = mpsc::channel();
+    let stdout_line_tx = line_tx.clone();
+    let stdout_reader = thread::spawn(move || {
+        forward_lines(stdout, &stdout_line_tx, |output| {
+            ExecutionMessage::CommandOutput { output }
+        })
+    });
+    let stderr_reader = thread::spawn(move || {
+        forward_lines(stderr, &line_tx, |output| ExecutionMessage::CommandError {
+            output,
+        })
+    });
+
+    let mut exited_at = None;
+    let forwarded = forward_output(&line_rx, output, || {
+        // Noticing the exit while forwarding keeps the end of the output out of the duration
+        if exited_at.is_none() && lock_process().poll().is_some() {
+            exited_at = Some(Instant::now());
+        }
+        exited_at.is_some() && interrupter.is_interrupted()
+    })
+    .and_then(|all_read| {
+        if !all_read {
+            // The readers are left behind, blocked until the processes the interrupted command
+            // started close its output
+            return Ok(());
+        }
+        for reader in [stdout_reader, stderr_reader] {
+            reader
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("The output reader panicked")))
+                .map_err(|source| RunError::ReadOutput {
+                    command: command.to_string(),
+                    source,
+                })?;
+        }
+        Ok(())
+    });
+
+    if let Err(error) = forwarded {
+        // Do not leave the process running once nobody reads its output anymore
+        let mut process = lock_process();
+        process.kill().map_err(PopenError::from)?;
+        process.wait()?;
+        return Err(error);
+    }
+
+    // The process is only locked for a moment at a time so that it can be interrupted
+    loop {
+        if let Some(status) = lock_process().wait_timeout(PROCESS_POLL_INTERVAL)? {
+            return Ok((status, exited_at.unwrap_or_else(Instant::now) - started_at));
+        }
+    }
+}
+
+/// Time spent waiting for a process to exit before letting an interruption stop it.
+const PROCESS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Forward the lines read from the command until its output is closed.
+///
+/// Returns `false` when it stopped before the end of the output because `is_abandoned` returned
+/// `true`, which is checked after every line and whenever none came for
+/// [`PROCESS_POLL_INTERVAL`].
+fn forward_output(
+    lines: &mpsc::Receiver<ExecutionMessage>,
+    output: &mut OutputForwarder,
+    mut is_abandoned: impl FnMut() -> bool,
+) -> Result<bool, RunError> {
+    loop {
+        match lines.recv_timeout(PROCESS_POLL_INTERVAL) {
+            Ok(message) => output.forward(message)?,
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(true),
+        }
+        if is_abandoned() {
+            return Ok(false);
+        }
+    }
+}
+
+/// Sends the output lines of a command attempt and captures its standard output.
+struct OutputForwarder<'a> {
+    sender: &'a MessageSender<'a>,
+    /// Standard output of the command, when captured.
+    captured_output: Option<String>,
+    /// Only capture the standard output lines instead of sending them.
+    silent: bool,
+    /// Size of the output after which the lines are dropped.
+    max_output_bytes: Option<usize>,
+    /// Size of the output read so far, line breaks included.
+    output_bytes: usize,
+}
+
+impl<'a> OutputForwarder<'a> {
+    fn new(
+        command: &model::action::Command,
+        execution: &'a Execution,
+        max_output_bytes: Option<usize>,
+    ) -> OutputForwarder<'a> {
+        OutputForwarder {
+            sender: &execution.sender,
+            captured_output: command.capture.as_ref().map(|_| String::new()),
+            silent: command.silent,
+            max_output_bytes,
+            output_bytes: 0,
+        }
+    }
+
+    /// Send a line of the command, unless the output grew past its maximum size. The first line
+    /// past it is replaced by [`ExecutionMessage::OutputTruncated`].
+    fn forward(&mut self, message: ExecutionMessage) -> Result<(), RunError> {
+        if let Some(limit) = self.max_output_bytes
+            && let ExecutionMessage::CommandOutput { output }
+            | ExecutionMessage::CommandError { output } = &message
+        {
+            let was_truncated = self.output_bytes > limit;
+            self.output_bytes = self.output_bytes.saturating_add(output.len() + 1);
+            if was_truncated {
+                return Ok(());
+            }
+            if self.output_bytes > limit {
+                self.sender
+                    .send(ExecutionMessage::OutputTruncated { limit })?;
+                return Ok(());
+            }
+        }
+
+        let is_output = matches!(message, ExecutionMessage::CommandOutput { .. });
+        if let (ExecutionMessage::CommandOutput { output }, Some(captured_output)) =
+            (&message, self.captured_output.as_mut())
+        {
+            captured_output.push_str(output);
+            captured_output.push('\n');
+        }
+        if !(self.silent && is_output) {
+            self.sender.send(message)?;
+        }
+        Ok(())
+    }
+}
+
+/// Send every line read from `output` through the channel, wrapped by `message`.
+fn forward_lines(
+    output: impl Read,
+    lines: &mpsc::Sender<ExecutionMessage>,
+    message: fn(String) -> ExecutionMessage,
+) -> io::Result<()> {
+    let mut forward_line = |line| {
+        lines
+            .send(message(line))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    };
+
+    let mut output = BufReader::new(output);
+    let mut splitter = LineSplitter::default();
+    loop {
+        let buffer = output.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        splitter.split(buffer, &mut forward_line)?;
+        let consumed = buffer.len();
+        output.consume(consumed);
+    }
+    splitter.finish(forward_line)
+}
+
+/// Splits output into lines as it is read. Lines end on `\n`, `\r\n` or a lone `\r` so that
+/// progress updates are shown as they come. Lines that are not valid UTF-8, such as the ones of
+/// tools using a legacy code page, have their invalid bytes replaced by `�`.
+#[derive(Default)]
+struct LineSplitter {
+    line: Vec<u8>,
+    after_carriage_return: bool,
+}
+
+impl LineSplitter {
+    /// Pass every line `bytes` ends to `forward_line`.
+    fn split(
+        &mut self,
+        bytes: &[u8],
+        mut forward_line: impl FnMut(String) -> io::Result<()>,
+    ) -> io::Result<()> {
+        for &byte in bytes {
+            match byte {
+                b'\n' if self.after_carriage_return => {}
+                b'\n' | b'\r' => forward_line(self.take_line())?,
+                _ => self.line.push(byte),
+            }
+            self.after_carriage_return = byte == b'\r';
+        }
+        Ok(())
+    }
+
+    /// Pass the last line to `forward_line` once the output is closed, as it may not end with a
+    /// line break.
+    fn finish(mut self, forward_line: impl FnOnce(String) -> io::Result<()>) -> io::Result<()> {
+        if self.line.is_empty() {
+            return Ok(());
+        }
+        forward_line(self.take_line())
+    }
+
+    fn take_line(&mut self) -> String {
+        String::from_utf8(std::mem::take(&mut self.line))
+            .unwrap_or_else(|error| String::from_utf8_lossy(error.as_bytes()).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use indexmap::IndexMap;
+
+    use super::*;
+    use crate::{
+        model::action::{Action, TaskCall},
+        service::interpolated_string::{RenderError, RenderErrorKind},
+    };
+
+    fn task(actions: Vec<Action>) -> model::Task {
+        model::Task {
+            actions,
+            ..Default::default()
+        }
+    }
+
+    fn env(vars: &[(&str, &str)]) -> IndexMap<String, InterpolatedString> {
+        vars.iter()
+            .map(|(key, value)| ((*key).to_string(), value.parse().unwrap()))
+            .collect()
+    }
+
+    fn command(command: &str) -> Action {
+        Action::Command(Box::new(command.parse().unwrap()))
+    }
+
+    fn task_call(name: &str) -> Action {
+        Action::TaskCall(TaskCall {
+            name: name.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_pre_run_hook_vetoes_sub_task() {
+        let mut tasks = IndexMap::new();
+        tasks.insert("root".to_string(), task(vec![task_call("forbidden")]));
+        tasks.insert(
+            "forbidden".to_string(),
+            task(vec![command("echo forbidden")]),
+        );
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let mut called = Vec::new();
+        let outcome = context
+            .run_task_by_name_with_hook("root", &mut ParamContext::default(), &tx, |name, _| {
+                called.push(name.to_string());
+                if name == "forbidden" {
+                    Err("not allowed".to_string())
+                } else {
+                    Ok(())
+                }
+            })
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(called, vec!["root", "forbidden"]);
+        assert_eq!(
+            outcome,
+            TaskOutcome::PreconditionFailed("Task 'forbidden' was vetoed: not allowed".to_string())
+        );
+        assert_eq!(
+            rx.iter().collect::<Vec<_>>(),
+            vec![
+                ExecutionMessage::TaskStarted {
+                    name: "root".to_string(),
+                },
+                ExecutionMessage::SubTask {
+                    task_name: "forbidden".to_string(),
+                    message: Box::new(ExecutionMessage::TaskStarted {
+                        name: "forbidden".to_string(),
+                    }),
+                },
+                ExecutionMessage::SubTask {
+                    task_name: "forbidden".to_string(),
+                    message: Box::new(ExecutionMessage::TaskVetoed {
+                        task_name: "forbidden".to_string(),
+                        reason: "not allowed".to_string(),
+                    }),
+                },
+                ExecutionMessage::SubTask {
+                    task_name: "forbidden".to_string(),
+                    message: Box::new(ExecutionMessage::TaskFinished {
+                        name: "forbidden".to_string(),
+                        success: false,
+                    }),
+                },
+                ExecutionMessage::TaskFinished {
+                    name: "root".to_string(),
+                    success: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cyclic_task_call_is_rejected() {
+        let mut tasks = IndexMap::new();
+        tasks.insert("a".to_string(), task(vec![task_call("b")]));
+        tasks.insert("b".to_string(), task(vec![task_call("a")]));
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (tx, _rx) = mpsc::channel();
+        let error = context
+            .run_task_by_name("a", &mut ParamContext::default(), &tx)
+            .unwrap_err();
+
+        assert!(matches!(&error, RunError::CyclicCall(cycle) if cycle == &["a", "b", "a"]));
+        assert_eq!(error.to_string(), "Cyclic task call detected: a -> b -> a");
+    }
+
+    #[test]
+    fn test_task_env_overrides_global_env() {
+        let mut param_context = ParamContext::default();
+        param_context.set("name", "john");
+
+        let rendered = render_env(
+            &IndexMap::new(),
+            &env(&[("GREETING", "hello"), ("USER", "nobody")]),
+            &env(&[("USER", "{{ name }}")]),
+            &param_context,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            rendered,
+            vec![
+                ("GREETING".to_string(), "hello".to_string()),
+                ("USER".to_string(), "john".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_env_with_undefined_param() {
+        let error = render_env(
+            &IndexMap::new(),
+            &IndexMap::new(),
+            &env(&[("USER", "{{ name }}")]),
+            &ParamContext::default(),
+            false,
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Could not find value for param 'name' while rendering '{{ name }}'"
+        );
+    }
+
+    #[test]
+    fn test_dry_run_does_not_execute_commands() {
+        let mut tasks = IndexMap::new();
+        tasks.insert(
+            "root".to_string(),
+            task(vec![command("echo root"), task_call("sub")]),
+        );
+        tasks.insert("sub".to_string(), task(vec![command("exit 1")]));
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name_with_options(
+                "root",
+                &mut ParamContext::default(),
+                &tx,
+                ExecutionOptions {
+                    dry_run: true,
+                    ..Default::default()
+                },
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        drop(tx);
+
+        assert!(status.success());
+        let messages = rx
+            .iter()
+            .map(|message| message.into_action_message().1)
+            .filter(|message| !is_task_event(message))
+            .collect::<Vec<_>>();
+        assert_eq!(messages.len(), 4);
+        assert!(matches!(
+            &messages[0],
+            ExecutionMessage::AboutToRunCommand { command, .. } if command == "echo root"
+        ));
+        assert_eq!(messages[1], ExecutionMessage::CommandSucceeded);
+        assert!(matches!(
+            &messages[2],
+            ExecutionMessage::AboutToRunCommand { command, .. } if command == "exit 1"
+        ));
+        assert_eq!(messages[3], ExecutionMessage::CommandSucceeded);
+    }
+
+    #[test]
+    fn test_failing_action_status_is_returned() {
+        let mut tasks = IndexMap::new();
+        tasks.insert(
+            "root".to_string(),
+            task(vec![task_call("failing"), command("echo unreachable")]),
+        );
+        tasks.insert("failing".to_string(), task(vec![command("exit 3")]));
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("root", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(status, TaskOutcome::Completed(ExitStatus::Exited(3)));
+        assert!(!rx.iter().any(|message| matches!(
+            message,
+            ExecutionMessage::AboutToRunCommand { command, .. } if command == "echo unreachable"
+        )));
+    }
+
+    #[test]
+    fn test_successful_task_status() {
+        let mut tasks = IndexMap::new();
+        tasks.insert("root".to_string(), task(vec![command("echo hello")]));
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (tx, _rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("root", &mut ParamContext::default(), &tx)
+            .unwrap();
+
+        assert_eq!(status, TaskOutcome::Completed(ExitStatus::Exited(0)));
+    }
+
+    #[test]
+    fn test_task_started_and_finished() {
+        let mut tasks = IndexMap::new();
+        tasks.insert("root".to_string(), task(vec![command("echo hello")]));
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        context
+            .run_task_by_name("root", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        let messages = rx.iter().collect::<Vec<_>>();
+        assert_eq!(
+            messages.first(),
+            Some(&ExecutionMessage::TaskStarted {
+                name: "root".to_string(),
+            })
+        );
+        assert_eq!(
+            messages.last(),
+            Some(&ExecutionMessage::TaskFinished {
+                name: "root".to_string(),
+                success: true,
+            })
+        );
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|message| is_task_event(message))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_missing_parameter_is_requested_once() {
+        let mut greet = task(vec![command("echo {{ name }}"), command("echo {{ name }}")]);
+        greet
+            .params
+            .insert("name".to_string(), model::task::Param::default());
+        let mut tasks = IndexMap::new();
+        tasks.insert("greet".to_string(), greet);
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (value_tx, value_rx) = mpsc::channel();
+        value_tx.send(Some("john".to_string())).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name_with_options(
+                "greet",
+                &mut ParamContext::default(),
+                &tx,
+                ExecutionOptions {
+                    parameter_values: Some(value_rx),
+                    ..Default::default()
+                },
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        drop(tx);
+
+        assert!(status.success());
+        let messages = rx.iter().collect::<Vec<_>>();
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|message| matches!(
+                    message,
+                    ExecutionMessage::ParameterValueRequired { .. }
+                ))
+                .count(),
+            1
+        );
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|message| matches!(
+                    message,
+                    ExecutionMessage::CommandOutput { output } if output == "john"
+                ))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_declined_parameter_request_aborts() {
+        let mut greet = task(vec![command("echo {{ name }}")]);
+        greet
+            .params
+            .insert("name".to_string(), model::task::Param::default());
+        let mut tasks = IndexMap::new();
+        tasks.insert("greet".to_string(), greet);
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (value_tx, value_rx) = mpsc::channel();
+        value_tx.send(None).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name_with_options(
+                "greet",
+                &mut ParamContext::default(),
+                &tx,
+                ExecutionOptions {
+                    parameter_values: Some(value_rx),
+                    ..Default::default()
+                },
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(
+            status,
+            TaskOutcome::PreconditionFailed(
+                "Task 'greet' is missing required parameters: name".to_string()
+            )
+        );
+        assert_eq!(
+            without_task_events(&rx),
+            vec![
+                ExecutionMessage::ParameterValueRequired {
+                    parameter_name: "name".to_string(),
+                    choices: vec![],
+                    secret: false,
+                },
+                ExecutionMessage::MissingRequiredTaskParameter {
+                    parameter_name: "name".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_all_missing_parameters_are_reported() {
+        let mut deploy = task(vec![command("echo {{ env }} {{ tag }}")]);
+        for name in ["env", "version", "tag"] {
+            deploy.params.insert(
+                name.to_string(),
+                model::task::Param {
+                    default: (name == "version").then(|| "1".parse().unwrap()),
+                    ..Default::default()
+                },
+            );
+        }
+        let mut tasks = IndexMap::new();
+        tasks.insert("deploy".to_string(), deploy);
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("deploy", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(
+            status,
+            TaskOutcome::PreconditionFailed(
+                "Task 'deploy' is missing required parameters: env, tag".to_string()
+            )
+        );
+        assert_eq!(
+            without_task_events(&rx),
+            vec![
+                ExecutionMessage::MissingRequiredTaskParameter {
+                    parameter_name: "env".to_string(),
+                },
+                ExecutionMessage::MissingRequiredTaskParameter {
+                    parameter_name: "tag".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_standalone_tasks_with_descriptions() {
+        let mut tasks = IndexMap::new();
+        tasks.insert(
+            "build".to_string(),
+            model::Task {
+                description: Some("Build the project".to_string()),
+                ..Default::default()
+            },
+        );
+        tasks.insert("test".to_string(), model::Task::default());
+        let mut deploy = model::Task::default();
+        deploy
+            .params
+            .insert("env".to_string(), model::task::Param::default());
+        tasks.insert("deploy".to_string(), deploy);
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            context.get_all_standalone_tasks(),
+            vec![("build", Some("Build the project")), ("test", None)]
+        );
+        assert_eq!(
+            context.get_all_standalone_task_names(),
+            vec!["build", "test"]
+        );
+    }
+
+    #[test]
+    fn test_list_tasks() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    description: Build the project
+    tags: [ci]
+    actions:
+      - echo build
+  deploy:
+    params:
+      env: {}
+      region:
+        default: eu
+    actions:
+      - echo deploy
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            context.list_tasks(),
+            vec![
+                TaskInfo {
+                    name: "build".to_string(),
+                    description: Some("Build the project".to_string()),
+                    standalone: true,
+                    params: Vec::new(),
+                    tags: vec!["ci".to_string()],
+                },
+                TaskInfo {
+                    name: "deploy".to_string(),
+                    description: None,
+                    standalone: false,
+                    params: vec![
+                        ParamInfo {
+                            name: "env".to_string(),
+                            default: None,
+                            required: true,
+                            param_type: model::task::ParamType::String,
+                        },
+                        ParamInfo {
+                            name: "region".to_string(),
+                            default: Some("eu".to_string()),
+                            required: false,
+                            param_type: model::task::ParamType::String,
+                        },
+                    ],
+                    tags: Vec::new(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_parameter_value_aborts() {
+        let mut build = task(vec![command("echo {{ jobs }}")]);
+        build.params.insert(
+            "jobs".to_string(),
+            model::task::Param {
+                param_type: model::task::ParamType::Int,
+                ..Default::default()
+            },
+        );
+        let mut tasks = IndexMap::new();
+        tasks.insert("build".to_string(), build);
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let mut param_context = ParamContext::default();
+        param_context.set("jobs", "abc");
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("build", &mut param_context, &tx)
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(
+            status,
+            TaskOutcome::PreconditionFailed(
+                "Task 'build' has invalid parameter values: jobs".to_string()
+            )
+        );
+        assert_eq!(
+            without_task_events(&rx),
+            vec![ExecutionMessage::InvalidParameterValue {
+                parameter_name: "jobs".to_string(),
+                reason: "'abc' is not an integer".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_stderr_is_reported_separately() {
+        let mut tasks = IndexMap::new();
+        tasks.insert(
+            "list".to_string(),
+            task(vec![command("ls taskerie-nonexistent-path")]),
+        );
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        context
+            .run_task_by_name("list", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        let messages = rx.iter().collect::<Vec<_>>();
+        assert!(
+            messages
+                .iter()
+                .any(|message| matches!(message, ExecutionMessage::CommandError { .. }))
+        );
+        assert!(
+            !messages
+                .iter()
+                .any(|message| matches!(message, ExecutionMessage::CommandOutput { .. }))
+        );
+    }
+
+    #[test]
+    fn test_captured_output_is_usable_by_next_actions() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  greet:
+    actions:
+      - run: echo hi
+        capture: output_var
+      - echo "{{ output_var }} again"
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("greet", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(status.success());
+        let outputs = rx
+            .iter()
+            .filter_map(|message| match message {
+                ExecutionMessage::CommandOutput { output } => Some(output),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(outputs, vec!["hi", "hi again"]);
+    }
+
+    #[test]
+    fn test_continue_on_error() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  cleanup:
+    actions:
+      - run: exit 2
+        continue_on_error: true
+      - failing: {}
+        continue_on_error: true
+      - echo done
+  failing:
+    actions:
+      - exit 3
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("cleanup", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(status.success());
+        let messages = rx.iter().collect::<Vec<_>>();
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|message| **message == ExecutionMessage::FailureIgnored)
+                .count(),
+            2
+        );
+        assert!(messages.contains(&ExecutionMessage::CommandOutput {
+            output: "done".to_string()
+        }));
+    }
+
+    #[test]
+    fn test_retries() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  flaky:
+    actions:
+      - run: exit 4
+        retries: 2
+        retry_delay_ms: 1
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("flaky", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(status, TaskOutcome::Completed(ExitStatus::Exited(4)));
+        assert_eq!(
+            rx.iter()
+                .filter(|message| !matches!(
+                    message,
+                    ExecutionMessage::AboutToRunCommand { .. }
+                        | ExecutionMessage::CommandFinished { .. }
+                ) && !is_task_event(message))
+                .collect::<Vec<_>>(),
+            vec![
+                ExecutionMessage::RetryingCommand { attempt: 1, max: 2 },
+                ExecutionMessage::RetryingCommand { attempt: 2, max: 2 },
+                ExecutionMessage::CommandFailed { exit_code: Some(4) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_working_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let working_directory = dir.path().join("nested").join("dir");
+        let mut task = task(vec![command("echo hi")]);
+        task.working_directory = Some(working_directory.to_str().unwrap().parse().unwrap());
+        let mut context = TaskerieContext {
+            tasks: [("build".to_string(), task)].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("build", &mut ParamContext::default(), &tx)
+            .unwrap();
+        assert!(matches!(status, TaskOutcome::PreconditionFailed(_)));
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            ExecutionMessage::TaskStarted { .. }
+        ));
+        assert!(matches!(
+            rx.try_recv().unwrap(),
+            ExecutionMessage::WorkingDirectoryNotFound { .. }
+        ));
+        assert!(!working_directory.exists());
+
+        context.tasks["build"].create_working_directory = true;
+        let status = context
+            .run_task_by_name("build", &mut ParamContext::default(), &tx)
+            .unwrap();
+        assert!(status.success());
+        assert!(working_directory.is_dir());
+    }
+
+    #[test]
+    fn test_run_task_collect() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    actions:
+      - echo compiling
+      - run: exit 3
+        continue_on_error: true
+      - echo done
+"#,
+        )
+        .unwrap();
+
+        let report = context
+            .run_task_collect("build", ParamContext::default())
+            .unwrap();
+
+        assert!(report.success());
+        assert_eq!(report.output, "compiling\ndone\n");
+        assert_eq!(
+            report
+                .commands
+                .iter()
+                .map(|command| (command.command.as_str(), command.exit_code))
+                .collect::<Vec<_>>(),
+            vec![
+                ("echo compiling", Some(0)),
+                ("exit 3", Some(3)),
+                ("echo done", Some(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parallel_actions() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  checks:
+    parallel: true
+    actions:
+      - echo lint
+      - exit 5
+      - run: echo v1
+        capture: version
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let mut param_context = ParamContext::default();
+        let status = context
+            .run_task_by_name("checks", &mut param_context, &tx)
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(status, TaskOutcome::Completed(ExitStatus::Exited(5)));
+        assert_eq!(param_context.get("version").unwrap(), "v1");
+        let mut messages_by_action = IndexMap::<_, Vec<_>>::new();
+        for message in rx {
+            let (action_path, message) = message.into_action_message();
+            if !matches!(
+                message,
+                ExecutionMessage::AboutToRunCommand { .. }
+                    | ExecutionMessage::CommandFinished { .. }
+            ) && !is_task_event(&message)
+            {
+                messages_by_action
+                    .entry(action_path)
+                    .or_default()
+                    .push(message);
+            }
+        }
+        messages_by_action.sort_keys();
+        assert_eq!(
+            messages_by_action.into_iter().collect::<Vec<_>>(),
+            vec![
+                (
+                    vec![0],
+                    vec![
+                        ExecutionMessage::CommandOutput {
+                            output: "lint".to_string()
+                        },
+                        ExecutionMessage::CommandSucceeded,
+                    ]
+                ),
+                (
+                    vec![1],
+                    vec![ExecutionMessage::CommandFailed { exit_code: Some(5) }]
+                ),
+                (
+                    vec![2],
+                    vec![
+                        ExecutionMessage::CommandOutput {
+                            output: "v1".to_string()
+                        },
+                        ExecutionMessage::CommandSucceeded,
+                    ]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_max_parallel() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  checks:
+    parallel: true
+    max_parallel: 1
+    actions:
+      - echo a
+      - echo b
+      - echo c
+"#,
+        )
+        .unwrap();
+
+        let report = context
+            .run_task_collect("checks", ParamContext::default())
+            .unwrap();
+
+        assert!(report.success());
+        // A single worker takes the actions in order
+        assert_eq!(report.output, "a\nb\nc\n");
+        assert!(
+            report
+                .commands
+                .iter()
+                .all(|command| command.exit_code == Some(0))
+        );
+    }
+
+    #[test]
+    fn test_needs() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  install:
+    actions:
+      - echo install
+  build:
+    needs: [install]
+    actions:
+      - echo build
+  test:
+    needs: [install]
+    actions:
+      - echo test
+  ci:
+    needs: [build, test]
+    actions:
+      - echo ci
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("ci", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(status.success());
+        assert_eq!(
+            rx.iter()
+                .filter_map(|message| match message.into_action_message().1 {
+                    ExecutionMessage::RunningDependency { name } => Some(format!("needs {name}")),
+                    ExecutionMessage::CommandOutput { output } => Some(output),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            vec![
+                "needs build",
+                "needs install",
+                "install",
+                "build",
+                "needs test",
+                "test",
+                "ci"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_dependency_aborts() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  install:
+    actions:
+      - exit 2
+  build:
+    needs: [install]
+    actions:
+      - echo build
+"#,
+        )
+        .unwrap();
+
+        let report = context
+            .run_task_collect("build", ParamContext::default())
+            .unwrap();
+
+        assert_eq!(
+            report.outcome,
+            TaskOutcome::Completed(ExitStatus::Exited(2))
+        );
+        assert_eq!(report.commands.len(), 1);
+    }
+
+    #[test]
+    fn test_diamond_dependency_runs_once() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  d:
+    actions:
+      - echo d
+  b:
+    needs: [d]
+    actions:
+      - echo b
+  c:
+    needs: [d]
+    actions:
+      - echo c
+  a:
+    needs: [b, c]
+    actions:
+      - echo a
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("a", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(status.success());
+        let messages = rx
+            .iter()
+            .map(|message| message.into_action_message().1)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|message| **message
+                    == ExecutionMessage::CommandOutput {
+                        output: "d".to_string()
+                    })
+                .count(),
+            1
+        );
+        assert!(
+            messages.contains(&ExecutionMessage::DependencyAlreadySatisfied {
+                name: "d".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_dependency_runs_once_per_params() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    params:
+      target: {}
+    actions:
+      - echo build {{ target }}
+  all:
+    params:
+      target:
+        default: linux
+    needs:
+      - build:
+          target: linux
+      - build:
+          target: "{{ target }}"
+      - build:
+          target: windows
+    actions:
+      - echo done
+"#,
+        )
+        .unwrap();
+
+        let report = context
+            .run_task_collect("all", ParamContext::default())
+            .unwrap();
+
+        assert!(report.success());
+        assert_eq!(report.output, "build linux\nbuild windows\ndone\n");
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  slow:
+    actions:
+      - run: echo started; sleep 5
+        retries: 3
+      - run: echo never
+"#,
+        )
+        .unwrap();
+        let interrupter = Interrupter::default();
+        let options = ExecutionOptions {
+            interrupter: interrupter.clone(),
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let started = std::time::Instant::now();
+        let runner = thread::spawn(move || {
+            context.run_task_by_name_with_options(
+                "slow",
+                &mut ParamContext::default(),
+                &tx,
+                options,
+                |_, _| Ok(()),
+            )
+        });
+        let mut messages = Vec::new();
+        for message in rx {
+            if matches!(message, ExecutionMessage::CommandOutput { .. }) {
+                interrupter.interrupt();
+            }
+            if !is_task_event(&message) {
+                messages.push(message);
+            }
+        }
+
+        assert_eq!(runner.join().unwrap().unwrap(), TaskOutcome::Interrupted);
+        assert!(started.elapsed() < Duration::from_secs(4));
+        assert!(matches!(
+            messages[..],
+            [
+                ExecutionMessage::AboutToRunCommand { .. },
+                ExecutionMessage::CommandOutput { .. },
+                ExecutionMessage::CommandFinished {
+                    exit_code: None,
+                    ..
+                },
+                ExecutionMessage::CommandFailed { exit_code: None },
+                ExecutionMessage::Interrupted,
+            ]
+        ));
+    }
+
+    fn forwarded_lines(output: &[u8]) -> Vec<ExecutionMessage> {
+        let (tx, rx) = mpsc::channel();
+        forward_lines(output, &tx, |output| ExecutionMessage::CommandOutput {
+            output,
+        })
+        .unwrap();
+        drop(tx);
+        rx.iter().collect()
+    }
+
+    fn outputs(lines: &[&str]) -> Vec<ExecutionMessage> {
+        lines
+            .iter()
+            .map(|line| ExecutionMessage::CommandOutput {
+                output: line.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_forward_lines_without_trailing_newline() {
+        assert_eq!(forwarded_lines(b"first\nlast"), outputs(&["first", "last"]));
+    }
+
+    #[test]
+    fn test_forward_lines_on_carriage_returns() {
+        let messages = forwarded_lines(b"10%\r50%\r100%\r\ndone\r\n\nend\n");
+        assert_eq!(
+            messages,
+            outputs(&["10%", "50%", "100%", "done", "", "end"])
+        );
+    }
+
+    #[test]
+    fn test_forward_lines_with_invalid_utf8() {
+        let messages = forwarded_lines(b"caf\xe9\nnext\n");
+        assert_eq!(messages, outputs(&["caf\u{FFFD}", "next"]));
+
+        // Started without shell, PowerShell having no `printf`
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  legacy:
+    actions:
+      - run: printf 'caf\351\nnext\n'
+        shell: false
+"#,
+        )
+        .unwrap();
+        let (tx, rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name("legacy", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+        assert!(outcome.success());
+        assert_eq!(
+            rx.iter()
+                .filter(|message| matches!(message, ExecutionMessage::CommandOutput { .. }))
+                .collect::<Vec<_>>(),
+            outputs(&["caf\u{FFFD}", "next"])
+        );
+    }
+
+    #[test]
+    fn test_resolved_parameters_are_reported() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  greet:
+    params:
+      name:
+      greeting:
+        default: hello
+    actions:
+      - run: echo {{ greeting }} {{ name }}
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let mut param_context = ParamContext::default();
+        param_context.set("name", "world");
+        context
+            .run_task_by_name("greet", &mut param_context, &tx)
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(
+            rx.iter()
+                .filter(|message| matches!(message, ExecutionMessage::ParameterResolved { .. }))
+                .collect::<Vec<_>>(),
+            vec![
+                ExecutionMessage::ParameterResolved {
+                    parameter_name: "name".to_string(),
+                    value: "world".to_string(),
+                },
+                ExecutionMessage::ParameterResolved {
+                    parameter_name: "greeting".to_string(),
+                    value: "hello".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_command_duration() {
+        let context = TaskerieContext {
+            tasks: [("wait".to_string(), task(vec![command("sleep 0.2")]))]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        context
+            .run_task_by_name("wait", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        let durations = rx
+            .iter()
+            .filter_map(|message| match message {
+                ExecutionMessage::CommandFinished {
+                    duration_ms,
+                    exit_code: Some(0),
+                } => Some(duration_ms),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(matches!(durations[..], [duration_ms] if (200..2000).contains(&duration_ms)));
+    }
+
+    #[test]
+    fn test_variable_program_name() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  install:
+    params:
+      pm:
+    actions:
+      - run: "{{ pm }} install"
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let mut param_context = ParamContext::default();
+        param_context.set("pm", "npm");
+        context
+            .run_task_by_name_with_options(
+                "install",
+                &mut param_context,
+                &tx,
+                ExecutionOptions {
+                    dry_run: true,
+                    ..Default::default()
+                },
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        drop(tx);
+
+        assert!(rx.iter().any(|message| matches!(
+            message,
+            ExecutionMessage::AboutToRunCommand { command, .. } if command == "npm install"
+        )));
+    }
+
+    #[test]
+    fn test_vars_precedence() {
+        let context = crate::load_from_str(
+            r#"
+vars:
+  given: from_vars
+  with_default: from_vars
+  without_default: from_vars
+  undeclared: from_vars
+tasks:
+  show:
+    params:
+      given:
+        default: from_default
+      with_default:
+        default: from_default
+      without_default:
+    actions:
+      - run: echo {{ given }} {{ with_default }} {{ without_default }} {{ undeclared }}
+        capture: output
+"#,
+        )
+        .unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let mut param_context = ParamContext::default();
+        param_context.set("given", "from_caller");
+        let status = context
+            .run_task_by_name("show", &mut param_context, &tx)
+            .unwrap();
+
+        assert!(status.success());
+        assert_eq!(
+            param_context.get("output").and_then(ParamValue::as_scalar),
+            Some("from_caller from_default from_vars from_vars")
+        );
+    }
+
+    #[test]
+    fn test_chained_defaults() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    params:
+      env:
+      target:
+        default: "{{ env }}-build"
+      archive:
+        default: "{{ target }}.zip"
+    actions:
+      - run: echo {{ archive }}
+        capture: output
+"#,
+        )
+        .unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let mut param_context = ParamContext::default();
+        param_context.set("env", "prod");
+        context
+            .run_task_by_name("build", &mut param_context, &tx)
+            .unwrap();
+        assert_eq!(
+            param_context.get("output").and_then(ParamValue::as_scalar),
+            Some("prod-build.zip")
+        );
+
+        let mut param_context = ParamContext::default();
+        param_context.set("env", "dev");
+        param_context.set("target", "custom");
+        context
+            .run_task_by_name("build", &mut param_context, &tx)
+            .unwrap();
+        assert_eq!(
+            param_context.get("output").and_then(ParamValue::as_scalar),
+            Some("custom.zip")
+        );
+    }
+
+    #[test]
+    fn test_default_referring_to_missing_param() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    params:
+      target:
+        default: "{{ env }}-build"
+      env:
+    actions:
+      - echo {{ target }}
+"#,
+        )
+        .unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let error = context
+            .run_task_by_name("build", &mut ParamContext::default(), &tx)
+            .unwrap_err();
+        assert_eq!(
+            format!("{:#}", anyhow::Error::from(error)),
+            "Could not render the default value of parameter 'target': Could not find value for \
+             param 'env' while rendering '{{ env }}-build'"
+        );
+    }
+
+    #[test]
+    fn test_multiline_command_runs_as_one_invocation() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  check:
+    actions:
+      - |
+        if ($true) {
+          echo yes
+        }
+  script:
+    actions:
+      - |
+        echo first
+        exit 3
+        echo unreachable
+"#,
+        )
+        .unwrap();
+
+        let model::Action::Command(check) = &context.tasks["check"].actions[0] else {
+            panic!("Expected a command");
+        };
+        assert_eq!(check.command.template(), "if ($true) {\n  echo yes\n}\n");
+
+        let (tx, rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name("script", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(outcome, TaskOutcome::Completed(ExitStatus::Exited(3)));
+        assert_eq!(
+            rx.iter()
+                .filter(
+                    |message| !matches!(message, ExecutionMessage::CommandFinished { .. })
+                        && !is_task_event(message)
+                )
+                .collect::<Vec<_>>(),
+            vec![
+                ExecutionMessage::AboutToRunCommand {
+                    command: "echo first\nexit 3\necho unreachable\n".to_string(),
+                    working_directory: std::env::current_dir()
+                        .unwrap()
+                        .canonicalize()
+                        .unwrap()
+                        .display()
+                        .to_string(),
+                    label: None,
+                },
+                ExecutionMessage::CommandOutput {
+                    output: "first".to_string(),
+                },
+                ExecutionMessage::CommandFailed { exit_code: Some(3) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_silent_command_output_is_not_sent() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  install:
+    actions:
+      - run: echo noisy
+        silent: true
+        capture: installed
+      - echo done
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let mut param_context = ParamContext::default();
+        let outcome = context
+            .run_task_by_name("install", &mut param_context, &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(outcome.success());
+        assert_eq!(
+            param_context
+                .get("installed")
+                .and_then(ParamValue::as_scalar),
+            Some("noisy")
+        );
+        let messages = rx.iter().collect::<Vec<_>>();
+        assert_eq!(
+            messages
+                .iter()
+                .filter_map(|message| match message {
+                    ExecutionMessage::CommandOutput { output } => Some(output.as_str()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>(),
+            vec!["done"]
+        );
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|message| matches!(message, ExecutionMessage::CommandSucceeded))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_command_stdin() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  greet:
+    params:
+      name:
+        default: world
+    actions:
+      - run: cat
+        stdin: |
+          hello
+          {{ name }}
+        capture: output
+"#,
+        )
+        .unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let mut param_context = ParamContext::default();
+        let outcome = context
+            .run_task_by_name("greet", &mut param_context, &tx)
+            .unwrap();
+
+        assert!(outcome.success());
+        assert_eq!(
+            param_context.get("output").and_then(ParamValue::as_scalar),
+            Some("hello\nworld")
+        );
+    }
+
+    #[test]
+    fn test_large_command_stdin() {
+        // Larger than a pipe buffer, so that input and output have to be handled concurrently
+        let input = "line\n".repeat(100_000);
+        let cat = Action::Command(Box::new(model::action::Command {
+            command: "cat".parse().unwrap(),
+            stdin: Some(input.parse().unwrap()),
+            capture: Some("output".to_string()),
+            silent: true,
+            ..Default::default()
+        }));
+        let context = TaskerieContext {
+            tasks: [("cat".to_string(), task(vec![cat]))].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let (tx, _rx) = mpsc::channel();
+        let mut param_context = ParamContext::default();
+        let outcome = context
+            .run_task_by_name("cat", &mut param_context, &tx)
+            .unwrap();
+
+        assert!(outcome.success());
+        assert_eq!(
+            param_context.get("output").unwrap().to_string().len(),
+            input.trim().len()
+        );
+    }
+
+    #[test]
+    fn test_tasks_with_tag() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    tags: [ci, rust]
+    actions:
+      - echo build
+  run:
+    actions:
+      - echo run
+  lint:
+    tags: [ci]
+    actions:
+      - echo lint
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            context
+                .tasks_with_tag("ci")
+                .map(|(name, _)| name)
+                .collect::<Vec<_>>(),
+            vec!["build", "lint"]
+        );
+        assert_eq!(context.tasks_with_tag("deploy").count(), 0);
+        assert!(
+            crate::load_from_str("tasks:\n  a:\n    tags: ['']\n    actions:\n      - echo a\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_task_call_param_render_error() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  release:
+    actions:
+      - deploy:
+          tag: "v{{ version }}"
+  deploy:
+    params:
+      tag:
+    actions:
+      - echo {{ tag }}
+"#,
+        )
+        .unwrap();
+
+        let (tx, _rx) = mpsc::channel();
+        let error = context
+            .run_task_by_name("release", &mut ParamContext::default(), &tx)
+            .unwrap_err();
+
+        assert!(matches!(
+            &error,
+            RunError::TaskCallParam {
+                task,
+                parameter,
+                source: RenderError { kind: RenderErrorKind::MissingParam(name), .. },
+            } if task == "deploy" && parameter == "tag" && name == "version"
+        ));
+        assert_eq!(
+            format!("{:#}", anyhow::Error::from(error)),
+            "While preparing call to task 'deploy', parameter 'tag': Could not find value for \
+             param 'version' while rendering 'v{{ version }}'"
+        );
+    }
+
+    #[test]
+    fn test_sub_task_messages_tell_their_origin() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  root:
+    actions:
+      - echo root
+      - sub:
+  sub:
+    actions:
+      - echo sub
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name("root", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(outcome.success());
+        let outputs = rx
+            .iter()
+            .map(ExecutionMessage::into_origin_message)
+            .filter_map(|(origin, message)| match message {
+                ExecutionMessage::CommandOutput { output } => Some((output, origin)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(outputs.len(), 2);
+        assert_eq!(outputs[0].0, "root");
+        assert_eq!(outputs[0].1.depth(), 0);
+        assert_eq!(outputs[1].0, "sub");
+        assert_eq!(outputs[1].1.depth(), 1);
+        assert_eq!(outputs[1].1.task_path, vec!["sub".to_string()]);
+    }
+
+    fn is_task_event(message: &ExecutionMessage) -> bool {
+        matches!(
+            message,
+            ExecutionMessage::TaskStarted { .. } | ExecutionMessage::TaskFinished { .. }
+        )
+    }
+
+    /// Messages received, without the ones telling when tasks start and finish.
+    fn without_task_events(rx: &mpsc::Receiver<ExecutionMessage>) -> Vec<ExecutionMessage> {
+        rx.iter()
+            .filter(|message| !is_task_event(message))
+            .collect()
+    }
+
+    /// Options running the commands with `sh`, for the tests written in POSIX shell syntax.
+    fn posix_shell_options() -> ExecutionOptions {
+        ExecutionOptions {
+            shell: Some("sh -c".to_string()),
+            ..ExecutionOptions::default()
+        }
+    }
+
+    /// Whether PowerShell itself is installed, and not a stand-in running another shell.
+    fn has_powershell() -> bool {
+        Exec::cmd("pwsh")
+            .args(&["-NoProfile", "-NonInteractive", "-Command"])
+            .arg("$PSVersionTable.PSVersion.Major")
+            .stdout(Redirection::Pipe)
+            .stderr(Redirection::Pipe)
+            .capture()
+            .is_ok_and(|capture| {
+                capture.success() && capture.stdout_str().trim().parse::<u32>().is_ok()
+            })
+    }
+
+    fn command_outputs(rx: &mpsc::Receiver<ExecutionMessage>) -> Vec<String> {
+        rx.try_iter()
+            .filter_map(|message| match message {
+                ExecutionMessage::CommandOutput { output } => Some(output),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_persistent_shell_keeps_state_between_actions() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("nested")).unwrap();
+        let config = |persistent_shell: bool| {
+            format!(
+                r#"
+tasks:
+  build:
+    persistent_shell: {persistent_shell}
+    actions:
+      - cd nested
+      - FOO=bar
+      - echo "${{PWD##*/}} $FOO"
+      - printf unterminated
+"#
+            )
+        };
+
+        for persistent_shell in [true, false] {
+            let mut context = crate::load_from_str(&config(persistent_shell)).unwrap();
+            context.base_dir = dir.path().to_path_buf();
+            let (tx, rx) = mpsc::channel();
+            let outcome = context
+                .run_task_by_name_with_options(
+                    "build",
+                    &mut ParamContext::default(),
+                    &tx,
+                    posix_shell_options(),
+                    |_, _| Ok(()),
+                )
+                .unwrap();
+
+            assert!(outcome.success());
+            let outputs = command_outputs(&rx);
+            assert_eq!(outputs[0] == "nested bar", persistent_shell);
+            assert_eq!(outputs[1], "unterminated");
+        }
+    }
+
+    #[test]
+    fn test_persistent_shell_exit_codes() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    persistent_shell: true
+    actions:
+      - run: sh -c "exit 3"
+        continue_on_error: true
+      - run: exit 5
+        continue_on_error: true
+      - echo restarted
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name_with_options(
+                "build",
+                &mut ParamContext::default(),
+                &tx,
+                posix_shell_options(),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        drop(tx);
+
+        assert!(outcome.success());
+        let messages = rx.iter().collect::<Vec<_>>();
+        assert!(messages.contains(&ExecutionMessage::CommandFailed { exit_code: Some(3) }));
+        assert!(messages.contains(&ExecutionMessage::CommandFailed { exit_code: Some(5) }));
+        assert!(messages.contains(&ExecutionMessage::CommandOutput {
+            output: "restarted".to_string()
+        }));
+    }
+
+    #[test]
+    fn test_persistent_shell_allowed_exit_codes() {
+        let config = |persistent_shell: bool| {
+            format!(
+                r#"
+tasks:
+  search:
+    persistent_shell: {persistent_shell}
+    actions:
+      - run: sh -c "exit 3"
+        allowed_exit_codes: [3]
+      - run: sh -c "exit 4"
+        allowed_exit_codes: [3]
+"#
+            )
+        };
+
+        // The exit code of a native command is the same whether the shell is kept or not
+        for persistent_shell in [true, false] {
+            let context = crate::load_from_str(&config(persistent_shell)).unwrap();
+            let (tx, rx) = mpsc::channel();
+            let outcome = context
+                .run_task_by_name_with_options(
+                    "search",
+                    &mut ParamContext::default(),
+                    &tx,
+                    posix_shell_options(),
+                    |_, _| Ok(()),
+                )
+                .unwrap();
+            drop(tx);
+
+            assert_eq!(outcome, TaskOutcome::Completed(ExitStatus::Exited(4)));
+            let messages = rx.iter().collect::<Vec<_>>();
+            assert!(messages.contains(&ExecutionMessage::CommandSucceeded));
+            assert!(messages.contains(&ExecutionMessage::CommandFailed { exit_code: Some(4) }));
+        }
+    }
+
+    #[test]
+    fn test_persistent_powershell() {
+        if !has_powershell() {
+            // Only runs where PowerShell is installed
+            return;
+        }
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    persistent_shell: true
+    actions:
+      - $env:FOO = "bar"
+      - Write-Output "$env:FOO"
+      - run: pwsh -NoProfile -Command "exit 3"
+        continue_on_error: true
+      - run: Get-Item /taskerie-missing-path
+        continue_on_error: true
+      - Write-Output done
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name("build", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(outcome.success());
+        let messages = rx.iter().collect::<Vec<_>>();
+        let failures = messages
+            .iter()
+            .filter_map(|message| match message {
+                ExecutionMessage::CommandFailed { exit_code } => Some(*exit_code),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // The failed cmdlet does not report the exit code of the native command before it
+        assert_eq!(failures, vec![Some(3), Some(1)]);
+        let outputs = messages
+            .iter()
+            .filter_map(|message| match message {
+                ExecutionMessage::CommandOutput { output } => Some(output.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(outputs, vec!["bar", "done"]);
+    }
+
+    #[test]
+    fn test_persistent_shell_cannot_run_in_parallel() {
+        let error = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    persistent_shell: true
+    parallel: true
+    actions:
+      - echo a
+"#,
+        )
+        .unwrap_err();
+
+        assert!(format!("{error:#}").contains("Parallel actions cannot share a persistent shell"));
+    }
+
+    #[test]
+    fn test_max_output_bytes() {
+        let context = crate::load_from_str(
+            r#"
+max_output_bytes: 10
+tasks:
+  global:
+    actions:
+      - seq 1 100000
+  task:
+    max_output_bytes: 4
+    actions:
+      - run: seq 1 100000
+        capture: numbers
+      - echo "{{ numbers }}"
+"#,
+        )
+        .unwrap();
+
+        // PowerShell has no `seq`
+        let (tx, rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name_with_options(
+                "global",
+                &mut ParamContext::default(),
+                &tx,
+                posix_shell_options(),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        assert!(outcome.success());
+        let messages = rx
+            .try_iter()
+            .filter(|message| {
+                matches!(
+                    message,
+                    ExecutionMessage::CommandOutput { .. }
+                        | ExecutionMessage::OutputTruncated { .. }
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            ["1", "2", "3", "4", "5"]
+                .map(|output| ExecutionMessage::CommandOutput {
+                    output: output.to_string()
+                })
+                .into_iter()
+                .chain([ExecutionMessage::OutputTruncated { limit: 10 }])
+                .collect::<Vec<_>>()
+        );
+
+        let outcome = context
+            .run_task_by_name_with_options(
+                "task",
+                &mut ParamContext::default(),
+                &tx,
+                posix_shell_options(),
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        assert!(outcome.success());
+        assert_eq!(command_outputs(&rx), vec!["1", "2", "1", "2"]);
+    }
+
+    #[test]
+    fn test_split_operators() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    actions:
+      - run: echo a && exit 3 && echo skipped; echo b && echo c
+        split_operators: true
+  failing:
+    actions:
+      - run: echo a; exit 4 && echo skipped
+        split_operators: true
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name("build", &mut ParamContext::default(), &tx)
+            .unwrap();
+        assert!(outcome.success());
+        assert_eq!(command_outputs(&rx), vec!["a", "b", "c"]);
+
+        let outcome = context
+            .run_task_by_name("failing", &mut ParamContext::default(), &tx)
+            .unwrap();
+        assert_eq!(outcome, TaskOutcome::Completed(ExitStatus::Exited(4)));
+        assert_eq!(command_outputs(&rx), vec!["a"]);
+    }
+
+    #[test]
+    fn test_run_without_sender() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    actions:
+      - echo building
+      - sub:
+  sub:
+    parallel: true
+    actions:
+      - echo a
+      - exit 2
+"#,
+        )
+        .unwrap();
+
+        let outcome = context
+            .run_task_by_name_with_sender("build", &mut ParamContext::default(), None)
+            .unwrap();
+
+        assert_eq!(outcome, TaskOutcome::Completed(ExitStatus::Exited(2)));
+    }
+
+    #[test]
+    fn test_strip_verbatim_prefix() {
+        assert_eq!(strip_verbatim_prefix(r"\\?\C:\project"), r"C:\project");
+        assert_eq!(
+            strip_verbatim_prefix(r"\\?\UNC\server\share\dir"),
+            r"\\server\share\dir"
+        );
+        assert_eq!(strip_verbatim_prefix("/home/project"), "/home/project");
+    }
+
+    #[test]
+    fn test_reported_working_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let base_dir = dir.path().canonicalize().unwrap();
+        let context = TaskerieContext {
+            tasks: [
+                ("found".to_string(), {
+                    let mut task = task(vec![command("echo hi")]);
+                    task.working_directory = Some(".".parse().unwrap());
+                    task
+                }),
+                ("missing".to_string(), {
+                    let mut task = task(vec![command("echo hi")]);
+                    task.working_directory = Some("missing".parse().unwrap());
+                    task
+                }),
+            ]
+            .into_iter()
+            .collect(),
+            base_dir: base_dir.clone(),
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        for name in ["found", "missing"] {
+            context
+                .run_task_by_name(name, &mut ParamContext::default(), &tx)
+                .unwrap();
+        }
+        drop(tx);
+
+        let paths = rx
+            .iter()
+            .filter_map(|message| match message {
+                ExecutionMessage::WorkingDirectoryResolved { path, .. }
+                | ExecutionMessage::WorkingDirectoryNotFound { path }
+                | ExecutionMessage::AboutToRunCommand {
+                    working_directory: path,
+                    ..
+                } => Some(path),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let base_dir = display_path(&base_dir);
+        assert_eq!(
+            paths,
+            vec![
+                base_dir.clone(),
+                base_dir.clone(),
+                display_path(&Path::new(&base_dir).join("missing")),
+            ]
+        );
+        assert!(paths.iter().all(|path| !path.starts_with(r"\\?\")));
+    }
+
+    #[test]
+    fn test_clean_env() {
+        let config = |clean_env: bool| {
+            format!(
+                r#"
+tasks:
+  build:
+    clean_env: {clean_env}
+    env:
+      DECLARED: declared
+    actions:
+      - echo "[$CARGO_PKG_NAME] [$DECLARED]"
+"#
+            )
+        };
+
+        // Set by cargo when running the tests
+        let host_value = std::env::var("CARGO_PKG_NAME").unwrap();
+        for (clean_env, expected) in [
+            (true, "[] [declared]".to_string()),
+            (false, format!("[{host_value}] [declared]")),
+        ] {
+            let context = crate::load_from_str(&config(clean_env)).unwrap();
+            let (tx, rx) = mpsc::channel();
+            let outcome = context
+                .run_task_by_name_with_options(
+                    "build",
+                    &mut ParamContext::default(),
+                    &tx,
+                    posix_shell_options(),
+                    |_, _| Ok(()),
+                )
+                .unwrap();
+
+            assert!(outcome.success());
+            assert_eq!(command_outputs(&rx), vec![expected]);
+        }
+    }
+
+    #[test]
+    fn test_allowed_exit_codes() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  search:
+    actions:
+      - run: exit 1
+        allowed_exit_codes: [1]
+      - echo searched
+      - run: exit 2
+        allowed_exit_codes: [1]
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("search", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(status, TaskOutcome::Completed(ExitStatus::Exited(2)));
+        let messages = without_task_events(&rx);
+        assert!(messages.contains(&ExecutionMessage::CommandOutput {
+            output: "searched".to_string()
+        }));
+        assert_eq!(
+            messages
+                .iter()
+                .filter(|message| matches!(
+                    message,
+                    ExecutionMessage::CommandSucceeded | ExecutionMessage::CommandFailed { .. }
+                ))
+                .collect::<Vec<_>>(),
+            [
+                &ExecutionMessage::CommandSucceeded,
+                &ExecutionMessage::CommandSucceeded,
+                &ExecutionMessage::CommandFailed { exit_code: Some(2) },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_command_without_shell() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  print:
+    params:
+      value:
+        default: "a  b $HOME; exit 3"
+    actions:
+      - run: printf "[%s]\n" "{{ value }}" '$PATH' {{ value }}
+        shell: false
+"#,
+        )
+        .unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let status = context
+            .run_task_by_name("print", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(status.success());
+        assert_eq!(
+            command_outputs(&rx),
+            ["[a  b $HOME; exit 3]", "[$PATH]", "[a  b $HOME; exit 3]"]
+        );
+    }
+
+    #[test]
+    fn test_resolve_shell() {
+        assert_eq!(
+            resolve_shell(None).unwrap(),
+            ["pwsh", "-NonInteractive", "-Command"]
+        );
+        assert_eq!(
+            resolve_shell(Some("'/opt/my shell/bash' -e -c")).unwrap(),
+            ["/opt/my shell/bash", "-e", "-c"]
+        );
+        assert_eq!(
+            resolve_shell(Some("bash \"-c")).unwrap_err().to_string(),
+            "Invalid shell in TASKERIE_SHELL: Unclosed quote in 'bash \"-c'"
+        );
+    }
+
+    #[test]
+    fn test_program_runs_command_with_shell() {
+        let shell = resolve_shell(Some("sh -c")).unwrap();
+        let output = program("echo $0", &[], &shell)
+            .stdout(Redirection::Pipe)
+            .capture()
+            .unwrap();
+        assert_eq!(output.stdout_str().trim(), "sh");
+    }
+
+    fn confirmed_task_context() -> TaskerieContext {
+        let mut deploy = task(vec![command("echo deployed")]);
+        deploy.confirm = Some("Deploy to production?".to_string());
+        let mut tasks = IndexMap::new();
+        tasks.insert("deploy".to_string(), deploy);
+        TaskerieContext {
+            tasks,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_declined_confirmation() {
+        let context = confirmed_task_context();
+        let (confirmation_tx, confirmation_rx) = mpsc::channel();
+        confirmation_tx.send(false).unwrap();
+        let (tx, rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name_with_options(
+                "deploy",
+                &mut ParamContext::default(),
+                &tx,
+                ExecutionOptions {
+                    confirmations: Some(confirmation_rx),
+                    ..Default::default()
+                },
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        drop(tx);
+
+        assert_eq!(
+            outcome,
+            TaskOutcome::PreconditionFailed(
+                "Task 'deploy' was vetoed: confirmation declined".to_string()
+            )
+        );
+        assert_eq!(
+            without_task_events(&rx),
+            [
+                ExecutionMessage::ConfirmationRequired {
+                    task_name: "deploy".to_string(),
+                    prompt: "Deploy to production?".to_string(),
+                },
+                ExecutionMessage::TaskVetoed {
+                    task_name: "deploy".to_string(),
+                    reason: "confirmation declined".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_accepted_confirmation() {
+        let context = confirmed_task_context();
+        let (confirmation_tx, confirmation_rx) = mpsc::channel();
+        confirmation_tx.send(true).unwrap();
+        let (tx, _rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name_with_options(
+                "deploy",
+                &mut ParamContext::default(),
+                &tx,
+                ExecutionOptions {
+                    confirmations: Some(confirmation_rx),
+                    ..Default::default()
+                },
+                |_, _| Ok(()),
+            )
+            .unwrap();
+
+        assert!(outcome.success());
+    }
+
+    #[test]
+    fn test_confirmation_without_prompt() {
+        let context = confirmed_task_context();
+        let (tx, _rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name("deploy", &mut ParamContext::default(), &tx)
+            .unwrap();
+        assert_eq!(
+            outcome,
+            TaskOutcome::PreconditionFailed(
+                "Task 'deploy' was vetoed: confirmation required but it cannot be asked for"
+                    .to_string()
+            )
+        );
+
+        let outcome = context
+            .run_task_by_name_with_options(
+                "deploy",
+                &mut ParamContext::default(),
+                &tx,
+                ExecutionOptions {
+                    assume_yes: true,
+                    ..Default::default()
+                },
+                |_, _| Ok(()),
+            )
+            .unwrap();
+        assert!(outcome.success());
+    }
+
+    #[test]
+    fn test_secret_params_are_masked() {
+        let mut login = task(vec![command("echo user:{{ password }}@host")]);
+        login.params.insert(
+            "password".to_string(),
+            model::task::Param {
+                secret: true,
+                ..Default::default()
+            },
+        );
+        let mut tasks = IndexMap::new();
+        tasks.insert("login".to_string(), login);
+        tasks.insert(
+            "ci".to_string(),
+            task(vec![Action::TaskCall(model::action::TaskCall {
+                name: "login".to_string(),
+                params: [("password".to_string(), "hunter2".parse().unwrap())]
+                    .into_iter()
+                    .collect(),
+                continue_on_error: false,
+            })]),
+        );
+        let context = TaskerieContext {
+            tasks,
+            ..Default::default()
+        };
+
+        let (tx, rx) = mpsc::channel();
+        let outcome = context
+            .run_task_by_name("ci", &mut ParamContext::default(), &tx)
+            .unwrap();
+        drop(tx);
+
+        assert!(outcome.success());
+        let messages = rx
+            .iter()
+            .map(|message| message.into_action_message().1)
+            .filter(|message| {
+                matches!(
+                    message,
+                    ExecutionMessage::ParameterResolved { .. }
+                        | ExecutionMessage::AboutToRunCommand { .. }
+                        | ExecutionMessage::CommandOutput { .. }
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            [
+                ExecutionMessage::ParameterResolved {
+                    parameter_name: "password".to_string(),
+                    value: SECRET_MASK.to_string(),
+                },
+                ExecutionMessage::AboutToRunCommand {
+                    command: "echo user:****@host".to_string(),
+                    working_directory: display_path(&Path::new("./").canonicalize().unwrap()),
+                    label: None,
+                },
+                ExecutionMessage::CommandOutput {
+                    output: "user:****@host".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_secret_params_are_masked_in_errors() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  deploy:
+    params:
+      token:
+        default: hunter2
+        secret: true
+    actions:
+      - run: taskerie-missing-program --token {{ token }}
+        shell: false
+"#,
+        )
+        .unwrap();
+
+        let error = context
+            .run_task_by_name_with_sender("deploy", &mut ParamContext::default(), None)
+            .unwrap_err();
+
+        let RunError::Spawn { command, .. } = &error else {
+            panic!("Unexpected error {error:?}");
+        };
+        assert_eq!(command, "taskerie-missing-program --token ****");
+        assert!(!format!("{error}").contains("hunter2"));
+    }
+
+    #[test]
+    fn test_mask_secrets() {
+        let secrets = ["abc def", "abc", "x"].map(ToString::to_string);
+        let mut text = "abc def abcx abd".to_string();
+        mask_secrets(&mut text, &secrets);
+        assert_eq!(text, "**** ******** abd");
+    }
 }
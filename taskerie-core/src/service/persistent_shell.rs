@@ -0,0 +1,273 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    sync::{Arc, Mutex, PoisonError, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use subprocess::{Exec, ExitStatus, Popen, PopenError, Redirection};
+
+use super::{CommandEnv, Execution, OutputForwarder, PROCESS_POLL_INTERVAL, forward_lines};
+use crate::{message::ExecutionMessage, model::RunError};
+
+/// Printed by the shell after each command, followed by its exit status: a number for POSIX
+/// shells, `True` or `False` then the exit code of the last native command for PowerShell.
+const COMMAND_END_MARKER: &str = "__taskerie_command_done__";
+
+/// Time given to the shell to exit once its input is closed before it is killed.
+pub(super) const SHELL_EXIT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A shell running the commands of a task one after the other, so that a command sees the
+/// working directory and variables changed by the previous ones.
+///
+/// The shell starts with the working directory and environment of the first command it runs. It
+/// is started again when a command exits it.
+#[derive(Default)]
+pub(super) struct PersistentShell {
+    running: Option<RunningShell>,
+}
+
+struct RunningShell {
+    process: Arc<Mutex<Popen>>,
+    /// Closed when the shell is dropped.
+    input: Option<File>,
+    /// Output lines of the shell, its standard error ones being sent as they come. Error lines
+    /// written by a command after its last output line may thus be reported with the next one.
+    lines: mpsc::Receiver<ExecutionMessage>,
+}
+
+impl PersistentShell {
+    /// Run `command` in the shell, forwarding its output until it is done. Returns its status
+    /// along with the time it ran for.
+    ///
+    /// The shell is started as `program` when given, PowerShell otherwise.
+    pub(super) fn run(
+        &mut self,
+        command: &str,
+        program: Option<&str>,
+        current_dir: &Path,
+        env: &CommandEnv,
+        execution: &Execution,
+        output: &mut OutputForwarder,
+    ) -> Result<(ExitStatus, Duration), RunError> {
+        let mut shell = match self.running.take() {
+            Some(shell) => shell,
+            None => RunningShell::start(program, current_dir, env).map_err(|source| {
+                RunError::Spawn {
+                    command: command.to_string(),
+                    source,
+                }
+            })?,
+        };
+        let interrupter = &execution.interrupter;
+        let _registration = interrupter.register(&shell.process);
+        let lock_process = || shell.process.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let started_at = Instant::now();
+        let input = shell
+            .input
+            .as_mut()
+            .expect("The input is open until the shell is dropped");
+        let written = input
+            .write_all(shell_input(command, program).as_bytes())
+            .and_then(|()| input.flush());
+        // A command exiting the shell closes its input before the marker is written, its status
+        // is then read once the output closes
+        if let Err(source) = written
+            && source.kind() != io::ErrorKind::BrokenPipe
+        {
+            return Err(RunError::ShellInput {
+                command: command.to_string(),
+                source,
+            });
+        }
+
+        let command_status = loop {
+            match shell.lines.recv_timeout(PROCESS_POLL_INTERVAL) {
+                Ok(ExecutionMessage::CommandOutput { output: ref line })
+                    if let Some((last_output, status)) = command_end(line) =>
+                {
+                    if let Some(last_output) = last_output {
+                        output.forward(last_output)?;
+                    }
+                    break Some(status);
+                }
+                Ok(message) => output.forward(message)?,
+                Err(mpsc::RecvTimeoutError::Timeout) => {}
+                Err(mpsc::RecvTimeoutError::Disconnected) => break None,
+            }
+            // The processes started by an interrupted command may keep the output open
+            if interrupter.is_interrupted() && lock_process().poll().is_some() {
+                break None;
+            }
+        };
+        let duration = started_at.elapsed();
+
+        if let Some(status) = command_status {
+            self.running = Some(shell);
+            return Ok((status, duration));
+        }
+        // The command exited the shell, or was interrupted, the exit status of the shell is the
+        // one of the command
+        loop {
+            if let Some(status) = lock_process().wait_timeout(PROCESS_POLL_INTERVAL)? {
+                return Ok((status, duration));
+            }
+        }
+    }
+}
+
+impl RunningShell {
+    fn start(
+        program: Option<&str>,
+        current_dir: &Path,
+        env: &CommandEnv,
+    ) -> Result<RunningShell, PopenError> {
+        let (program, arguments) = shell_program(program);
+        let mut process = env
+            .apply(Exec::cmd(program).args(arguments))
+            .cwd(current_dir)
+            .stdin(Redirection::Pipe)
+            .stdout(Redirection::Pipe)
+            .stderr(Redirection::Pipe)
+            .popen()?;
+        let (Some(input), Some(stdout), Some(stderr)) = (
+            process.stdin.take(),
+            process.stdout.take(),
+            process.stderr.take(),
+        ) else {
+            unreachable!("The input and output of the shell are piped");
+        };
+
+        // The readers stop on their own once the shell exits, their errors showing up as a
+        // closed output
+        let (line_tx, lines) = mpsc::channel();
+        let stdout_line_tx = line_tx.clone();
+        thread::spawn(move || {
+            forward_lines(stdout, &stdout_line_tx, |output| {
+                ExecutionMessage::CommandOutput { output }
+            })
+        });
+        thread::spawn(move || {
+            forward_lines(stderr, &line_tx, |output| ExecutionMessage::CommandError {
+                output,
+            })
+        });
+
+        Ok(RunningShell {
+            process: Arc::new(Mutex::new(process)),
+            input: Some(input),
+            lines,
+        })
+    }
+}
+
+impl Drop for RunningShell {
+    fn drop(&mut self) {
+        // Closing the input lets the shell exit on its own
+        drop(self.input.take());
+        let mut process = self.process.lock().unwrap_or_else(PoisonError::into_inner);
+        if !matches!(process.wait_timeout(SHELL_EXIT_TIMEOUT), Ok(Some(_))) {
+            let _ = process.kill();
+            let _ = process.wait();
+        }
+    }
+}
+
+/// Program and arguments of a shell started as `program`, or PowerShell reading the commands from
+/// its input.
+pub(super) fn shell_program(program: Option<&str>) -> (&str, &'static [&'static str]) {
+    match program {
+        Some(program) => (program, &[]),
+        None => ("pwsh", &["-NonInteractive", "-NoLogo", "-File", "-"]),
+    }
+}
+
+/// Text written to the input of a shell started as `program` to run `command`, followed by the end
+/// marker and its exit status. `$LASTEXITCODE` is empty for POSIX shells, which do not define it.
+pub(super) fn shell_input(command: &str, program: Option<&str>) -> String {
+    // Otherwise a failed cmdlet would report the exit code of a native command that failed before
+    let reset = if is_powershell(program) {
+        "$global:LASTEXITCODE = 0\n"
+    } else {
+        ""
+    };
+    // The blank line ends the multi-line statements PowerShell waits for more input of
+    format!("{reset}{command}\n\necho \"{COMMAND_END_MARKER}$? $LASTEXITCODE\"\n")
+}
+
+/// Whether a shell started as `program` is PowerShell.
+fn is_powershell(program: Option<&str>) -> bool {
+    program.is_none_or(|program| {
+        Path::new(program)
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| {
+                stem.eq_ignore_ascii_case("pwsh") || stem.eq_ignore_ascii_case("powershell")
+            })
+    })
+}
+
+/// Exit status of the command when `line` is the end marker, along with the output not ending
+/// with a line break that is printed before it.
+pub(super) fn command_end(line: &str) -> Option<(Option<ExecutionMessage>, ExitStatus)> {
+    let (last_output, status) = line.split_once(COMMAND_END_MARKER)?;
+    let last_output = (!last_output.is_empty()).then(|| ExecutionMessage::CommandOutput {
+        output: last_output.to_string(),
+    });
+    Some((last_output, parse_status(status)))
+}
+
+/// Exit status printed after the marker, `$?` being a number for POSIX shells and a boolean for
+/// PowerShell.
+///
+/// A failure in PowerShell has the exit code of the last native command, as a one-shot shell would
+/// exit with, unless it is missing or zero because a cmdlet failed.
+fn parse_status(status: &str) -> ExitStatus {
+    let mut parts = status.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some("True"), _) => ExitStatus::Exited(0),
+        (Some("False"), last_exit_code) => ExitStatus::Exited(
+            last_exit_code
+                .and_then(|code| code.parse().ok())
+                .filter(|&code| code != 0)
+                .unwrap_or(1),
+        ),
+        (Some(status), _) => status
+            .parse()
+            .map_or(ExitStatus::Undetermined, ExitStatus::Exited),
+        (None, _) => ExitStatus::Undetermined,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_status() {
+        assert_eq!(parse_status("0 "), ExitStatus::Exited(0));
+        assert_eq!(parse_status("3 "), ExitStatus::Exited(3));
+        assert_eq!(parse_status("True "), ExitStatus::Exited(0));
+        // A native command that failed before a successful cmdlet
+        assert_eq!(parse_status("True 3"), ExitStatus::Exited(0));
+        assert_eq!(parse_status("False 3"), ExitStatus::Exited(3));
+        // A failed cmdlet
+        assert_eq!(parse_status("False "), ExitStatus::Exited(1));
+        assert_eq!(parse_status("False 0"), ExitStatus::Exited(1));
+        assert_eq!(parse_status(""), ExitStatus::Undetermined);
+    }
+
+    #[test]
+    fn test_shell_input() {
+        assert!(
+            shell_input("Get-Item x", None).starts_with("$global:LASTEXITCODE = 0\nGet-Item x\n")
+        );
+        assert!(
+            shell_input("Get-Item x", Some("/usr/bin/pwsh")).starts_with("$global:LASTEXITCODE")
+        );
+        assert!(shell_input("ls", Some("bash")).starts_with("ls\n"));
+    }
+}
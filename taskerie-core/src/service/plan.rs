@@ -0,0 +1,373 @@
+use super::SECRET_MASK;
+use crate::model::{
+    Action, InterpolatedString, ParamContext, RunError, TaskerieContext,
+    action::TaskCall,
+    plan::{ExecutionPlan, PlannedAction},
+};
+
+impl TaskerieContext {
+    /// Describe everything running a task would do, expanding the needed and called tasks,
+    /// without running any command.
+    ///
+    /// Values only known when running, such as missing params or captured outputs, show as
+    /// `<name?>`. The values of secret params are masked.
+    pub fn explain(
+        &self,
+        name: &str,
+        param_context: &ParamContext,
+    ) -> Result<ExecutionPlan, RunError> {
+        let task = self
+            .get_task_by_name(name)
+            .ok_or_else(|| self.task_not_found(name))?;
+
+        let mut param_context = param_context.clone();
+        for (var_name, value) in &self.vars {
+            if !task.params.contains_key(var_name) && !param_context.has(var_name) {
+                param_context.set(var_name, value);
+            }
+        }
+        for (param_name, param) in &task.params {
+            let has_value = param_context.has(param_name)
+                || param.default.is_some()
+                || self.vars.contains_key(param_name);
+            if param.is_secret() && has_value {
+                // Masked like in the messages of a run
+                param_context.set(param_name, SECRET_MASK);
+                continue;
+            }
+            if param_context.has(param_name) {
+                continue;
+            }
+            let value = match (&param.default, self.vars.get(param_name)) {
+                (Some(default), _) => default.render_with_placeholders(&param_context),
+                (None, Some(value)) => value.clone(),
+                (None, None) => format!("<{param_name}?>"),
+            };
+            param_context.set(param_name, &value);
+        }
+
+        let needs = task
+            .needs
+            .iter()
+            .map(|dependency| self.explain_task_call(dependency, &param_context))
+            .collect::<Result<_, RunError>>()?;
+        let actions = task
+            .actions
+            .iter()
+            .map(|action| {
+                Ok(match action {
+                    Action::Command(command) => PlannedAction::Command {
+                        command: command.command.render_with_placeholders(&param_context),
+                        continue_on_error: command.continue_on_error,
+                    },
+                    Action::TaskCall(task_call) => PlannedAction::TaskCall {
+                        plan: self.explain_task_call(task_call, &param_context)?,
+                        continue_on_error: task_call.continue_on_error,
+                    },
+                })
+            })
+            .collect::<Result<_, RunError>>()?;
+
+        Ok(ExecutionPlan {
+            task_name: name.to_string(),
+            params: task
+                .params
+                .keys()
+                .filter_map(|param_name| {
+                    let value = param_context.get(param_name)?;
+                    Some((param_name.clone(), value.to_string()))
+                })
+                .collect(),
+            working_directory: task
+                .working_directory
+                .as_ref()
+                .map(|dir| dir.render_with_placeholders(&param_context)),
+            parallel: task.parallel,
+            needs,
+            actions,
+        })
+    }
+
+    fn explain_task_call(
+        &self,
+        task_call: &TaskCall,
+        param_context: &ParamContext,
+    ) -> Result<ExecutionPlan, RunError> {
+        let call_param_context = task_call
+            .params
+            .iter()
+            .map(|(name, value)| (name.clone(), value.render_with_placeholders(param_context)))
+            .collect();
+        self.explain(&task_call.name, &call_param_context)
+    }
+
+    /// Commands running a task would run, in order and without rendering them, the needed and
+    /// called tasks being expanded in place. A task needed several times is listed each time.
+    pub fn resolve_commands(&self, name: &str) -> Result<Vec<&InterpolatedString>, RunError> {
+        let mut commands = Vec::new();
+        self.collect_commands(name, &mut Vec::new(), &mut commands)?;
+        Ok(commands)
+    }
+
+    fn collect_commands<'a>(
+        &'a self,
+        name: &str,
+        call_stack: &mut Vec<String>,
+        commands: &mut Vec<&'a InterpolatedString>,
+    ) -> Result<(), RunError> {
+        if call_stack.iter().any(|called| called == name) {
+            return Err(super::cyclic_call(call_stack, name));
+        }
+        let task = self
+            .get_task_by_name(name)
+            .ok_or_else(|| self.task_not_found(name))?;
+
+        call_stack.push(name.to_string());
+        for dependency in &task.needs {
+            self.collect_commands(&dependency.name, call_stack, commands)?;
+        }
+        for action in &task.actions {
+            match action {
+                Action::Command(command) => commands.push(&command.command),
+                Action::TaskCall(task_call) => {
+                    self.collect_commands(&task_call.name, call_stack, commands)?;
+                }
+            }
+        }
+        call_stack.pop();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_explain() {
+        let context = crate::load_from_str(
+            r#"
+vars:
+  registry: ghcr.io
+tasks:
+  release:
+    params:
+      version:
+      tag:
+        default: "v{{ version }}"
+    needs: [lint]
+    actions:
+      - run: cargo build
+        continue_on_error: true
+      - publish:
+          image: app:{{ tag }}
+  lint:
+    actions:
+      - cargo clippy
+  publish:
+    params:
+      image:
+      token:
+    working_directory: "{{ image }}"
+    actions:
+      - docker push {{ registry }}/{{ image }} {{ token }}
+"#,
+        )
+        .unwrap();
+
+        let publish = ExecutionPlan {
+            task_name: "publish".to_string(),
+            params: vec![
+                ("image".to_string(), "app:v<version?>".to_string()),
+                ("token".to_string(), "<token?>".to_string()),
+            ],
+            working_directory: Some("app:v<version?>".to_string()),
+            parallel: false,
+            needs: Vec::new(),
+            actions: vec![PlannedAction::Command {
+                command: "docker push ghcr.io/app:v<version?> <token?>".to_string(),
+                continue_on_error: false,
+            }],
+        };
+        let lint = ExecutionPlan {
+            task_name: "lint".to_string(),
+            params: Vec::new(),
+            working_directory: None,
+            parallel: false,
+            needs: Vec::new(),
+            actions: vec![PlannedAction::Command {
+                command: "cargo clippy".to_string(),
+                continue_on_error: false,
+            }],
+        };
+        assert_eq!(
+            context
+                .explain("release", &ParamContext::default())
+                .unwrap(),
+            ExecutionPlan {
+                task_name: "release".to_string(),
+                params: vec![
+                    ("version".to_string(), "<version?>".to_string()),
+                    ("tag".to_string(), "v<version?>".to_string()),
+                ],
+                working_directory: None,
+                parallel: false,
+                needs: vec![lint],
+                actions: vec![
+                    PlannedAction::Command {
+                        command: "cargo build".to_string(),
+                        continue_on_error: true,
+                    },
+                    PlannedAction::TaskCall {
+                        plan: publish,
+                        continue_on_error: false,
+                    },
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn test_explain_with_given_params() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  greet:
+    params:
+      name:
+    actions:
+      - echo hello {{ name }}
+"#,
+        )
+        .unwrap();
+
+        let param_context = [("name".to_string(), "john".to_string())]
+            .into_iter()
+            .collect();
+        let plan = context.explain("greet", &param_context).unwrap();
+        assert_eq!(
+            plan.actions,
+            vec![PlannedAction::Command {
+                command: "echo hello john".to_string(),
+                continue_on_error: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_explain_masks_secrets() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  deploy:
+    params:
+      token:
+        secret: true
+      password:
+        default: hunter2
+        secret: true
+      vault:
+        from_command: vault read token
+    actions:
+      - deploy --token {{ token }} --password {{ password }} --vault {{ vault }}
+"#,
+        )
+        .unwrap();
+
+        let param_context = [("token".to_string(), "abc123".to_string())]
+            .into_iter()
+            .collect();
+        let plan = context.explain("deploy", &param_context).unwrap();
+        assert_eq!(
+            plan.params,
+            vec![
+                ("token".to_string(), "****".to_string()),
+                ("password".to_string(), "****".to_string()),
+                ("vault".to_string(), "<vault?>".to_string()),
+            ]
+        );
+        assert_eq!(
+            plan.actions,
+            vec![PlannedAction::Command {
+                command: "deploy --token **** --password **** --vault <vault?>".to_string(),
+                continue_on_error: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_explain_unknown_task() {
+        let context =
+            crate::load_from_str("tasks:\n  greet:\n    actions:\n      - echo hello\n").unwrap();
+        assert!(matches!(
+            context.explain("gret", &ParamContext::default()),
+            Err(RunError::TaskNotFound { name, .. }) if name == "gret"
+        ));
+    }
+
+    #[test]
+    fn test_resolve_commands() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  release:
+    needs: [lint]
+    actions:
+      - cargo build
+      - publish:
+          image: app
+  lint:
+    actions:
+      - cargo clippy
+  publish:
+    params:
+      image:
+    actions:
+      - docker push {{ image }}
+      - lint: {}
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            context
+                .resolve_commands("release")
+                .unwrap()
+                .iter()
+                .map(|command| command.template())
+                .collect::<Vec<_>>(),
+            [
+                "cargo clippy",
+                "cargo build",
+                "docker push {{ image }}",
+                "cargo clippy"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_resolve_commands_rejects_cycles() {
+        let mut context = crate::load_from_str(
+            r#"
+tasks:
+  a:
+    actions:
+      - b: {}
+  b:
+    actions:
+      - echo b
+"#,
+        )
+        .unwrap();
+        // Loading rejects cycles, so one is added afterwards
+        context.tasks["b"].needs.push(TaskCall {
+            name: "a".to_string(),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            context.resolve_commands("a").unwrap_err().to_string(),
+            "Cyclic task call detected: a -> b -> a"
+        );
+    }
+}
@@ -0,0 +1,1074 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    future::Future,
+    io,
+    path::Path,
+    pin::Pin,
+    process::Stdio,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures_core::Stream;
+use futures_util::future::join_all;
+use itertools::Itertools;
+use subprocess::{ExitStatus, PopenError};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    process::{Child, ChildStdin, Command},
+    sync::{OnceCell, mpsc as async_mpsc},
+    task::JoinHandle,
+    time,
+};
+
+use super::{
+    CLEAN_ENV_KEPT_VARIABLES, CommandEnv, DependencyKey, Execution, LineSplitter, MessageScope,
+    MessageSender, MessageSink, OutputForwarder, PROCESS_POLL_INTERVAL, ParamResolution,
+    RenderedCommand, cache::CacheLookup, command_finished, cyclic_call, display_path,
+    parallel_outcome, persistent_shell, render_task_call_params, resolve_shell, retry_attempt,
+    shell_variable,
+};
+use crate::{
+    message::ExecutionMessage,
+    model::{
+        self, ExecutionOptions, ParamContext, RunError, TaskOutcome, TaskerieContext,
+        action::Sequencing,
+    },
+};
+
+/// Messages of a run started by [`TaskerieContext::run_task_async`], which ends once the run is
+/// done.
+pub struct ExecutionMessageStream {
+    receiver: async_mpsc::UnboundedReceiver<ExecutionMessage>,
+}
+
+impl Stream for ExecutionMessageStream {
+    type Item = ExecutionMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl MessageSink for async_mpsc::UnboundedSender<ExecutionMessage> {
+    fn send(&self, message: ExecutionMessage) -> Result<(), mpsc::SendError<ExecutionMessage>> {
+        // The messages are dropped along with the stream, the run going on
+        let _ = async_mpsc::UnboundedSender::send(self, message);
+        Ok(())
+    }
+}
+
+impl TaskerieContext {
+    /// Run a task from a tokio runtime, returning the stream of its messages along with a handle
+    /// resolving to its outcome.
+    ///
+    /// The task runs as [`run_task_by_name_with_options`](Self::run_task_by_name_with_options)
+    /// would, its commands being started and awaited with `tokio::process` so that no thread is
+    /// blocked while they run. Missing params and confirmations cannot be asked for, as waiting
+    /// on the receivers of `options` would block the runtime: tasks needing them do not run.
+    /// Interrupting the run kills its commands right away.
+    ///
+    /// Dropping the stream does not stop the run, which the interrupter of `options` does.
+    /// Panics when called outside of a tokio runtime.
+    pub fn run_task_async(
+        self: Arc<Self>,
+        name: &str,
+        mut param_context: ParamContext,
+        options: ExecutionOptions,
+    ) -> (
+        ExecutionMessageStream,
+        JoinHandle<Result<TaskOutcome, RunError>>,
+    ) {
+        let name = name.to_string();
+        let (sender, receiver) = async_mpsc::unbounded_channel();
+        let outcome = tokio::spawn(async move {
+            let runner = AsyncRunner {
+                context: &self,
+                dependencies: Mutex::default(),
+            };
+            runner
+                .run(&name, &mut param_context, &sender, options)
+                .await
+        });
+        (ExecutionMessageStream { receiver }, outcome)
+    }
+}
+
+type BoxFuture<'f, T> = Pin<Box<dyn Future<Output = T> + Send + 'f>>;
+
+/// Runs the tasks of a single async run, as the blocking run path does with the processes started
+/// by tokio.
+struct AsyncRunner<'c> {
+    context: &'c TaskerieContext,
+    /// Status of the dependencies run so far by name and sorted params, `None` when they could
+    /// not run. Actions running concurrently wait for each other without blocking the runtime.
+    dependencies: Mutex<HashMap<DependencyKey, Arc<OnceCell<Option<TaskOutcome>>>>>,
+}
+
+impl AsyncRunner<'_> {
+    async fn run(
+        &self,
+        name: &str,
+        param_context: &mut ParamContext,
+        sender: &async_mpsc::UnboundedSender<ExecutionMessage>,
+        options: ExecutionOptions,
+    ) -> Result<TaskOutcome, RunError> {
+        let pre_run_hook = Mutex::new(|_: &str, _: &ParamContext| Ok(()));
+        // Only used by the blocking run path, the dependencies are tracked by the runner
+        let blocking_dependencies = Mutex::default();
+        let secrets = Mutex::default();
+        let shell = options.shell.or_else(shell_variable);
+        let mut execution = Execution {
+            sender: MessageSender {
+                sender: Some(sender),
+                scopes: Vec::new(),
+                secrets: &secrets,
+            },
+            dry_run: options.dry_run,
+            parameter_values: None,
+            confirmations: None,
+            assume_yes: options.assume_yes,
+            pre_run_hook: &pre_run_hook,
+            call_stack: Vec::new(),
+            dependencies: &blocking_dependencies,
+            interrupter: options.interrupter,
+            shell: shell.as_deref(),
+        };
+        let Some(task) = self.context.get_task_by_name(name) else {
+            return Err(self.context.task_not_found(name));
+        };
+        let outcome = self
+            .run_task(name, task, param_context, &mut execution)
+            .await;
+        execution.finish(outcome)
+    }
+
+    /// Run a task between [`ExecutionMessage::TaskStarted`] and
+    /// [`ExecutionMessage::TaskFinished`] messages, the latter not being sent on error.
+    fn run_task<'f>(
+        &'f self,
+        name: &'f str,
+        task: &'f model::task::Task,
+        param_context: &'f mut ParamContext,
+        execution: &'f mut Execution<'_>,
+    ) -> BoxFuture<'f, Result<TaskOutcome, RunError>> {
+        // Boxed as tasks run each other
+        Box::pin(async move {
+            if execution.call_stack.iter().any(|called| called == name) {
+                return Err(cyclic_call(&execution.call_stack, name));
+            }
+
+            execution.sender.send(ExecutionMessage::TaskStarted {
+                name: name.to_string(),
+            })?;
+            let outcome = self
+                .run_started_task(name, task, param_context, execution)
+                .await?;
+            execution.sender.send(ExecutionMessage::TaskFinished {
+                name: name.to_string(),
+                success: outcome.success(),
+            })?;
+            Ok(outcome)
+        })
+    }
+
+    /// Run a task as a sub-task of the current one, its messages being wrapped accordingly.
+    async fn run_sub_task(
+        &self,
+        name: &str,
+        task: &model::task::Task,
+        param_context: &mut ParamContext,
+        execution: &mut Execution<'_>,
+    ) -> Result<TaskOutcome, RunError> {
+        execution
+            .sender
+            .scopes
+            .push(MessageScope::Task(name.to_string()));
+        let outcome = self.run_task(name, task, param_context, execution).await;
+        execution.sender.scopes.pop();
+        outcome
+    }
+
+    async fn run_started_task(
+        &self,
+        name: &str,
+        task: &model::task::Task,
+        param_context: &mut ParamContext,
+        execution: &mut Execution<'_>,
+    ) -> Result<TaskOutcome, RunError> {
+        let context = self.context;
+        context.set_context_values(task, param_context);
+        let mut missing_params = Vec::new();
+        for (param_name, param) in &task.params {
+            let ask = missing_params.is_empty();
+            match context.resolve_param(param_name, param, param_context, execution, ask)? {
+                ParamResolution::Resolved => {}
+                ParamResolution::Source(source) => {
+                    let value = read_param_source(
+                        &context.base_dir,
+                        param_name,
+                        source,
+                        param_context,
+                        execution.shell,
+                    )
+                    .await?;
+                    param_context.set(param_name, &value);
+                }
+                ParamResolution::Missing => missing_params.push(param_name.as_str()),
+            }
+            execution
+                .sender
+                .add_param_secret(param, param_context.get(param_name));
+        }
+        if let Some(outcome) =
+            context.check_preconditions(name, task, param_context, &missing_params, execution)?
+        {
+            return Ok(outcome);
+        }
+        let fingerprint = match context.lookup_cache(name, task, param_context, execution)? {
+            CacheLookup::Hit => return Ok(TaskOutcome::Completed(ExitStatus::Exited(0))),
+            CacheLookup::Miss(fingerprint) => Some(fingerprint),
+            CacheLookup::Uncached => None,
+        };
+
+        execution.call_stack.push(name.to_string());
+        let outcome = match self.run_dependencies(task, param_context, execution).await {
+            Ok(Some(failed_outcome)) => Ok(failed_outcome),
+            Ok(None) if task.parallel => {
+                self.run_actions_in_parallel(task, param_context, execution)
+                    .await
+            }
+            Ok(None) => self.run_actions(task, param_context, execution).await,
+            Err(error) => Err(error),
+        };
+        execution.call_stack.pop();
+
+        if let (Some(fingerprint), Ok(outcome)) = (&fingerprint, &outcome) {
+            context.store_cache_result(name, fingerprint, outcome, execution);
+        }
+        outcome
+    }
+
+    /// Run the tasks needed by `task`, their own dependencies first, skipping the ones that
+    /// already ran with the same params during this execution.
+    ///
+    /// Returns the outcome of the first dependency that failed.
+    async fn run_dependencies(
+        &self,
+        task: &model::task::Task,
+        param_context: &ParamContext,
+        execution: &mut Execution<'_>,
+    ) -> Result<Option<TaskOutcome>, RunError> {
+        for task_call in &task.needs {
+            let name = &task_call.name;
+            if execution.call_stack.contains(name) {
+                return Err(cyclic_call(&execution.call_stack, name));
+            }
+            let dependency = self
+                .context
+                .get_task_by_name(name)
+                .ok_or_else(|| self.context.task_not_found(name))?;
+            let mut dependency_param_context = render_task_call_params(task_call, param_context)?;
+
+            let mut params = dependency_param_context
+                .params
+                .clone()
+                .into_iter()
+                .collect_vec();
+            params.sort();
+            let (outcome, already_run) = match self
+                .dependencies
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry((name.clone(), params))
+            {
+                Entry::Occupied(entry) => (entry.get().clone(), true),
+                Entry::Vacant(entry) => (entry.insert(Arc::default()).clone(), false),
+            };
+
+            // Parallel actions needing the same task wait for the first one to run it
+            let mut error = None;
+            let dependency_outcome = outcome
+                .get_or_init(|| async {
+                    let result = match execution
+                        .sender
+                        .send(ExecutionMessage::RunningDependency { name: name.clone() })
+                    {
+                        Ok(()) => {
+                            self.run_sub_task(
+                                name,
+                                dependency,
+                                &mut dependency_param_context,
+                                execution,
+                            )
+                            .await
+                        }
+                        Err(send_error) => Err(send_error.into()),
+                    };
+                    result.map_err(|e| error = Some(e)).ok()
+                })
+                .await;
+            if let Some(error) = error {
+                return Err(error);
+            }
+            let dependency_outcome = dependency_outcome
+                .clone()
+                .ok_or_else(|| RunError::DependencyNotRun(name.clone()))?;
+            if !dependency_outcome.success() {
+                return Ok(Some(dependency_outcome));
+            }
+            if already_run {
+                execution
+                    .sender
+                    .send(ExecutionMessage::DependencyAlreadySatisfied { name: name.clone() })?;
+            }
+        }
+        Ok(None)
+    }
+
+    async fn run_actions(
+        &self,
+        task: &model::task::Task,
+        param_context: &mut ParamContext,
+        execution: &mut Execution<'_>,
+    ) -> Result<TaskOutcome, RunError> {
+        let mut shell = task.persistent_shell.then(PersistentShell::default);
+        let mut outcome = Ok(TaskOutcome::Completed(ExitStatus::Exited(0)));
+        for action in &task.actions {
+            if execution.interrupter.is_interrupted() {
+                outcome = Ok(TaskOutcome::Interrupted);
+                break;
+            }
+            let action_outcome = self
+                .run_action(action, task, param_context, execution, shell.as_mut())
+                .await;
+            match action_outcome {
+                Ok(action_outcome) if !action_outcome.success() => {
+                    if action.continue_on_error() {
+                        if let Err(error) = execution.sender.send(ExecutionMessage::FailureIgnored)
+                        {
+                            outcome = Err(error.into());
+                            break;
+                        }
+                        continue;
+                    }
+                    outcome = Ok(action_outcome);
+                    break;
+                }
+                Ok(_) => {}
+                Err(error) => {
+                    outcome = Err(error);
+                    break;
+                }
+            }
+        }
+        // Unlike the blocking one, the shell cannot wait to exit when dropped
+        if let Some(shell) = shell {
+            shell.close().await;
+        }
+        outcome
+    }
+
+    /// Run the actions of a task concurrently, at most `max_parallel` at once, each on its own
+    /// copy of the params. Values captured by the actions are then set in `param_context`.
+    ///
+    /// Every action runs even if another fails; the outcome is the one of the first failed
+    /// action.
+    async fn run_actions_in_parallel(
+        &self,
+        task: &model::task::Task,
+        param_context: &mut ParamContext,
+        execution: &Execution<'_>,
+    ) -> Result<TaskOutcome, RunError> {
+        let worker_count = task
+            .max_parallel
+            .unwrap_or(task.actions.len())
+            .clamp(1, task.actions.len().max(1));
+        let next_action_id = AtomicUsize::new(0);
+        let shared_param_context: &ParamContext = param_context;
+
+        let workers = (0..worker_count).map(|_| async {
+            let mut results = Vec::new();
+            while !execution.interrupter.is_interrupted() {
+                let action_id = next_action_id.fetch_add(1, Ordering::Relaxed);
+                let Some(action) = task.actions.get(action_id) else {
+                    return Ok(results);
+                };
+                let mut action_param_context = shared_param_context.clone();
+                let mut action_execution = execution.fork(action_id);
+                let outcome = self
+                    .run_action(
+                        action,
+                        task,
+                        &mut action_param_context,
+                        &mut action_execution,
+                        None,
+                    )
+                    .await?;
+                if !outcome.success() && action.continue_on_error() {
+                    action_execution
+                        .sender
+                        .send(ExecutionMessage::FailureIgnored)?;
+                }
+                results.push((action_id, outcome, action_param_context));
+            }
+            Ok(results)
+        });
+        let results = join_all(workers)
+            .await
+            .into_iter()
+            .flatten_ok()
+            .collect::<Result<Vec<_>, RunError>>()?;
+        Ok(parallel_outcome(task, param_context, results))
+    }
+
+    /// Run an action, its commands in `shell` when given.
+    async fn run_action(
+        &self,
+        action: &model::action::Action,
+        task: &model::task::Task,
+        param_context: &mut ParamContext,
+        execution: &mut Execution<'_>,
+        shell: Option<&mut PersistentShell>,
+    ) -> Result<TaskOutcome, RunError> {
+        match action {
+            model::action::Action::Command(command) => {
+                let (current_dir, env) =
+                    match self
+                        .context
+                        .command_context(command, task, param_context, execution)?
+                    {
+                        Ok(command_context) => command_context,
+                        Err(reason) => return Ok(TaskOutcome::PreconditionFailed(reason)),
+                    };
+                run_command(
+                    command,
+                    &current_dir,
+                    &env,
+                    param_context,
+                    execution,
+                    shell,
+                    task.max_output_bytes.or(self.context.max_output_bytes),
+                )
+                .await
+                .map(TaskOutcome::Completed)
+            }
+            model::action::Action::TaskCall(task_call) => {
+                let called_task = self
+                    .context
+                    .get_task_by_name(&task_call.name)
+                    .ok_or_else(|| self.context.task_not_found(&task_call.name))?;
+                let mut task_param_context = render_task_call_params(task_call, param_context)?;
+                self.run_sub_task(
+                    &task_call.name,
+                    called_task,
+                    &mut task_param_context,
+                    execution,
+                )
+                .await
+            }
+        }
+    }
+}
+
+/// Read the value of the param `param_name` from its command or file, relative to `base_dir`.
+async fn read_param_source(
+    base_dir: &Path,
+    param_name: &str,
+    source: &model::task::ParamSource,
+    param_context: &ParamContext,
+    shell_override: Option<&str>,
+) -> Result<String, RunError> {
+    let model::task::ParamSource::Command(command) = source else {
+        // Reading a file does not wait on another process
+        return super::read_param_source(
+            base_dir,
+            param_name,
+            source,
+            param_context,
+            shell_override,
+        );
+    };
+    let error = |reason: String| RunError::ParamSource {
+        parameter: param_name.to_string(),
+        reason,
+    };
+    let command = command.render(param_context)?;
+    let shell = resolve_shell(shell_override)?;
+    let output = program(&command, &[], &shell)
+        .current_dir(base_dir.join("./"))
+        .stderr(Stdio::inherit())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .map_err(|source| error(format!("{command}: {source}")))?;
+    if !output.status.success() {
+        return Err(error(format!("{command} failed")));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Run a command with its retries, in `shell` when given unless it reads an input. Only the first
+/// `max_output_bytes` of the output of each attempt are forwarded when given.
+///
+/// The parts of a split command run one after the other, the status being the one of the last
+/// part that ran.
+async fn run_command(
+    command: &model::action::Command,
+    current_dir: &Path,
+    env: &CommandEnv,
+    param_context: &mut ParamContext,
+    execution: &Execution<'_>,
+    mut shell: Option<&mut PersistentShell>,
+    max_output_bytes: Option<usize>,
+) -> Result<ExitStatus, RunError> {
+    let execution_message_sender = &execution.sender;
+    let current_dir_str = display_path(current_dir);
+    let capture = command.capture.as_deref();
+    let rendered = RenderedCommand::new(command, param_context, execution.shell)?;
+
+    let mut captured_output = capture.map(|_| String::new());
+    let mut status = ExitStatus::Exited(0);
+    for (sequencing, part) in &rendered.commands {
+        if *sequencing == Sequencing::OnSuccess && !status.success() {
+            continue;
+        }
+        execution_message_sender.send(ExecutionMessage::AboutToRunCommand {
+            command: part.clone(),
+            working_directory: current_dir_str.clone(),
+            label: command.label.clone(),
+        })?;
+
+        if execution.dry_run {
+            execution_message_sender.send(ExecutionMessage::CommandSucceeded)?;
+            continue;
+        }
+
+        let mut attempt = 0;
+        status = loop {
+            let mut output = OutputForwarder::new(command, execution, max_output_bytes);
+            let (status, duration) = match shell.as_deref_mut() {
+                Some(shell) if rendered.runs_in_shell() => {
+                    shell
+                        .run(
+                            part,
+                            rendered.persistent_shell_program(),
+                            current_dir,
+                            env,
+                            execution,
+                            &mut output,
+                        )
+                        .await?
+                }
+                _ => {
+                    spawn_command(
+                        part,
+                        program(part, &rendered.arguments, &rendered.shell),
+                        current_dir,
+                        env,
+                        execution,
+                        rendered.stdin.as_deref(),
+                        &mut output,
+                    )
+                    .await?
+                }
+            };
+            if retry_attempt(command, status, duration, &mut attempt, execution)? {
+                time::sleep(command.retry_delay).await;
+                continue;
+            }
+            if let (Some(captured_output), Some(output)) =
+                (captured_output.as_mut(), output.captured_output)
+            {
+                captured_output.push_str(&output);
+            }
+            break command_finished(command, status, execution)?;
+        };
+    }
+
+    if let (Some(capture), Some(captured_output)) = (capture, captured_output) {
+        param_context.set(capture, captured_output.trim());
+    }
+    Ok(status)
+}
+
+/// Process running `command` through `shell`, or else `arguments` directly when there are some.
+fn program(command: &str, arguments: &[String], shell: &[String]) -> Command {
+    match arguments.split_first() {
+        Some((program, arguments)) => {
+            let mut process = Command::new(program);
+            process.args(arguments);
+            process
+        }
+        None => {
+            let mut process = Command::new(&shell[0]);
+            process.args(&shell[1..]).arg(command);
+            process
+        }
+    }
+}
+
+impl CommandEnv {
+    fn apply_to(&self, process: &mut Command) {
+        if self.clean {
+            process.env_clear();
+            for key in CLEAN_ENV_KEPT_VARIABLES {
+                if let Some(value) = std::env::var_os(key) {
+                    process.env(key, value);
+                }
+            }
+        }
+        process.envs(self.vars.iter().map(|(key, value)| (key, value)));
+    }
+}
+
+/// Run a single attempt of `command` with `process`, writing `stdin` to its input and forwarding
+/// its output until it exits. Returns its status along with the time it ran for.
+async fn spawn_command(
+    command: &str,
+    mut process: Command,
+    current_dir: &Path,
+    env: &CommandEnv,
+    execution: &Execution<'_>,
+    stdin: Option<&str>,
+    output: &mut OutputForwarder<'_>,
+) -> Result<(ExitStatus, Duration), RunError> {
+    let interrupter = &execution.interrupter;
+    let started_at = Instant::now();
+    env.apply_to(&mut process);
+    process
+        .current_dir(current_dir)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    if stdin.is_some() {
+        process.stdin(Stdio::piped());
+    }
+    let mut child = process.spawn().map_err(|source| RunError::Spawn {
+        command: command.to_string(),
+        source: source.into(),
+    })?;
+
+    if let (Some(input), Some(mut child_stdin)) = (stdin, child.stdin.take()) {
+        // Written concurrently so that a process writing its output before reading all its input
+        // does not block on a full pipe. Closing the input once written lets it finish.
+        let input = input.to_string();
+        tokio::spawn(async move {
+            // The process may exit without reading everything, which is not an error
+            let _ = child_stdin.write_all(input.as_bytes()).await;
+        });
+    }
+
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        unreachable!("The output of the command is piped");
+    };
+    // Both pipes are drained concurrently so the process never blocks on a full one
+    let (line_tx, mut lines) = async_mpsc::unbounded_channel();
+    let stdout_reader = tokio::spawn(forward_lines(stdout, line_tx.clone(), |output| {
+        ExecutionMessage::CommandOutput { output }
+    }));
+    let stderr_reader = tokio::spawn(forward_lines(stderr, line_tx, |output| {
+        ExecutionMessage::CommandError { output }
+    }));
+
+    let mut status = None;
+    let mut exited_at = None;
+    let mut interruption_check = time::interval(PROCESS_POLL_INTERVAL);
+    let all_read = loop {
+        tokio::select! {
+            line = lines.recv() => match line {
+                Some(message) => {
+                    if let Err(error) = output.forward(message) {
+                        // Do not leave the process running once nobody reads its output anymore
+                        child.kill().await.map_err(PopenError::from)?;
+                        return Err(error);
+                    }
+                }
+                None => break true,
+            },
+            // Noticing the exit while forwarding keeps the end of the output out of the duration
+            exit_status = child.wait(), if status.is_none() => {
+                status = Some(exit_status.map_err(PopenError::from)?);
+                exited_at = Some(Instant::now());
+            }
+            _ = interruption_check.tick() => {
+                if interrupter.is_interrupted() {
+                    if status.is_some() {
+                        // The readers are left behind until the processes the interrupted
+                        // command started close its output
+                        break false;
+                    }
+                    // An error means the process is already gone
+                    let _ = child.start_kill();
+                }
+            }
+        }
+    };
+
+    if all_read {
+        for reader in [stdout_reader, stderr_reader] {
+            reader
+                .await
+                .unwrap_or_else(|_| Err(io::Error::other("The output reader panicked")))
+                .map_err(|source| RunError::ReadOutput {
+                    command: command.to_string(),
+                    source,
+                })?;
+        }
+    }
+    let status = match status {
+        Some(status) => status,
+        None => child.wait().await.map_err(PopenError::from)?,
+    };
+    Ok((
+        exit_status(status),
+        exited_at.unwrap_or_else(Instant::now) - started_at,
+    ))
+}
+
+fn exit_status(status: std::process::ExitStatus) -> ExitStatus {
+    if let Some(code) = status.code() {
+        // Windows exit codes are unsigned
+        return ExitStatus::Exited(code.cast_unsigned());
+    }
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return ExitStatus::Signaled(signal.try_into().unwrap_or(u8::MAX));
+    }
+    ExitStatus::Undetermined
+}
+
+/// Send every line read from `output` through the channel, wrapped by `message`.
+async fn forward_lines(
+    mut output: impl AsyncRead + Unpin,
+    lines: async_mpsc::UnboundedSender<ExecutionMessage>,
+    message: fn(String) -> ExecutionMessage,
+) -> io::Result<()> {
+    let forward_line = |line| {
+        lines
+            .send(message(line))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    };
+
+    let mut splitter = LineSplitter::default();
+    let mut buffer = vec![0; 8 * 1024];
+    loop {
+        let read = output.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        splitter.split(&buffer[..read], forward_line)?;
+    }
+    splitter.finish(forward_line)
+}
+
+/// A shell running the commands of a task one after the other, as the blocking
+/// [`PersistentShell`](super::persistent_shell::PersistentShell) does.
+#[derive(Default)]
+struct PersistentShell {
+    running: Option<RunningShell>,
+}
+
+struct RunningShell {
+    /// Killed if the shell is dropped without being closed.
+    process: Child,
+    input: ChildStdin,
+    /// Output lines of the shell, its standard error ones being sent as they come.
+    lines: async_mpsc::UnboundedReceiver<ExecutionMessage>,
+}
+
+impl PersistentShell {
+    /// Run `command` in the shell, forwarding its output until it is done. Returns its status
+    /// along with the time it ran for.
+    ///
+    /// The shell is started as `program` when given, PowerShell otherwise.
+    async fn run(
+        &mut self,
+        command: &str,
+        program: Option<&str>,
+        current_dir: &Path,
+        env: &CommandEnv,
+        execution: &Execution<'_>,
+        output: &mut OutputForwarder<'_>,
+    ) -> Result<(ExitStatus, Duration), RunError> {
+        let mut shell = match self.running.take() {
+            Some(shell) => shell,
+            None => RunningShell::start(program, current_dir, env).map_err(|source| {
+                RunError::Spawn {
+                    command: command.to_string(),
+                    source: source.into(),
+                }
+            })?,
+        };
+        let interrupter = &execution.interrupter;
+
+        let started_at = Instant::now();
+        let input = persistent_shell::shell_input(command, program);
+        let written = match shell.input.write_all(input.as_bytes()).await {
+            Ok(()) => shell.input.flush().await,
+            Err(error) => Err(error),
+        };
+        // A command exiting the shell closes its input before the marker is written, its status
+        // is then read once the output closes
+        if let Err(source) = written
+            && source.kind() != io::ErrorKind::BrokenPipe
+        {
+            return Err(RunError::ShellInput {
+                command: command.to_string(),
+                source,
+            });
+        }
+
+        let mut interruption_check = time::interval(PROCESS_POLL_INTERVAL);
+        let command_status = loop {
+            tokio::select! {
+                line = shell.lines.recv() => match line {
+                    Some(ExecutionMessage::CommandOutput { output: ref line })
+                        if let Some((last_output, status)) = persistent_shell::command_end(line) =>
+                    {
+                        if let Some(last_output) = last_output {
+                            output.forward(last_output)?;
+                        }
+                        break Some(status);
+                    }
+                    Some(message) => output.forward(message)?,
+                    None => break None,
+                },
+                _ = interruption_check.tick() => {
+                    if interrupter.is_interrupted() {
+                        // The processes started by an interrupted command may keep the output open
+                        if shell.process.try_wait().map_err(PopenError::from)?.is_some() {
+                            break None;
+                        }
+                        // An error means the shell is already gone
+                        let _ = shell.process.start_kill();
+                    }
+                }
+            }
+        };
+        let duration = started_at.elapsed();
+
+        if let Some(status) = command_status {
+            self.running = Some(shell);
+            return Ok((status, duration));
+        }
+        // The command exited the shell, or was interrupted, the exit status of the shell is the
+        // one of the command
+        let status = shell.process.wait().await.map_err(PopenError::from)?;
+        Ok((exit_status(status), duration))
+    }
+
+    /// Close the input of the shell, letting it exit on its own before killing it.
+    async fn close(self) {
+        let Some(RunningShell {
+            mut process, input, ..
+        }) = self.running
+        else {
+            return;
+        };
+        drop(input);
+        if !matches!(
+            time::timeout(persistent_shell::SHELL_EXIT_TIMEOUT, process.wait()).await,
+            Ok(Ok(_))
+        ) {
+            // An error means the shell is already gone
+            let _ = process.kill().await;
+        }
+    }
+}
+
+impl RunningShell {
+    fn start(program: Option<&str>, current_dir: &Path, env: &CommandEnv) -> io::Result<Self> {
+        let (program, arguments) = persistent_shell::shell_program(program);
+        let mut shell = Command::new(program);
+        shell.args(arguments);
+        env.apply_to(&mut shell);
+        let mut process = shell
+            .current_dir(current_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let (Some(input), Some(stdout), Some(stderr)) = (
+            process.stdin.take(),
+            process.stdout.take(),
+            process.stderr.take(),
+        ) else {
+            unreachable!("The input and output of the shell are piped");
+        };
+
+        // The readers stop on their own once the shell exits, their errors showing up as a
+        // closed output
+        let (line_tx, lines) = async_mpsc::unbounded_channel();
+        tokio::spawn(forward_lines(stdout, line_tx.clone(), |output| {
+            ExecutionMessage::CommandOutput { output }
+        }));
+        tokio::spawn(forward_lines(stderr, line_tx, |output| {
+            ExecutionMessage::CommandError { output }
+        }));
+
+        Ok(RunningShell {
+            process,
+            input,
+            lines,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::future::poll_fn;
+
+    use super::*;
+
+    /// Messages of the stream, unwrapped from the parallel actions and sub-tasks they come from.
+    async fn collect(mut messages: ExecutionMessageStream) -> Vec<ExecutionMessage> {
+        let mut collected = Vec::new();
+        while let Some(message) = poll_fn(|cx| Pin::new(&mut messages).poll_next(cx)).await {
+            collected.push(message.into_action_message().1);
+        }
+        collected
+    }
+
+    fn command_outputs(messages: &[ExecutionMessage]) -> Vec<&str> {
+        messages
+            .iter()
+            .filter_map(|message| match message {
+                ExecutionMessage::CommandOutput { output } => Some(output.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_run_task_async() {
+        let context = Arc::new(
+            crate::load_from_str("tasks:\n  greet:\n    actions:\n      - echo hello\n").unwrap(),
+        );
+        let (messages, outcome) = context.run_task_async(
+            "greet",
+            ParamContext::default(),
+            ExecutionOptions::default(),
+        );
+
+        assert_eq!(command_outputs(&collect(messages).await), ["hello"]);
+        assert!(outcome.await.unwrap().unwrap().success());
+    }
+
+    #[tokio::test]
+    async fn test_run_task_async_with_needs_and_parallel_actions() {
+        let context = Arc::new(
+            crate::load_from_str(
+                r#"
+tasks:
+  setup:
+    actions:
+      - echo setup
+  build:
+    parallel: true
+    actions:
+      - lint: {}
+      - lint: {}
+      - run: printf "b\nc\n" | tail -n 1
+        capture: last
+  lint:
+    needs: [setup]
+    actions:
+      - echo lint
+  release:
+    actions:
+      - build: {}
+      - run: exit 3
+        retries: 1
+        allowed_exit_codes: [4]
+"#,
+            )
+            .unwrap(),
+        );
+        let (messages, outcome) = context.run_task_async(
+            "release",
+            ParamContext::default(),
+            ExecutionOptions::default(),
+        );
+        let messages = collect(messages).await;
+
+        assert_eq!(
+            outcome.await.unwrap().unwrap(),
+            TaskOutcome::Completed(ExitStatus::Exited(3))
+        );
+        let mut outputs = command_outputs(&messages);
+        outputs.sort_unstable();
+        assert_eq!(outputs, ["c", "lint", "lint", "setup"]);
+        assert!(
+            messages.contains(&ExecutionMessage::DependencyAlreadySatisfied {
+                name: "setup".to_string()
+            })
+        );
+        assert!(messages.contains(&ExecutionMessage::RetryingCommand { attempt: 1, max: 1 }));
+    }
+
+    #[tokio::test]
+    async fn test_run_task_async_with_persistent_shell() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("nested")).unwrap();
+        let mut context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    persistent_shell: true
+    actions:
+      - cd nested
+      - echo "${PWD##*/}"
+      - run: sh -c "exit 3"
+        allowed_exit_codes: [3]
+      - run: exit 5
+"#,
+        )
+        .unwrap();
+        context.base_dir = dir.path().to_path_buf();
+        let options = ExecutionOptions {
+            shell: Some("sh -c".to_string()),
+            ..ExecutionOptions::default()
+        };
+        let (messages, outcome) =
+            Arc::new(context).run_task_async("build", ParamContext::default(), options);
+        let messages = collect(messages).await;
+
+        assert_eq!(
+            outcome.await.unwrap().unwrap(),
+            TaskOutcome::Completed(ExitStatus::Exited(5))
+        );
+        assert!(messages.contains(&ExecutionMessage::CommandOutput {
+            output: "nested".to_string()
+        }));
+        assert!(messages.contains(&ExecutionMessage::CommandSucceeded));
+    }
+
+    #[tokio::test]
+    async fn test_interrupt_async_run() {
+        let context = Arc::new(
+            crate::load_from_str("tasks:\n  wait:\n    actions:\n      - sleep 10\n").unwrap(),
+        );
+        let options = ExecutionOptions::default();
+        let interrupter = options.interrupter.clone();
+        let started_at = Instant::now();
+        let (messages, outcome) = context.run_task_async("wait", ParamContext::default(), options);
+
+        time::sleep(Duration::from_millis(200)).await;
+        interrupter.interrupt();
+
+        assert_eq!(outcome.await.unwrap().unwrap(), TaskOutcome::Interrupted);
+        assert!(started_at.elapsed() < Duration::from_secs(5));
+        assert!(
+            collect(messages)
+                .await
+                .contains(&ExecutionMessage::Interrupted)
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use crate::model::{RunError, TaskerieContext};
+
+impl TaskerieContext {
+    /// Name of the task closest to `name`, ignoring case, when it is close enough to be a typo.
+    #[must_use]
+    pub fn closest_task_name(&self, name: &str) -> Option<&str> {
+        // One edit every three characters, rounded up
+        let max_distance = name.chars().count().div_ceil(3);
+        self.tasks
+            .keys()
+            .map(|task_name| (edit_distance(name, task_name), task_name))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, task_name)| task_name.as_str())
+    }
+
+    /// Error for a task that is not defined, suggesting the closest task name if any.
+    pub(crate) fn task_not_found(&self, name: &str) -> RunError {
+        RunError::TaskNotFound {
+            name: name.to_string(),
+            suggestion: self.closest_task_name(name).map(str::to_string),
+        }
+    }
+}
+
+/// Levenshtein distance between two strings, ignoring case.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.to_lowercase().chars().collect::<Vec<_>>();
+    let b = b.to_lowercase().chars().collect::<Vec<_>>();
+
+    // Distances between the start of `a` and the start of `b` processed so far
+    let mut distances = (0..=b.len()).collect::<Vec<_>>();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut previous_diagonal = distances[0];
+        distances[0] = i + 1;
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous_diagonal + usize::from(a_char != b_char);
+            previous_diagonal = distances[j + 1];
+            distances[j + 1] = substitution
+                .min(distances[j] + 1)
+                .min(previous_diagonal + 1);
+        }
+    }
+    distances[b.len()]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("build", "build"), 0);
+        assert_eq!(edit_distance("Build", "build"), 0);
+        assert_eq!(edit_distance("biuld", "build"), 2);
+        assert_eq!(edit_distance("buid", "build"), 1);
+        assert_eq!(edit_distance("", "test"), 4);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_closest_task_name() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    actions:
+      - echo build
+  deploy_production:
+    actions:
+      - echo deploy
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(context.closest_task_name("BUILD"), Some("build"));
+        assert_eq!(context.closest_task_name("buid"), Some("build"));
+        assert_eq!(context.closest_task_name("biuld"), Some("build"));
+        assert_eq!(context.closest_task_name("bld"), None);
+        assert_eq!(
+            context.closest_task_name("deploy_prodution"),
+            Some("deploy_production")
+        );
+        assert_eq!(context.closest_task_name("lint"), None);
+        assert_eq!(
+            context.task_not_found("biuld").to_string(),
+            "Task 'biuld' not found. Did you mean 'build'?"
+        );
+    }
+}
@@ -1,34 +1,227 @@
 use std::convert::TryInto;
 
+use anyhow::{Context, anyhow, bail};
+use indexmap::IndexMap;
+use itertools::Itertools;
+
 use crate::{
     config,
-    model::{self, task},
+    model::{self, Delimiters, InterpolatedString, task},
 };
 
-impl TryFrom<config::Task> for model::task::Task {
+impl TryFrom<(config::Task, &Delimiters)> for model::task::Task {
     type Error = anyhow::Error;
 
-    fn try_from(value: config::Task) -> Result<Self, Self::Error> {
+    fn try_from((value, delimiters): (config::Task, &Delimiters)) -> Result<Self, Self::Error> {
+        let parse = |value: &str| InterpolatedString::parse_with_delimiters(value, delimiters);
+        if value.tags.iter().any(|tag| tag.trim().is_empty()) {
+            bail!("Tags cannot be empty");
+        }
+        if value.persistent_shell && value.parallel {
+            bail!("Parallel actions cannot share a persistent shell");
+        }
+        if value.persistent_shell
+            && value.actions.iter().any(|action| {
+                matches!(action, config::Action::Command(command) if command.working_directory.is_some())
+            })
+        {
+            bail!("Commands sharing a persistent shell cannot have their own working directory");
+        }
         Ok(Self {
+            description: value.description,
             actions: value
                 .actions
                 .into_iter()
-                .map(TryInto::try_into)
+                .map(|action| (action, delimiters).try_into())
                 .collect::<anyhow::Result<Vec<_>>>()?,
             params: value
                 .params
                 .into_iter()
-                .map(|(name, param)| (name, param.into()))
-                .collect(),
-            working_directory: value.working_directory.map(|dir| dir.parse()).transpose()?,
+                .map(|(name, param)| {
+                    (param, delimiters)
+                        .try_into()
+                        .map(|param| (name.clone(), param))
+                        .with_context(|| format!("Invalid parameter '{name}'"))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            working_directory: value.working_directory.map(|dir| parse(&dir)).transpose()?,
+            create_working_directory: value.create_working_directory,
+            raw_working_directory: !value.canonicalize_cwd.unwrap_or(true),
+            needs: value
+                .needs
+                .into_iter()
+                .map(|dependency| (dependency, delimiters).try_into())
+                .collect::<anyhow::Result<_>>()?,
+            parallel: value.parallel,
+            max_parallel: value.max_parallel,
+            persistent_shell: value.persistent_shell,
+            max_output_bytes: value.max_output_bytes,
+            clean_env: value.clean_env,
+            env: value
+                .env
+                .into_iter()
+                .map(|(key, value)| parse(&value).map(|value| (key, value)))
+                .collect::<Result<_, _>>()?,
+            tags: value.tags,
+            confirm: value.confirm,
+            cache: value.cache,
+            inputs: value.inputs,
+        })
+    }
+}
+
+impl TryFrom<(config::Dependency, &Delimiters)> for model::action::TaskCall {
+    type Error = anyhow::Error;
+
+    fn try_from(
+        (dependency, delimiters): (config::Dependency, &Delimiters),
+    ) -> Result<Self, Self::Error> {
+        let (name, params) = match dependency {
+            config::Dependency::Task(name) => (name, IndexMap::new()),
+            config::Dependency::TaskWithParams(task) => task
+                .into_iter()
+                .exactly_one()
+                .map_err(|_| anyhow!("A dependency must name a single task"))?,
+        };
+        Ok(Self {
+            name,
+            params: params
+                .into_iter()
+                .map(|(key, value)| {
+                    InterpolatedString::parse_with_delimiters(&value, delimiters)
+                        .map(|value| (key, value))
+                })
+                .collect::<Result<_, _>>()?,
+            ..Default::default()
+        })
+    }
+}
+
+impl TryFrom<(config::Param, &Delimiters)> for task::Param {
+    type Error = anyhow::Error;
+
+    fn try_from((param, delimiters): (config::Param, &Delimiters)) -> Result<Self, Self::Error> {
+        let param_type = match (param.param_type, param.choices.is_empty()) {
+            (None | Some(config::ParamType::String), true) => task::ParamType::String,
+            (Some(config::ParamType::Int), true) => task::ParamType::Int,
+            (Some(config::ParamType::Bool), true) => task::ParamType::Bool,
+            (None | Some(config::ParamType::Enum), false) => task::ParamType::Enum(param.choices),
+            (Some(config::ParamType::Enum), true) => bail!("An enum parameter requires choices"),
+            (Some(_), false) => bail!("Only enum parameters can have choices"),
+        };
+
+        let parse = |value: &str| InterpolatedString::parse_with_delimiters(value, delimiters);
+        let source = match (param.from_command, param.from_file) {
+            (Some(_), Some(_)) => {
+                bail!("A parameter cannot be read from both a command and a file")
+            }
+            (Some(command), None) => Some(task::ParamSource::Command(parse(&command)?)),
+            (None, Some(path)) => Some(task::ParamSource::File(parse(&path)?)),
+            (None, None) => None,
+        };
+        if source.is_some() && param.default.is_some() {
+            bail!("A parameter read from a command or a file cannot have a default");
+        }
+        let default = param.default.map(|default| parse(&default)).transpose()?;
+        // Interpolated defaults are only known, and validated, when the task runs
+        if let Some(default) = &default
+            && default.parts.is_empty()
+        {
+            param_type
+                .validate(&default.value)
+                .map_err(|reason| anyhow!("Invalid default value: {reason}"))?;
+        }
+
+        Ok(Self {
+            default,
+            source,
+            secret: param.secret,
+            param_type,
         })
     }
 }
 
-impl From<config::Param> for task::Param {
-    fn from(param: config::Param) -> Self {
-        Self {
-            default: param.default,
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn param(
+        default: Option<&str>,
+        param_type: Option<config::ParamType>,
+        choices: &[&str],
+    ) -> config::Param {
+        config::Param {
+            default: default.map(ToString::to_string),
+            from_command: None,
+            from_file: None,
+            secret: false,
+            param_type,
+            choices: choices.iter().map(ToString::to_string).collect(),
+            unknown: config::UnknownKeys::default(),
         }
     }
+
+    #[test]
+    fn test_untyped_param_is_string() {
+        let param =
+            task::Param::try_from((param(None, None, &[]), &Delimiters::default())).unwrap();
+        assert_eq!(param.param_type, task::ParamType::String);
+    }
+
+    #[test]
+    fn test_enum_param() {
+        let param = task::Param::try_from((
+            param(Some("dev"), Some(config::ParamType::Enum), &["dev", "prod"]),
+            &Delimiters::default(),
+        ))
+        .unwrap();
+        assert_eq!(
+            param.param_type,
+            task::ParamType::Enum(vec!["dev".to_string(), "prod".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_enum_param_without_choices() {
+        assert!(
+            task::Param::try_from((
+                param(None, Some(config::ParamType::Enum), &[]),
+                &Delimiters::default()
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_invalid_default() {
+        assert!(
+            task::Param::try_from((
+                param(Some("abc"), Some(config::ParamType::Int), &[]),
+                &Delimiters::default()
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_param_sources() {
+        let delimiters = Delimiters::default();
+        let mut config_param = param(None, None, &[]);
+        config_param.from_file = Some("secret.txt".to_string());
+        let parsed = task::Param::try_from((config_param, &delimiters)).unwrap();
+        assert_eq!(
+            parsed.source,
+            Some(task::ParamSource::File("secret.txt".parse().unwrap()))
+        );
+        assert!(!parsed.is_required());
+
+        let mut config_param = param(Some("x"), None, &[]);
+        config_param.from_command = Some("op read secret".to_string());
+        assert_eq!(
+            task::Param::try_from((config_param, &delimiters))
+                .unwrap_err()
+                .to_string(),
+            "A parameter read from a command or a file cannot have a default"
+        );
+    }
 }
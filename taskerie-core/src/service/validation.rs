@@ -0,0 +1,552 @@
+use std::collections::HashSet;
+
+use anyhow::bail;
+use itertools::Itertools;
+
+use crate::{
+    model::{Action, TaskerieContext},
+    service::interpolated_string::ENV_VARIABLE_PREFIX,
+};
+
+impl TaskerieContext {
+    /// Run every check of the task definitions, as done when loading a config. Structural
+    /// problems, such as undefined tasks, are all reported together.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut problems = Vec::new();
+        if self.tasks.is_empty() {
+            problems.push("The config defines no tasks".to_string());
+        }
+        if let Some(default_task) = &self.default_task
+            && !self.tasks.contains_key(default_task)
+        {
+            problems.push(format!("Default task '{default_task}' is not defined"));
+        }
+        if let Err(error) = self.validate_non_empty_tasks() {
+            problems.push(error.to_string());
+        }
+        if let Err(error) = self.validate_task_references() {
+            problems.push(error.to_string());
+        }
+        if !problems.is_empty() {
+            bail!("{}", problems.join("\n"));
+        }
+        self.validate_no_cycles()?;
+        self.validate_task_call_params()?;
+        self.validate_interpolations()?;
+        for warning in self.validate_params() {
+            log::warn!("{warning}");
+        }
+        Ok(())
+    }
+
+    /// Check that every task has actions to run or tasks it needs, listing all the empty ones.
+    pub fn validate_non_empty_tasks(&self) -> anyhow::Result<()> {
+        let empty_tasks = self
+            .tasks
+            .iter()
+            .filter(|(_, task)| task.actions.is_empty() && task.needs.is_empty())
+            .map(|(name, _)| format!("'{name}'"))
+            .collect::<Vec<_>>();
+        if !empty_tasks.is_empty() {
+            bail!("Tasks have nothing to do: {}", empty_tasks.join(", "));
+        }
+        Ok(())
+    }
+
+    /// Check that every called or needed task is defined, listing all the undefined ones.
+    pub fn validate_task_references(&self) -> anyhow::Result<()> {
+        let mut dangling_references = Vec::new();
+        for (name, task) in &self.tasks {
+            for action in &task.actions {
+                if let Action::TaskCall(task_call) = action
+                    && !self.tasks.contains_key(&task_call.name)
+                {
+                    dangling_references.push(format!("'{name}' calls '{}'", task_call.name));
+                }
+            }
+            for dependency in &task.needs {
+                if !self.tasks.contains_key(&dependency.name) {
+                    dangling_references.push(format!("'{name}' needs '{}'", dependency.name));
+                }
+            }
+        }
+        if !dangling_references.is_empty() {
+            bail!(
+                "Undefined tasks are referenced: {}",
+                dangling_references.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Check that no task ends up calling itself, directly or through other tasks.
+    pub fn validate_no_cycles(&self) -> anyhow::Result<()> {
+        let mut visited = HashSet::new();
+        for name in self.tasks.keys() {
+            self.visit_task_calls(name, &mut Vec::new(), &mut visited)?;
+        }
+        Ok(())
+    }
+
+    /// Check that task calls only give parameters declared by the called task, and that needed
+    /// tasks are given all their required parameters.
+    pub fn validate_task_call_params(&self) -> anyhow::Result<()> {
+        for task in self.tasks.values() {
+            for task_call in task.task_calls() {
+                let Some(called_task) = self.tasks.get(&task_call.name) else {
+                    continue;
+                };
+                if let Some(param_name) = task_call
+                    .params
+                    .keys()
+                    .find(|param_name| !called_task.params.contains_key(*param_name))
+                {
+                    bail!("Task '{}' has no parameter '{param_name}'", task_call.name);
+                }
+            }
+            for dependency in &task.needs {
+                let Some(needed_task) = self.tasks.get(&dependency.name) else {
+                    continue;
+                };
+                if let Some((param_name, _)) = needed_task.params.iter().find(|(name, param)| {
+                    param.is_required()
+                        && !dependency.params.contains_key(*name)
+                        && !self.vars.contains_key(*name)
+                }) {
+                    bail!(
+                        "Task '{}' is needed without its required parameter '{param_name}'",
+                        dependency.name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the commands of every task only refer to its params, the outputs its commands
+    /// capture, the vars and environment variables, listing all the other variables. Variables
+    /// with a default value can be left undefined.
+    pub fn validate_interpolations(&self) -> anyhow::Result<()> {
+        let mut undeclared_variables = Vec::new();
+        for (name, task) in &self.tasks {
+            let commands = task
+                .actions
+                .iter()
+                .filter_map(|action| match action {
+                    Action::Command(command) => Some(command),
+                    Action::TaskCall(_) => None,
+                })
+                .collect::<Vec<_>>();
+            let is_known = |variable: &str| {
+                task.params.contains_key(variable)
+                    || self.vars.contains_key(variable)
+                    || variable.starts_with(ENV_VARIABLE_PREFIX)
+                    || commands
+                        .iter()
+                        .any(|command| command.capture.as_deref() == Some(variable))
+            };
+            for command in &commands {
+                let variables_with_default = command
+                    .command
+                    .parts
+                    .iter()
+                    .filter(|part| part.default.is_some())
+                    .map(|part| part.name.as_str())
+                    .collect::<HashSet<_>>();
+                for variable in command.command.variable_names() {
+                    if !is_known(variable) && !variables_with_default.contains(variable) {
+                        undeclared_variables.push(format!("'{name}' uses '{variable}'"));
+                    }
+                }
+            }
+        }
+        if !undeclared_variables.is_empty() {
+            bail!(
+                "Commands refer to undeclared variables: {}",
+                undeclared_variables.join(", ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Cross-check the params declared by each task with the ones it uses and is called with,
+    /// returning the anomalies that do not prevent it from running: params the task never refers
+    /// to, and task calls giving a param the default value it already has.
+    #[must_use]
+    pub fn validate_params(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        for (name, task) in &self.tasks {
+            // The global environment variables are rendered with the params of each task
+            let used_names = task
+                .templates()
+                .chain(self.env.values())
+                .flat_map(|template| &template.parts)
+                .map(|part| part.name.as_str())
+                .collect::<HashSet<_>>();
+            for param_name in task.params.keys() {
+                if !used_names.contains(param_name.as_str()) {
+                    warnings.push(format!(
+                        "Task '{name}' declares parameter '{param_name}' but never uses it"
+                    ));
+                }
+            }
+
+            for task_call in task.task_calls() {
+                let Some(called_task) = self.tasks.get(&task_call.name) else {
+                    continue;
+                };
+                for (param_name, value) in &task_call.params {
+                    if let Some(default) = called_task
+                        .params
+                        .get(param_name)
+                        .and_then(|param| param.default.as_ref())
+                        && default.source == value.source
+                    {
+                        warnings.push(format!(
+                            "Task '{name}' gives '{}' its parameter '{param_name}' with its default value '{}'",
+                            task_call.name, value.source
+                        ));
+                    }
+                }
+            }
+        }
+        warnings
+    }
+
+    fn visit_task_calls<'a>(
+        &'a self,
+        name: &'a str,
+        call_stack: &mut Vec<&'a str>,
+        visited: &mut HashSet<&'a str>,
+    ) -> anyhow::Result<()> {
+        if let Some(position) = call_stack.iter().position(|called| *called == name) {
+            bail!(
+                "Cyclic task call detected: {} -> {name}",
+                call_stack[position..].iter().join(" -> ")
+            );
+        }
+        if !visited.insert(name) {
+            return Ok(());
+        }
+        let Some(task) = self.tasks.get(name) else {
+            return Ok(());
+        };
+
+        call_stack.push(name);
+        for task_call in task.task_calls() {
+            self.visit_task_calls(&task_call.name, call_stack, visited)?;
+        }
+        call_stack.pop();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use crate::model::{Task, action::TaskCall};
+
+    use super::*;
+
+    fn task_calling(names: &[&str]) -> Task {
+        Task {
+            actions: names
+                .iter()
+                .map(|name| {
+                    Action::TaskCall(TaskCall {
+                        name: (*name).to_string(),
+                        ..Default::default()
+                    })
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn context(tasks: &[(&str, &[&str])]) -> TaskerieContext {
+        TaskerieContext {
+            tasks: tasks
+                .iter()
+                .map(|(name, calls)| ((*name).to_string(), task_calling(calls)))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_task_call_with_declared_params() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  a:
+    actions:
+      - b:
+          name: x
+  b:
+    params:
+      name: {}
+    actions:
+      - echo {{ name }}
+"#,
+        );
+        assert!(context.is_ok());
+    }
+
+    #[test]
+    fn test_task_call_with_unknown_param() {
+        let error = crate::load_from_str(
+            r#"
+tasks:
+  a:
+    actions:
+      - b:
+          nmae: x
+  b:
+    params:
+      name: {}
+    actions:
+      - echo {{ name }}
+"#,
+        )
+        .unwrap_err();
+        assert_eq!(error.to_string(), "Task 'b' has no parameter 'nmae'");
+    }
+
+    #[test]
+    fn test_dangling_task_references() {
+        let context = context(&[("a", &["b", "missing"]), ("b", &["other"])]);
+        assert_eq!(
+            context.validate().unwrap_err().to_string(),
+            "Undefined tasks are referenced: 'a' calls 'missing', 'b' calls 'other'"
+        );
+    }
+
+    #[test]
+    fn test_default_task() {
+        let mut context = crate::load_from_str(
+            r#"
+default: build
+tasks:
+  build:
+    actions:
+      - echo build
+"#,
+        )
+        .unwrap();
+
+        context.default_task = Some("biuld".to_string());
+        assert_eq!(
+            context.validate().unwrap_err().to_string(),
+            "Default task 'biuld' is not defined"
+        );
+    }
+
+    #[test]
+    fn test_dangling_needs() {
+        let mut context = context(&[("a", &[])]);
+        context.tasks["a"].needs = vec![TaskCall {
+            name: "missing".to_string(),
+            ..Default::default()
+        }];
+        assert_eq!(
+            context.validate().unwrap_err().to_string(),
+            "Undefined tasks are referenced: 'a' needs 'missing'"
+        );
+    }
+
+    #[test]
+    fn test_needs_cycle() {
+        let mut context = context(&[("a", &[]), ("b", &["a"])]);
+        context.tasks["a"].needs = vec![TaskCall {
+            name: "b".to_string(),
+            ..Default::default()
+        }];
+        assert_eq!(
+            context.validate_no_cycles().unwrap_err().to_string(),
+            "Cyclic task call detected: a -> b -> a"
+        );
+    }
+
+    #[test]
+    fn test_no_cycles() {
+        let context = context(&[("a", &["b", "c"]), ("b", &["c"]), ("c", &[])]);
+        assert!(context.validate_no_cycles().is_ok());
+    }
+
+    #[test]
+    fn test_two_task_cycle() {
+        let context = context(&[("a", &["b"]), ("b", &["a"])]);
+        assert_eq!(
+            context.validate_no_cycles().unwrap_err().to_string(),
+            "Cyclic task call detected: a -> b -> a"
+        );
+    }
+
+    #[test]
+    fn test_self_cycle() {
+        let context = context(&[("a", &["a"])]);
+        assert_eq!(
+            context.validate_no_cycles().unwrap_err().to_string(),
+            "Cyclic task call detected: a -> a"
+        );
+    }
+
+    #[test]
+    fn test_cycle_not_including_first_task() {
+        let context = context(&[("a", &["b"]), ("b", &["c"]), ("c", &["b"])]);
+        assert_eq!(
+            context.validate_no_cycles().unwrap_err().to_string(),
+            "Cyclic task call detected: b -> c -> b"
+        );
+    }
+
+    #[test]
+    fn test_needs_without_required_param() {
+        let error = crate::load_from_str(
+            r#"
+tasks:
+  a:
+    needs: [b]
+    actions:
+      - echo a
+  b:
+    params:
+      name: {}
+    actions:
+      - echo {{ name }}
+"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Task 'b' is needed without its required parameter 'name'"
+        );
+    }
+
+    #[test]
+    fn test_no_tasks() {
+        assert_eq!(
+            crate::load_from_str("tasks: {}").unwrap_err().to_string(),
+            "The config defines no tasks"
+        );
+    }
+
+    #[test]
+    fn test_structural_problems_are_reported_together() {
+        let error = crate::load_from_str(
+            r#"
+tasks:
+  build:
+    actions: []
+  test:
+    actions:
+      - missing: {}
+  lint:
+    actions: []
+"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Tasks have nothing to do: 'build', 'lint'\n\
+             Undefined tasks are referenced: 'test' calls 'missing'"
+        );
+    }
+
+    #[test]
+    fn test_task_with_only_needs_is_not_empty() {
+        let context = crate::load_from_str(
+            r#"
+tasks:
+  all:
+    needs: [build]
+  build:
+    actions:
+      - echo build
+"#,
+        );
+        context.unwrap();
+    }
+
+    #[test]
+    fn test_needs_with_required_param_from_vars() {
+        let context = crate::load_from_str(
+            r#"
+vars:
+  name: world
+tasks:
+  a:
+    needs: [b]
+  b:
+    params:
+      name:
+    actions:
+      - echo {{ name }}
+"#,
+        );
+        context.unwrap();
+    }
+
+    #[test]
+    fn test_validate_params() {
+        let context = crate::load_from_str(
+            r#"
+env:
+  TARGET: "{{ target }}"
+tasks:
+  build:
+    params:
+      mode:
+        default: debug
+      target:
+        default: host
+      unused:
+        default: x
+    actions:
+      - echo {{ mode }}
+  release:
+    actions:
+      - build:
+          mode: debug
+          target: arm
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            context.validate_params(),
+            [
+                "Task 'build' declares parameter 'unused' but never uses it",
+                "Task 'release' gives 'build' its parameter 'mode' with its default value 'debug'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_undeclared_variables() {
+        let error = crate::load_from_str(
+            r#"
+vars:
+  registry: ghcr.io
+tasks:
+  build:
+    params:
+      target:
+    actions:
+      - run: git rev-parse HEAD
+        capture: commit
+      - 'docker build {{ registry }}/{{ target }}:{{ commit }} {{ env.HOME }} {{ mode | default: release }}'
+      - echo {{ taget }} {{ taget }}
+  test:
+    actions:
+      - echo {{ target }}
+"#,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Commands refer to undeclared variables: 'build' uses 'taget', 'test' uses 'target'"
+        );
+    }
+}
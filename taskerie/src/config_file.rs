@@ -0,0 +1,67 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::bail;
+
+/// Name of the config file looked for, the example one of the repository in debug builds.
+pub const CONFIG_FILE_NAME: &str = if cfg!(debug_assertions) {
+    "taskerie.example.yaml"
+} else {
+    "taskerie.yaml"
+};
+
+/// Config file given on the command line, or else the closest one found from the current
+/// directory.
+pub fn config_path(file: Option<&Path>) -> anyhow::Result<PathBuf> {
+    if let Some(file) = file {
+        return Ok(file.to_path_buf());
+    }
+    let current_dir = std::env::current_dir()?;
+    match find_config(&current_dir) {
+        Some(path) => Ok(path),
+        None => bail!(
+            "No {CONFIG_FILE_NAME} found in {} or its parent directories, use --file to give the \
+             path of a config",
+            current_dir.display()
+        ),
+    }
+}
+
+/// Look for the config file in `start` and then in each of its parent directories.
+pub fn find_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_find_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_config(&nested), None);
+
+        let root_config = dir.path().join(CONFIG_FILE_NAME);
+        fs::write(&root_config, "tasks: {}\n").unwrap();
+        assert_eq!(find_config(&nested), Some(root_config));
+
+        // The closest config wins, directories named like it are skipped
+        let closest_config = dir.path().join("a").join(CONFIG_FILE_NAME);
+        fs::write(&closest_config, "tasks: {}\n").unwrap();
+        fs::create_dir(nested.join(CONFIG_FILE_NAME)).unwrap();
+        assert_eq!(find_config(&nested), Some(closest_config));
+    }
+
+    #[test]
+    fn test_config_path_flag() {
+        let path = Path::new("missing.yaml");
+
+        assert_eq!(config_path(Some(path)).unwrap(), path);
+    }
+}
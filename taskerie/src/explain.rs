@@ -0,0 +1,120 @@
+use std::fmt::Write;
+
+use taskerie_core::model::{ExecutionPlan, PlannedAction};
+
+/// Format a plan as an indented tree, the needed and called tasks being nested in the task
+/// running them.
+#[must_use]
+pub fn format_plan(plan: &ExecutionPlan) -> String {
+    let mut output = String::new();
+    write_plan(&mut output, plan, "", 0);
+    output
+}
+
+fn write_plan(output: &mut String, plan: &ExecutionPlan, label: &str, depth: usize) {
+    let indent = "  ".repeat(depth);
+    let _ = write!(output, "{indent}{label}{}", plan.task_name);
+    if !plan.params.is_empty() {
+        let params = plan
+            .params
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>();
+        let _ = write!(output, " ({})", params.join(", "));
+    }
+    if let Some(working_directory) = &plan.working_directory {
+        let _ = write!(output, " in {working_directory}");
+    }
+    if plan.parallel {
+        output.push_str(" [parallel]");
+    }
+    output.push('\n');
+
+    for need in &plan.needs {
+        write_plan(output, need, "\u{21B3} needs ", depth + 1);
+    }
+    for action in &plan.actions {
+        match action {
+            PlannedAction::Command {
+                command,
+                continue_on_error,
+            } => {
+                let _ = write!(output, "{indent}  \u{231C} {command}");
+                if *continue_on_error {
+                    output.push_str(" [continue on error]");
+                }
+                output.push('\n');
+            }
+            PlannedAction::TaskCall {
+                plan,
+                continue_on_error,
+            } => {
+                let label = if *continue_on_error {
+                    "\u{21B3} calls (continue on error) "
+                } else {
+                    "\u{21B3} calls "
+                };
+                write_plan(output, plan, label, depth + 1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn command(command: &str) -> PlannedAction {
+        PlannedAction::Command {
+            command: command.to_string(),
+            continue_on_error: false,
+        }
+    }
+
+    #[test]
+    fn test_format_plan() {
+        let plan = ExecutionPlan {
+            task_name: "release".to_string(),
+            params: vec![("version".to_string(), "<version?>".to_string())],
+            working_directory: None,
+            parallel: false,
+            needs: vec![ExecutionPlan {
+                task_name: "lint".to_string(),
+                params: Vec::new(),
+                working_directory: None,
+                parallel: true,
+                needs: Vec::new(),
+                actions: vec![command("cargo clippy"), command("cargo fmt --check")],
+            }],
+            actions: vec![
+                PlannedAction::Command {
+                    command: "cargo build".to_string(),
+                    continue_on_error: true,
+                },
+                PlannedAction::TaskCall {
+                    plan: ExecutionPlan {
+                        task_name: "publish".to_string(),
+                        params: vec![("tag".to_string(), "v<version?>".to_string())],
+                        working_directory: Some("dist".to_string()),
+                        parallel: false,
+                        needs: Vec::new(),
+                        actions: vec![command("docker push app:v<version?>")],
+                    },
+                    continue_on_error: false,
+                },
+            ],
+        };
+
+        assert_eq!(
+            format_plan(&plan),
+            "release (version=<version?>)
+  \u{21B3} needs lint [parallel]
+    \u{231C} cargo clippy
+    \u{231C} cargo fmt --check
+  \u{231C} cargo build [continue on error]
+  \u{21B3} calls publish (tag=v<version?>) in dist
+    \u{231C} docker push app:v<version?>
+"
+        );
+    }
+}
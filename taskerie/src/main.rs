@@ -1,100 +1,379 @@
+mod config_file;
+mod explain;
+mod menu;
+mod param_editor;
+mod printer;
+mod render;
+mod task_args;
+mod task_list;
+mod watch;
+
 use std::{
-    env::args,
-    path::Path,
-    sync::{Arc, mpsc},
+    io::{self, IsTerminal},
+    path::PathBuf,
+    process,
+    sync::{
+        Arc, LazyLock,
+        atomic::{AtomicBool, Ordering},
+        mpsc,
+    },
     thread,
 };
 
-use anyhow::Context;
-use taskerie_core::{message::ExecutionMessage, model::ParamContext};
+use anyhow::{Context, bail};
+use clap::{CommandFactory, Parser, ValueEnum, builder::PossibleValuesParser};
+use clap_complete::Shell;
+use menu::MenuEntry;
+use printer::{MessagePrinter, OutputLevel};
+use render::{OutputRenderer, PlainRenderer, TerminalRenderer};
+use taskerie_core::{
+    ExitStatus,
+    message::ExecutionMessage,
+    model::{ExecutionOptions, Interrupter, ParamContext, TaskOutcome, TaskerieContext},
+};
 
-fn main() -> anyhow::Result<()> {
-    pretty_env_logger::init();
+/// Interrupts the running task on Ctrl-C.
+static INTERRUPTER: LazyLock<Interrupter> = LazyLock::new(Interrupter::default);
+/// Whether a task is running, otherwise Ctrl-C exits right away.
+static TASK_RUNNING: AtomicBool = AtomicBool::new(false);
 
-    let path = if cfg!(debug_assertions) {
-        Path::new("taskerie.example.yaml")
-    } else {
-        Path::new("taskerie.yaml")
-    };
-    let mut taskerie = Arc::new(taskerie_core::load(path).with_context(|| path.display())?);
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    /// Task to run directly instead of showing the menu, defaults to the `default` task of the
+    /// config
+    task: Option<String>,
+    /// Config file to load, looked for in the current directory and its parents by default
+    #[arg(short, long, value_name = "PATH")]
+    file: Option<PathBuf>,
+    /// List every task with its parameters and description, then exit
+    #[arg(short, long, conflicts_with = "task")]
+    list: bool,
+    /// Only list the tasks bearing this tag
+    #[arg(long, requires = "list")]
+    tag: Option<String>,
+    /// Print the parameters of a task with their type and default, then exit
+    #[arg(long, value_name = "TASK", conflicts_with_all = ["task", "list"])]
+    describe: Option<String>,
+    /// Task parameters, given as `--name value`, `--name=value` or positionally in declaration
+    /// order
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true, requires = "task")]
+    params: Vec<String>,
+    /// Review and edit the task parameters before running it
+    #[arg(short, long)]
+    edit_params: bool,
+    /// Print the commands the task would run without executing them
+    #[arg(long)]
+    dry_run: bool,
+    /// Run the tasks asking for confirmation without asking, which is required to run them when
+    /// the input is not a terminal or the output is JSON
+    #[arg(short, long)]
+    yes: bool,
+    /// Print the tree of commands and tasks the task would run, without running anything
+    #[arg(long, conflicts_with_all = ["watch", "dry_run"])]
+    explain: bool,
+    /// Run the task again whenever a file matching this glob, relative to the current
+    /// directory, changes
+    #[arg(long, value_name = "GLOB")]
+    watch: Option<String>,
+    /// Print a completion script for this shell, completing the task names of the config in the
+    /// current directory, then exit
+    #[arg(long, value_name = "SHELL", conflicts_with = "task")]
+    completions: Option<Shell>,
+    /// How execution events are printed
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Only print failures
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Also print the parameter values and working directories
+    #[arg(short, long)]
+    verbose: bool,
+    /// Print without colors, which is also the case when the `NO_COLOR` environment variable is
+    /// set
+    #[arg(long)]
+    no_color: bool,
+}
 
-    let reload = "\u{2699}  Reload taskerie".to_string();
-    let exit = "\u{2699}  Exit".to_string();
+impl Cli {
+    fn output_level(&self) -> OutputLevel {
+        if self.quiet {
+            OutputLevel::Quiet
+        } else if self.verbose {
+            OutputLevel::Verbose
+        } else {
+            OutputLevel::Normal
+        }
+    }
 
-    loop {
-        let mut task_names = taskerie.get_all_standalone_task_names();
-        task_names.push(reload.clone());
-        task_names.push(exit.clone());
+    fn renderer(&self) -> Box<dyn OutputRenderer> {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        if self.no_color || no_color || !io::stdout().is_terminal() {
+            Box::new(PlainRenderer)
+        } else {
+            Box::new(TerminalRenderer)
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// Human readable output
+    Text,
+    /// One JSON object per event and line, missing parameters are never prompted for
+    Json,
+}
 
-        let task_name_arg = args().nth(1);
-        let selected_task = if let Some(task_name) = &task_name_arg {
-            task_name.clone()
+fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init();
+    let cli = Cli::parse();
+    ctrlc::set_handler(|| {
+        if TASK_RUNNING.load(Ordering::SeqCst) {
+            INTERRUPTER.interrupt();
         } else {
-            inquire::Select::new("Select a task to execute", task_names)
-                .with_page_size(999)
-                .prompt()?
+            process::exit(130);
+        }
+    })?;
+
+    let path = config_file::config_path(cli.file.as_deref());
+    if let Some(shell) = cli.completions {
+        // Completions are still useful without a config, they just do not complete task names
+        let task_names = path
+            .and_then(taskerie_core::load)
+            .map(|taskerie| taskerie.get_all_standalone_task_names())
+            .unwrap_or_default();
+        print_completions(shell, task_names);
+        return Ok(());
+    }
+    let path = &path?;
+    let mut taskerie =
+        Arc::new(taskerie_core::load(path).with_context(|| path.display().to_string())?);
+
+    if let Some(task_name) = &cli.describe {
+        let Some(task) = taskerie
+            .list_tasks()
+            .into_iter()
+            .find(|task| &task.name == task_name)
+        else {
+            report_unknown_task(&taskerie, task_name);
+            process::exit(1);
         };
+        println!("{}", task_list::format_task_help(&task));
+        return Ok(());
+    }
 
-        if selected_task == reload {
-            debug_assert_eq!(Arc::strong_count(&taskerie), 1);
-            taskerie = Arc::new(taskerie_core::load(path).with_context(|| path.display())?);
-            println!("Sucessfully reloaded");
-            continue;
+    if cli.list {
+        let mut tasks = taskerie.list_tasks();
+        if let Some(tag) = &cli.tag {
+            tasks.retain(|task| task.tags.contains(tag));
         }
+        println!("{}", task_list::format_task_list(&tasks));
+        return Ok(());
+    }
 
-        if selected_task == exit {
-            break;
+    if let Some(task_name) = cli.task.as_ref().or(taskerie.default_task.as_ref()) {
+        if cli.explain {
+            let param_context = match taskerie.get_task_by_name(task_name) {
+                Some(task) => task_args::parse_task_args(task_name, task, &cli.params)?,
+                None => ParamContext::default(),
+            };
+            let plan = taskerie.explain(task_name, &param_context)?;
+            print!("{}", explain::format_plan(&plan));
+            return Ok(());
+        }
+        if let Some(pattern) = &cli.watch {
+            let task_name = task_name.clone();
+            return watch::watch(path, taskerie, &task_name, pattern, &cli);
         }
+        process::exit(run_from_args(&taskerie, task_name, &cli)?);
+    }
+    if cli.watch.is_some() {
+        bail!("A task to watch is required");
+    }
+    if cli.explain {
+        bail!("A task to explain is required");
+    }
+
+    loop {
+        let selected_entry =
+            inquire::Select::new("Select a task to execute", menu::menu_entries(&taskerie))
+                .with_page_size(999)
+                .prompt()?;
 
-        let (tx, rx) = mpsc::channel();
-        let executor_taskerie = taskerie.clone();
-        let executor_selected_task = selected_task.clone();
-
-        let executor_thread = thread::spawn(move || {
-            executor_taskerie.run_task_by_name(
-                executor_selected_task,
-                &mut ParamContext::default(),
-                &tx,
-            )?;
-            anyhow::Ok(())
-        });
-
-        for message in rx {
-            match message {
-                ExecutionMessage::MissingRequiredTaskParameter { parameter_name } => {
-                    println!(
-                        "Parameter '{parameter_name}' is undefined and has no default value provided"
-                    );
-                }
-                ExecutionMessage::WorkingDirectoryNotFound { path } => {
-                    println!("\u{274C} Requested working directory \"{path}\" not found");
-                }
-                ExecutionMessage::AboutToRunCommand {
-                    command,
-                    working_directory,
-                } => {
-                    println!("\u{231C} {working_directory}> {command}");
-                }
-                ExecutionMessage::CommandFailed => {
-                    println!("\u{231E}\u{274C}");
-                }
-                ExecutionMessage::CommandSucceeded => {
-                    println!("\u{231E}\u{2705}");
-                }
-                ExecutionMessage::CommandOutput { output } => {
-                    println!("\u{23B8}{output}");
-                }
+        let selected_task = match selected_entry {
+            MenuEntry::Task { name, .. } => name,
+            MenuEntry::Reload => {
+                debug_assert_eq!(Arc::strong_count(&taskerie), 1);
+                taskerie = Arc::new(
+                    taskerie_core::load(path).with_context(|| path.display().to_string())?,
+                );
+                println!("Sucessfully reloaded");
+                continue;
+            }
+            MenuEntry::Exit => break,
+        };
+
+        let mut param_context = ParamContext::default();
+        if cli.edit_params {
+            let task = taskerie
+                .get_task_by_name(&selected_task)
+                .with_context(|| format!("Task {selected_task} not found"))?;
+            match param_editor::edit_params(task, &param_context, &taskerie.delimiters)? {
+                Some(edited) => param_context = edited,
+                None => continue,
             }
         }
 
-        if let Err(e) = executor_thread.join().unwrap() {
-            eprintln!("\u{274C} Error executing task {selected_task}: {e}");
+        run_task(&taskerie, &selected_task, param_context, &cli);
+    }
+
+    Ok(())
+}
+
+/// Print a completion script for `shell`, embedding the task names.
+fn print_completions(shell: Shell, task_names: Vec<String>) {
+    // `mut_arg` would move the argument after the parameters, which must stay last
+    let mut command = Cli::command().mut_args(|arg| {
+        if arg.get_id() == "task" {
+            arg.value_parser(PossibleValuesParser::new(&task_names))
+        } else {
+            arg
         }
+    });
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+}
+
+/// Tell that `task_name` is not defined, suggesting the task it is most likely a typo of.
+fn report_unknown_task(taskerie: &TaskerieContext, task_name: &str) {
+    eprintln!("\u{274C} Unknown task '{task_name}'");
+    if let Some(closest) = taskerie.closest_task_name(task_name) {
+        eprintln!("Did you mean '{closest}'?");
+        return;
+    }
+    eprintln!("Available tasks are:");
+    for name in taskerie.get_all_standalone_task_names() {
+        eprintln!("  {name}");
+    }
+}
+
+/// Run the task given on the command line and return the process exit code.
+fn run_from_args(
+    taskerie: &Arc<TaskerieContext>,
+    task_name: &str,
+    cli: &Cli,
+) -> anyhow::Result<i32> {
+    let Some(task) = taskerie.get_task_by_name(task_name) else {
+        report_unknown_task(taskerie, task_name);
+        return Ok(1);
+    };
 
-        if task_name_arg.is_some() {
-            break;
+    let mut param_context = task_args::parse_task_args(task_name, task, &cli.params)?;
+    if cli.edit_params {
+        match param_editor::edit_params(task, &param_context, &taskerie.delimiters)? {
+            Some(edited) => param_context = edited,
+            None => return Ok(1),
         }
     }
 
-    Ok(())
+    Ok(match run_task(taskerie, task_name, param_context, cli) {
+        Some(TaskOutcome::Completed(ExitStatus::Exited(code))) => i32::try_from(code).unwrap_or(1),
+        Some(outcome) if outcome.success() => 0,
+        _ => 1,
+    })
+}
+
+/// Run a task on a separate thread while printing its messages, then print a summary.
+///
+/// Returns `None` when the task could not be executed.
+fn run_task(
+    taskerie: &Arc<TaskerieContext>,
+    task_name: &str,
+    mut param_context: ParamContext,
+    cli: &Cli,
+) -> Option<TaskOutcome> {
+    let (tx, rx) = mpsc::channel();
+    let executor_taskerie = taskerie.clone();
+    let executor_task_name = task_name.to_string();
+
+    let (parameter_value_tx, parameter_value_rx) = mpsc::channel();
+    let (confirmation_tx, confirmation_rx) = mpsc::channel();
+    let options = ExecutionOptions {
+        dry_run: cli.dry_run,
+        parameter_values: (cli.format == OutputFormat::Text).then_some(parameter_value_rx),
+        confirmations: (cli.format == OutputFormat::Text && io::stdin().is_terminal())
+            .then_some(confirmation_rx),
+        assume_yes: cli.yes,
+        interrupter: INTERRUPTER.clone(),
+        ..ExecutionOptions::default()
+    };
+    INTERRUPTER.reset();
+    TASK_RUNNING.store(true, Ordering::SeqCst);
+
+    let executor_thread = thread::spawn(move || {
+        executor_taskerie.run_task_by_name_with_options(
+            executor_task_name,
+            &mut param_context,
+            &tx,
+            options,
+            |_, _| Ok(()),
+        )
+    });
+
+    let mut printer = MessagePrinter::new(cli.output_level(), cli.renderer());
+    for message in rx {
+        if cli.format == OutputFormat::Json {
+            println!(
+                "{}",
+                serde_json::to_string(&message).expect("Execution messages are serializable")
+            );
+            continue;
+        }
+        let (origin, message) = message.into_origin_message();
+        // The executor only stops waiting for answers if it is gone, in which case there is
+        // nothing to do.
+        match message {
+            ExecutionMessage::ParameterValueRequired {
+                parameter_name,
+                choices,
+                secret,
+            } => {
+                let value = if !choices.is_empty() {
+                    inquire::Select::new(&parameter_name, choices).prompt().ok()
+                } else if secret {
+                    inquire::Password::new(&parameter_name)
+                        .without_confirmation()
+                        .prompt()
+                        .ok()
+                } else {
+                    inquire::Text::new(&parameter_name).prompt().ok()
+                };
+                let _ = parameter_value_tx.send(value);
+            }
+            ExecutionMessage::ConfirmationRequired { prompt, .. } => {
+                let confirmed = inquire::Confirm::new(&prompt)
+                    .with_default(false)
+                    .prompt()
+                    .unwrap_or(false);
+                let _ = confirmation_tx.send(confirmed);
+            }
+            message => printer.print(&origin, message),
+        }
+    }
+
+    let result = executor_thread.join().unwrap();
+    TASK_RUNNING.store(false, Ordering::SeqCst);
+    match result {
+        // The JSON output is made of events only
+        Ok(outcome) if cli.format == OutputFormat::Json => Some(outcome),
+        Ok(outcome) => {
+            printer.print_timings();
+            printer.print_outcome(task_name, &outcome);
+            Some(outcome)
+        }
+        Err(e) => {
+            printer.print_error(task_name, &e.into());
+            None
+        }
+    }
 }
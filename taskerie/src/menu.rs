@@ -0,0 +1,68 @@
+use std::fmt;
+
+use taskerie_core::model::TaskerieContext;
+
+/// An entry of the interactive task selection menu.
+#[derive(PartialEq, Eq, Debug)]
+pub enum MenuEntry {
+    Task {
+        name: String,
+        description: Option<String>,
+    },
+    Reload,
+    Exit,
+}
+
+impl fmt::Display for MenuEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MenuEntry::Task {
+                name,
+                description: Some(description),
+            } => write!(f, "{name} \u{2014} {description}"),
+            MenuEntry::Task {
+                name,
+                description: None,
+            } => write!(f, "{name}"),
+            MenuEntry::Reload => write!(f, "\u{2699}  Reload taskerie"),
+            MenuEntry::Exit => write!(f, "\u{2699}  Exit"),
+        }
+    }
+}
+
+/// Build the menu entries for every standalone task, followed by the built-in entries.
+#[must_use]
+pub fn menu_entries(taskerie: &TaskerieContext) -> Vec<MenuEntry> {
+    taskerie
+        .get_all_standalone_tasks()
+        .into_iter()
+        .map(|(name, description)| MenuEntry::Task {
+            name: name.to_string(),
+            description: description.map(ToString::to_string),
+        })
+        .chain([MenuEntry::Reload, MenuEntry::Exit])
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_task_entry_with_description() {
+        let entry = MenuEntry::Task {
+            name: "build".to_string(),
+            description: Some("Build the project".to_string()),
+        };
+        assert_eq!(entry.to_string(), "build \u{2014} Build the project");
+    }
+
+    #[test]
+    fn test_task_entry_without_description() {
+        let entry = MenuEntry::Task {
+            name: "build".to_string(),
+            description: None,
+        };
+        assert_eq!(entry.to_string(), "build");
+    }
+}
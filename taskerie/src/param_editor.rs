@@ -0,0 +1,269 @@
+use std::fmt;
+
+use inquire::{Select, Text};
+use taskerie_core::model::{Delimiters, ParamContext, Task, task::ParamType};
+
+/// A task parameter as presented to the user before the task runs.
+#[derive(PartialEq, Eq, Debug)]
+pub struct EditableParam {
+    pub name: String,
+    pub value: Option<String>,
+    /// Values the parameter is restricted to, empty when any value is accepted.
+    pub choices: Vec<String>,
+    /// Whether a parameter without value is read from its command or file when the task runs.
+    pub read_when_run: bool,
+}
+
+impl fmt::Display for EditableParam {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            Some(value) => write!(f, "{} = {value}", self.name),
+            None if self.read_when_run => write!(f, "{} = <read when run>", self.name),
+            None => write!(f, "{} = <required>", self.name),
+        }
+    }
+}
+
+/// Build the initial editable view of a task's parameters, pre-filled with the values already
+/// provided or their defaults. The ones read from a command or a file are left without value.
+#[must_use]
+pub fn editable_params(
+    task: &Task,
+    param_context: &ParamContext,
+    delimiters: &Delimiters,
+) -> Vec<EditableParam> {
+    task.params
+        .iter()
+        .map(|(name, param)| EditableParam {
+            name: name.clone(),
+            value: param_context
+                .get(name)
+                .map(ToString::to_string)
+                .or_else(|| {
+                    // Defaults referring to params not given yet are shown as written
+                    param.default.as_ref().map(|default| {
+                        default.render(param_context).map_or_else(
+                            |_| default.template_with_delimiters(delimiters),
+                            |value| value.into_owned(),
+                        )
+                    })
+                }),
+            choices: match &param.param_type {
+                ParamType::Enum(choices) => choices.clone(),
+                _ => Vec::new(),
+            },
+            read_when_run: param.source.is_some(),
+        })
+        .collect()
+}
+
+/// Let the user edit every parameter of the task before confirming execution.
+///
+/// Returns `None` when the user cancels.
+pub fn edit_params(
+    task: &Task,
+    param_context: &ParamContext,
+    delimiters: &Delimiters,
+) -> anyhow::Result<Option<ParamContext>> {
+    let mut params = editable_params(task, param_context, delimiters);
+    let run = "\u{25B6}  Run".to_string();
+    let cancel = "\u{2699}  Cancel".to_string();
+
+    loop {
+        let mut choices = params.iter().map(ToString::to_string).collect::<Vec<_>>();
+        choices.push(run.clone());
+        choices.push(cancel.clone());
+
+        let selected = Select::new("Edit parameters before running", choices)
+            .with_page_size(999)
+            .raw_prompt()?;
+
+        if let Some(param) = params.get_mut(selected.index) {
+            param.value = Some(prompt_value(param)?);
+            continue;
+        }
+
+        if selected.value == cancel {
+            return Ok(None);
+        }
+
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.value.is_none() && !param.read_when_run)
+        {
+            println!("Parameter '{}' has no value", param.name);
+            continue;
+        }
+
+        // The params left without value are read when the task runs
+        let mut param_context = ParamContext::default();
+        for param in &params {
+            if let Some(value) = &param.value {
+                param_context.set(&param.name, value);
+            }
+        }
+        return Ok(Some(param_context));
+    }
+}
+
+/// Prompt for a new value, starting from the current one.
+fn prompt_value(param: &EditableParam) -> anyhow::Result<String> {
+    if param.choices.is_empty() {
+        let mut prompt = Text::new(&param.name);
+        if let Some(value) = &param.value {
+            prompt = prompt.with_initial_value(value);
+        }
+        return Ok(prompt.prompt()?);
+    }
+
+    let starting_cursor = param
+        .value
+        .as_ref()
+        .and_then(|value| param.choices.iter().position(|choice| choice == value))
+        .unwrap_or_default();
+    Ok(Select::new(&param.name, param.choices.clone())
+        .with_starting_cursor(starting_cursor)
+        .prompt()?)
+}
+
+#[cfg(test)]
+mod test {
+    use indexmap::IndexMap;
+    use taskerie_core::model::task::{Param, ParamSource};
+
+    use super::*;
+
+    #[test]
+    fn test_editable_params_from_task() {
+        let mut params = IndexMap::new();
+        params.insert(
+            "name".to_string(),
+            Param {
+                default: Some("john".parse().unwrap()),
+                ..Default::default()
+            },
+        );
+        params.insert("age".to_string(), Param::default());
+        let task = Task {
+            params,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            editable_params(&task, &ParamContext::default(), &Delimiters::default()),
+            vec![
+                EditableParam {
+                    name: "name".to_string(),
+                    value: Some("john".to_string()),
+                    choices: vec![],
+                    read_when_run: false,
+                },
+                EditableParam {
+                    name: "age".to_string(),
+                    value: None,
+                    choices: vec![],
+                    read_when_run: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_editable_params_without_params() {
+        let task = Task::default();
+
+        assert!(
+            editable_params(&task, &ParamContext::default(), &Delimiters::default()).is_empty()
+        );
+    }
+
+    #[test]
+    fn test_editable_params_prefer_provided_values() {
+        let mut task = Task::default();
+        task.params.insert(
+            "name".to_string(),
+            Param {
+                default: Some("john".parse().unwrap()),
+                ..Default::default()
+            },
+        );
+        let mut param_context = ParamContext::default();
+        param_context.set("name", "jane");
+
+        assert_eq!(
+            editable_params(&task, &param_context, &Delimiters::default()),
+            vec![EditableParam {
+                name: "name".to_string(),
+                value: Some("jane".to_string()),
+                choices: vec![],
+                read_when_run: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_editable_param_display() {
+        let defaulted = EditableParam {
+            name: "name".to_string(),
+            value: Some("john".to_string()),
+            choices: vec![],
+            read_when_run: false,
+        };
+        let required = EditableParam {
+            name: "age".to_string(),
+            value: None,
+            choices: vec![],
+            read_when_run: false,
+        };
+
+        assert_eq!(defaulted.to_string(), "name = john");
+        assert_eq!(required.to_string(), "age = <required>");
+    }
+
+    #[test]
+    fn test_editable_params_with_choices() {
+        let mut task = Task::default();
+        task.params.insert(
+            "env".to_string(),
+            Param {
+                default: Some("dev".parse().unwrap()),
+                param_type: ParamType::Enum(vec!["dev".to_string(), "prod".to_string()]),
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            editable_params(&task, &ParamContext::default(), &Delimiters::default()),
+            vec![EditableParam {
+                name: "env".to_string(),
+                value: Some("dev".to_string()),
+                choices: vec!["dev".to_string(), "prod".to_string()],
+                read_when_run: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_editable_params_read_when_run() {
+        let mut task = Task::default();
+        task.params.insert(
+            "token".to_string(),
+            Param {
+                source: Some(ParamSource::Command("cat token".parse().unwrap())),
+                ..Default::default()
+            },
+        );
+
+        let params = editable_params(&task, &ParamContext::default(), &Delimiters::default());
+        assert_eq!(
+            params,
+            vec![EditableParam {
+                name: "token".to_string(),
+                value: None,
+                choices: vec![],
+                read_when_run: true,
+            }]
+        );
+        assert_eq!(params[0].to_string(), "token = <read when run>");
+    }
+}
@@ -0,0 +1,172 @@
+use std::{collections::HashMap, time::Duration};
+
+use taskerie_core::{
+    message::{ExecutionMessage, MessageOrigin},
+    model::TaskOutcome,
+};
+
+use crate::render::OutputRenderer;
+
+/// How much of a task run is printed.
+#[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum OutputLevel {
+    /// Only failures, along with the command that failed
+    Quiet,
+    /// Commands and their output
+    #[default]
+    Normal,
+    /// Also the parameter values and working directories
+    Verbose,
+}
+
+/// Prints the messages of a task run as text, keeping those relevant at its output level.
+pub struct MessagePrinter {
+    level: OutputLevel,
+    renderer: Box<dyn OutputRenderer>,
+    /// Banner of the last command started by each action path, printed when it fails in quiet
+    /// mode.
+    pending_banners: HashMap<Vec<usize>, String>,
+    /// Last command started by each action path.
+    running_commands: HashMap<Vec<usize>, String>,
+    /// Every command run so far, with how long it took and whether it succeeded.
+    timings: Vec<CommandTiming>,
+}
+
+#[derive(Debug)]
+struct CommandTiming {
+    command: String,
+    duration: Duration,
+    success: bool,
+}
+
+impl MessagePrinter {
+    pub fn new(level: OutputLevel, renderer: Box<dyn OutputRenderer>) -> Self {
+        MessagePrinter {
+            level,
+            renderer,
+            pending_banners: HashMap::new(),
+            running_commands: HashMap::new(),
+            timings: Vec::new(),
+        }
+    }
+
+    /// Print how long each command took and the total, unless nothing ran or in quiet mode.
+    pub fn print_timings(&self) {
+        if self.timings.is_empty() || self.level == OutputLevel::Quiet {
+            return;
+        }
+        println!("\u{23F1}  Timings");
+        for timing in &self.timings {
+            let line = format!(
+                "{:>9}  {}",
+                format_duration(timing.duration),
+                timing.command
+            );
+            if timing.success {
+                println!("{line}");
+            } else {
+                println!("{}", self.renderer.failure(format!("{line} \u{274C}")));
+            }
+        }
+        let total = self.timings.iter().map(|timing| timing.duration).sum();
+        println!("{:>9}  Total", format_duration(total));
+    }
+
+    /// Print a message unwrapped by [`ExecutionMessage::into_origin_message`], indented by the
+    /// depth of the sub-task that sent it. Parameter value requests are not printed, prompting for
+    /// them is up to the caller.
+    pub fn print(&mut self, origin: &MessageOrigin, message: ExecutionMessage) {
+        let action_path = origin.action_path.as_slice();
+        match &message {
+            ExecutionMessage::AboutToRunCommand { command, .. } => {
+                self.running_commands
+                    .insert(action_path.to_vec(), command.clone());
+            }
+            ExecutionMessage::CommandFinished {
+                duration_ms,
+                exit_code,
+            } => self.timings.push(CommandTiming {
+                command: self
+                    .running_commands
+                    .get(action_path)
+                    .cloned()
+                    .unwrap_or_default(),
+                duration: Duration::from_millis(*duration_ms),
+                success: *exit_code == Some(0),
+            }),
+            _ => {}
+        }
+
+        let Some(text) = self.renderer.render(&message) else {
+            return;
+        };
+        let line = format!(
+            "{}{}{text}",
+            "  ".repeat(origin.depth()),
+            action_prefix(action_path)
+        );
+        let shown = match message {
+            ExecutionMessage::MissingRequiredTaskParameter { .. }
+            | ExecutionMessage::InvalidParameterValue { .. }
+            | ExecutionMessage::TaskVetoed { .. }
+            | ExecutionMessage::WorkingDirectoryNotFound { .. }
+            | ExecutionMessage::CommandFailed { .. }
+            | ExecutionMessage::Interrupted => true,
+            ExecutionMessage::ParameterResolved { .. }
+            | ExecutionMessage::WorkingDirectoryResolved { .. } => {
+                self.level == OutputLevel::Verbose
+            }
+            _ => self.level != OutputLevel::Quiet,
+        };
+        if !shown {
+            if let ExecutionMessage::AboutToRunCommand { .. } = message {
+                self.pending_banners.insert(action_path.to_vec(), line);
+            }
+            return;
+        }
+
+        if let ExecutionMessage::CommandFailed { .. } = message
+            && let Some(banner) = self.pending_banners.remove(action_path)
+        {
+            println!("{banner}");
+        }
+        if let ExecutionMessage::CommandError { .. } = message {
+            eprintln!("{line}");
+        } else {
+            println!("{line}");
+        }
+    }
+
+    /// Print whether the task succeeded once it is done running.
+    pub fn print_outcome(&self, task_name: &str, outcome: &TaskOutcome) {
+        if !outcome.success() || self.level != OutputLevel::Quiet {
+            println!("{}", self.renderer.render_outcome(task_name, outcome));
+        }
+    }
+
+    /// Print why the task could not run, along with the errors that caused it.
+    pub fn print_error(&self, task_name: &str, error: &anyhow::Error) {
+        eprintln!(
+            "{}",
+            self.renderer.failure(format!(
+                "\u{274C} Error executing task {task_name}: {error:#}"
+            ))
+        );
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}s", duration.as_secs_f64())
+}
+
+/// Messages of parallel actions are prefixed by the ids of these actions.
+fn action_prefix(action_path: &[usize]) -> String {
+    if action_path.is_empty() {
+        return String::new();
+    }
+    let action_path = action_path
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    format!("[{}] ", action_path.join("."))
+}
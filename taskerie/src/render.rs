@@ -0,0 +1,213 @@
+use owo_colors::OwoColorize;
+use taskerie_core::{ExitStatus, message::ExecutionMessage, model::TaskOutcome};
+
+/// Turns execution messages into the text printed for them. Implementors only choose how
+/// successes, failures and command banners are styled, the text itself is shared.
+pub trait OutputRenderer {
+    fn success(&self, text: String) -> String;
+    fn failure(&self, text: String) -> String;
+    fn banner(&self, text: String) -> String;
+
+    /// Text of a message unwrapped by [`ExecutionMessage::into_action_message`], `None` for
+    /// messages that are not meant to be printed.
+    fn render(&self, message: &ExecutionMessage) -> Option<String> {
+        Some(match message {
+            ExecutionMessage::MissingRequiredTaskParameter { parameter_name } => format!(
+                "Parameter '{parameter_name}' is undefined and has no default value provided"
+            ),
+            ExecutionMessage::InvalidParameterValue {
+                parameter_name,
+                reason,
+            } => self.failure(format!(
+                "\u{274C} Invalid value for parameter '{parameter_name}': {reason}"
+            )),
+            ExecutionMessage::ParameterResolved {
+                parameter_name,
+                value,
+            } => format!("\u{2022} {parameter_name} = {value}"),
+            ExecutionMessage::TaskVetoed { task_name, reason } => {
+                self.failure(format!("\u{274C} Task '{task_name}' was vetoed: {reason}"))
+            }
+            ExecutionMessage::TaskStarted { name } => self.banner(format!("\u{25B6} Task {name}")),
+            ExecutionMessage::TaskFinished {
+                name,
+                success: true,
+            } => self.success(format!("\u{25A0} Task {name} done")),
+            ExecutionMessage::TaskFinished {
+                name,
+                success: false,
+            } => self.failure(format!("\u{25A0} Task {name} failed")),
+            ExecutionMessage::RunningDependency { name } => {
+                format!("\u{21B3} Running dependency {name}")
+            }
+            ExecutionMessage::CacheHit { task_name } => {
+                self.success(format!("\u{21B3} Task {task_name} is up to date, skipped"))
+            }
+            ExecutionMessage::DependencyAlreadySatisfied { name } => {
+                format!("\u{21B3} Dependency {name} already satisfied")
+            }
+            ExecutionMessage::WorkingDirectoryResolved {
+                working_directory,
+                path,
+            } => format!("\u{2022} Working directory {working_directory} is {path}"),
+            ExecutionMessage::WorkingDirectoryNotFound { path } => self.failure(format!(
+                "\u{274C} Requested working directory \"{path}\" not found"
+            )),
+            ExecutionMessage::AboutToRunCommand {
+                label: Some(label), ..
+            } => self.banner(format!("\u{2192} {label}")),
+            ExecutionMessage::AboutToRunCommand {
+                command,
+                working_directory,
+                label: None,
+            } => self.banner(format!("\u{231C} {working_directory}> {command}")),
+            ExecutionMessage::CommandOutput { output }
+            | ExecutionMessage::CommandError { output } => {
+                format!("\u{23B8}{output}")
+            }
+            ExecutionMessage::OutputTruncated { limit } => {
+                format!("\u{26A0}  Output truncated after {limit} bytes")
+            }
+            ExecutionMessage::CommandFailed { .. } => self.failure("\u{231E}\u{274C}".to_string()),
+            ExecutionMessage::RetryingCommand { attempt, max } => {
+                self.failure(format!("\u{231E}\u{274C} Retrying ({attempt}/{max})"))
+            }
+            ExecutionMessage::CommandSucceeded => self.success("\u{231E}\u{2705}".to_string()),
+            ExecutionMessage::Interrupted => self.failure("\u{26D4} Interrupted".to_string()),
+            ExecutionMessage::FailureIgnored => "\u{26A0}  Failure ignored, continuing".to_string(),
+            ExecutionMessage::ParameterValueRequired { .. }
+            | ExecutionMessage::ConfirmationRequired { .. }
+            | ExecutionMessage::CommandFinished { .. } => return None,
+            ExecutionMessage::ParallelAction { .. } | ExecutionMessage::SubTask { .. } => {
+                unreachable!("Messages of parallel actions are unwrapped")
+            }
+        })
+    }
+
+    /// Text printed once a task is done running, telling apart the tasks that could not start
+    /// from the ones whose commands failed.
+    fn render_outcome(&self, task_name: &str, outcome: &TaskOutcome) -> String {
+        match outcome {
+            outcome if outcome.success() => {
+                self.success(format!("\u{2705} Task {task_name} succeeded"))
+            }
+            TaskOutcome::Completed(ExitStatus::Exited(code)) => self.failure(format!(
+                "\u{274C} Task {task_name} failed with exit code {code}"
+            )),
+            TaskOutcome::Completed(_) => self.failure(format!("\u{274C} Task {task_name} failed")),
+            TaskOutcome::PreconditionFailed(reason) => self.failure(format!(
+                "\u{26A0}  Task {task_name} could not start: {reason}"
+            )),
+            TaskOutcome::Interrupted => {
+                self.failure(format!("\u{26D4} Task {task_name} was interrupted"))
+            }
+        }
+    }
+}
+
+/// Renderer for terminals, coloring successes in green, failures in red and dimming command
+/// banners.
+pub struct TerminalRenderer;
+
+impl OutputRenderer for TerminalRenderer {
+    fn success(&self, text: String) -> String {
+        text.green().to_string()
+    }
+
+    fn failure(&self, text: String) -> String {
+        text.red().to_string()
+    }
+
+    fn banner(&self, text: String) -> String {
+        text.dimmed().to_string()
+    }
+}
+
+/// Renderer without any styling, for `--no-color`, `NO_COLOR` and output that is not a terminal.
+pub struct PlainRenderer;
+
+impl OutputRenderer for PlainRenderer {
+    fn success(&self, text: String) -> String {
+        text
+    }
+
+    fn failure(&self, text: String) -> String {
+        text
+    }
+
+    fn banner(&self, text: String) -> String {
+        text
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_terminal_renderer_keeps_plain_text() {
+        let messages = [
+            ExecutionMessage::AboutToRunCommand {
+                command: "cargo build".to_string(),
+                working_directory: "/project".to_string(),
+                label: None,
+            },
+            ExecutionMessage::AboutToRunCommand {
+                command: "cargo build".to_string(),
+                working_directory: "/project".to_string(),
+                label: Some("Compile".to_string()),
+            },
+            ExecutionMessage::CommandOutput {
+                output: "Compiling".to_string(),
+            },
+            ExecutionMessage::CommandSucceeded,
+            ExecutionMessage::CommandFailed { exit_code: Some(1) },
+        ];
+        let expected = [
+            "\u{231C} /project> cargo build",
+            "\u{2192} Compile",
+            "\u{23B8}Compiling",
+            "\u{231E}\u{2705}",
+            "\u{231E}\u{274C}",
+        ];
+
+        for (message, expected) in messages.iter().zip(expected) {
+            assert_eq!(PlainRenderer.render(message).unwrap(), expected);
+            let colored = TerminalRenderer.render(message).unwrap();
+            assert!(colored.contains(expected));
+        }
+        assert_eq!(
+            TerminalRenderer
+                .render(&ExecutionMessage::CommandSucceeded)
+                .unwrap(),
+            "\u{1b}[32m\u{231E}\u{2705}\u{1b}[39m"
+        );
+        assert_eq!(
+            PlainRenderer.render(&ExecutionMessage::CommandFinished {
+                duration_ms: 10,
+                exit_code: Some(0),
+            }),
+            None
+        );
+    }
+
+    #[test]
+    fn test_render_outcome() {
+        assert_eq!(
+            PlainRenderer.render_outcome("build", &TaskOutcome::Completed(ExitStatus::Exited(2))),
+            "\u{274C} Task build failed with exit code 2"
+        );
+        assert_eq!(
+            TerminalRenderer
+                .render_outcome("build", &TaskOutcome::Completed(ExitStatus::Exited(0))),
+            "\u{1b}[32m\u{2705} Task build succeeded\u{1b}[39m"
+        );
+        assert_eq!(
+            PlainRenderer.render_outcome(
+                "build",
+                &TaskOutcome::PreconditionFailed("Working directory 'dist' not found".to_string())
+            ),
+            "\u{26A0}  Task build could not start: Working directory 'dist' not found"
+        );
+    }
+}
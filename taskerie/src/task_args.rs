@@ -0,0 +1,228 @@
+use anyhow::{anyhow, bail};
+use taskerie_core::model::{
+    ParamContext, Task,
+    task::{Param, ParamType},
+};
+
+/// Parse `--key value` and `--key=value` command line arguments into task parameters.
+///
+/// Boolean parameters are flags: `--key` alone sets them to `true` and `--no-key` to `false`.
+///
+/// Bare values are positional: they are given in order to the declared parameters that are not
+/// named.
+pub fn parse_task_args(
+    task_name: &str,
+    task: &Task,
+    args: &[String],
+) -> anyhow::Result<ParamContext> {
+    let mut param_context = ParamContext::default();
+    let mut positional_values = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        let Some(param) = arg.strip_prefix("--") else {
+            positional_values.push(arg.as_str());
+            continue;
+        };
+        let is_flag = |name: &str| {
+            task.params
+                .get(name)
+                .is_some_and(|param| matches!(param.param_type, ParamType::Bool))
+        };
+        let (name, value) = if let Some((name, value)) = param.split_once('=') {
+            (name, value)
+        } else if is_flag(param) {
+            (param, "true")
+        } else if let Some(name) = param.strip_prefix("no-")
+            && is_flag(name)
+        {
+            (name, "false")
+        } else {
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("Expected a value for parameter '{param}'"))?;
+            (param, value.as_str())
+        };
+
+        let Some(param) = task.params.get(name) else {
+            bail!("Task '{task_name}' has no parameter '{name}'");
+        };
+        set_param(&mut param_context, name, param, value)?;
+    }
+
+    let mut free_params = task
+        .params
+        .iter()
+        .filter(|(name, _)| !param_context.has(name))
+        .collect::<Vec<_>>()
+        .into_iter();
+    for value in positional_values {
+        let Some((name, param)) = free_params.next() else {
+            bail!("Unexpected argument '{value}', every parameter of task '{task_name}' is given");
+        };
+        set_param(&mut param_context, name, param, value)?;
+    }
+
+    Ok(param_context)
+}
+
+fn set_param(
+    param_context: &mut ParamContext,
+    name: &str,
+    param: &Param,
+    value: &str,
+) -> anyhow::Result<()> {
+    param
+        .param_type
+        .validate(value)
+        .map_err(|reason| anyhow!("Invalid value for parameter '{name}': {reason}"))?;
+    param_context.set(name, value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn task() -> Task {
+        let mut task = Task::default();
+        task.params.insert(
+            "env".to_string(),
+            Param {
+                param_type: ParamType::Enum(vec!["dev".to_string(), "prod".to_string()]),
+                ..Default::default()
+            },
+        );
+        task.params.insert(
+            "tag".to_string(),
+            Param {
+                default: Some("latest".parse().unwrap()),
+                ..Default::default()
+            },
+        );
+        task
+    }
+
+    fn task_with_flag() -> Task {
+        let mut task = task();
+        task.params.insert(
+            "force".to_string(),
+            Param {
+                default: Some("false".parse().unwrap()),
+                param_type: ParamType::Bool,
+                ..Default::default()
+            },
+        );
+        task
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn test_separate_values() {
+        let params = parse_task_args(
+            "deploy",
+            &task(),
+            &args(&["--env", "prod", "--tag", "v1.2"]),
+        )
+        .unwrap();
+        assert_eq!(params.get("env").unwrap(), "prod");
+        assert_eq!(params.get("tag").unwrap(), "v1.2");
+    }
+
+    #[test]
+    fn test_inline_values() {
+        let params =
+            parse_task_args("deploy", &task(), &args(&["--env=prod", "--tag=a=b"])).unwrap();
+        assert_eq!(params.get("env").unwrap(), "prod");
+        assert_eq!(params.get("tag").unwrap(), "a=b");
+    }
+
+    #[test]
+    fn test_no_args() {
+        let params = parse_task_args("deploy", &task(), &[]).unwrap();
+        assert!(!params.has("env"));
+        assert!(!params.has("tag"));
+    }
+
+    #[test]
+    fn test_unknown_param() {
+        let error = parse_task_args("deploy", &task(), &args(&["--version", "1"])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Task 'deploy' has no parameter 'version'"
+        );
+    }
+
+    #[test]
+    fn test_missing_value() {
+        let error = parse_task_args("deploy", &task(), &args(&["--env"])).unwrap_err();
+        assert_eq!(error.to_string(), "Expected a value for parameter 'env'");
+    }
+
+    #[test]
+    fn test_positional_values() {
+        let params = parse_task_args("deploy", &task(), &args(&["prod", "v1.2"])).unwrap();
+        assert_eq!(params.get("env").unwrap(), "prod");
+        assert_eq!(params.get("tag").unwrap(), "v1.2");
+    }
+
+    #[test]
+    fn test_mixed_positional_and_named_values() {
+        let params = parse_task_args("deploy", &task(), &args(&["v1.2", "--env", "dev"])).unwrap();
+        assert_eq!(params.get("env").unwrap(), "dev");
+        assert_eq!(params.get("tag").unwrap(), "v1.2");
+    }
+
+    #[test]
+    fn test_too_many_positional_values() {
+        let error =
+            parse_task_args("deploy", &task(), &args(&["prod", "v1.2", "extra"])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unexpected argument 'extra', every parameter of task 'deploy' is given"
+        );
+    }
+
+    #[test]
+    fn test_invalid_positional_value() {
+        let error = parse_task_args("deploy", &task(), &args(&["staging"])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid value for parameter 'env': 'staging' is not one of dev, prod"
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_choice() {
+        let error = parse_task_args("deploy", &task(), &args(&["--env", "staging"])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid value for parameter 'env': 'staging' is not one of dev, prod"
+        );
+    }
+
+    #[test]
+    fn test_bare_boolean_flag() {
+        let task = task_with_flag();
+        let params = parse_task_args("deploy", &task, &args(&["--force", "prod"])).unwrap();
+        assert_eq!(params.get("force").unwrap(), "true");
+        assert_eq!(params.get("env").unwrap(), "prod");
+
+        let params = parse_task_args("deploy", &task, &args(&["--no-force"])).unwrap();
+        assert_eq!(params.get("force").unwrap(), "false");
+
+        let params = parse_task_args("deploy", &task, &args(&["--force=false"])).unwrap();
+        assert_eq!(params.get("force").unwrap(), "false");
+    }
+
+    #[test]
+    fn test_value_flag_takes_next_argument() {
+        let params =
+            parse_task_args("deploy", &task_with_flag(), &args(&["--tag", "--force"])).unwrap();
+        assert_eq!(params.get("tag").unwrap(), "--force");
+        assert!(!params.has("force"));
+    }
+}
@@ -0,0 +1,176 @@
+use taskerie_core::model::TaskInfo;
+
+/// Format tasks as a table with aligned name, parameters and description columns.
+///
+/// Required parameters are shown as `<name>` and optional ones as `[name=default]`, or `[name]`
+/// when their value is read from a command or a file.
+#[must_use]
+pub fn format_task_list(tasks: &[TaskInfo]) -> String {
+    let rows = tasks
+        .iter()
+        .map(|task| {
+            let params = task
+                .params
+                .iter()
+                .map(|param| match &param.default {
+                    Some(default) => format!("[{}={default}]", param.name),
+                    None if param.required => format!("<{}>", param.name),
+                    None => format!("[{}]", param.name),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            [
+                task.name.clone(),
+                params,
+                task.description.clone().unwrap_or_default(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let header = [
+        "TASK".to_string(),
+        "PARAMS".to_string(),
+        "DESCRIPTION".to_string(),
+    ];
+    let name_width = column_width(std::iter::once(&header).chain(&rows), 0);
+    let params_width = column_width(std::iter::once(&header).chain(&rows), 1);
+
+    std::iter::once(&header)
+        .chain(&rows)
+        .map(|[name, params, description]| {
+            format!("{name:name_width$}  {params:params_width$}  {description}")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Format the parameters of a task, one per line with aligned name, type and default columns,
+/// the ones without default being marked `(required)`, or `(read when run)` when their value
+/// comes from a command or a file.
+#[must_use]
+pub fn format_task_help(task: &TaskInfo) -> String {
+    let title = match &task.description {
+        Some(description) => format!("{}: {description}", task.name),
+        None => task.name.clone(),
+    };
+    if task.params.is_empty() {
+        return format!("{title}\n  No parameters");
+    }
+    let rows = task
+        .params
+        .iter()
+        .map(|param| {
+            [
+                param.name.clone(),
+                param.param_type.to_string(),
+                match &param.default {
+                    Some(default) => default.clone(),
+                    None if param.required => "(required)".to_string(),
+                    None => "(read when run)".to_string(),
+                },
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    let name_width = column_width(&rows, 0);
+    let type_width = column_width(&rows, 1);
+    std::iter::once(title)
+        .chain(rows.iter().map(|[name, param_type, default]| {
+            format!("  {name:name_width$}  {param_type:type_width$}  {default}")
+        }))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn column_width<'a>(rows: impl IntoIterator<Item = &'a [String; 3]>, column: usize) -> usize {
+    rows.into_iter()
+        .map(|row| row[column].chars().count())
+        .max()
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use taskerie_core::model::{ParamInfo, task::ParamType};
+
+    use super::*;
+
+    #[test]
+    fn test_format_task_list() {
+        let tasks = vec![
+            TaskInfo {
+                name: "build".to_string(),
+                description: Some("Build the project".to_string()),
+                standalone: true,
+                params: Vec::new(),
+                tags: Vec::new(),
+            },
+            TaskInfo {
+                name: "deploy".to_string(),
+                description: None,
+                standalone: false,
+                params: vec![
+                    ParamInfo {
+                        name: "env".to_string(),
+                        default: None,
+                        required: true,
+                        param_type: ParamType::String,
+                    },
+                    ParamInfo {
+                        name: "region".to_string(),
+                        default: Some("eu".to_string()),
+                        required: false,
+                        param_type: ParamType::String,
+                    },
+                ],
+                tags: Vec::new(),
+            },
+        ];
+
+        assert_eq!(
+            format_task_list(&tasks),
+            "TASK    PARAMS             DESCRIPTION\n\
+             build                      Build the project\n\
+             deploy  <env> [region=eu]"
+        );
+    }
+
+    #[test]
+    fn test_format_task_help() {
+        let param = |name: &str, param_type, default: Option<&str>| ParamInfo {
+            name: name.to_string(),
+            default: default.map(ToString::to_string),
+            required: default.is_none(),
+            param_type,
+        };
+        let mut task = TaskInfo {
+            name: "deploy".to_string(),
+            description: Some("Deploy the app".to_string()),
+            standalone: false,
+            params: vec![
+                param(
+                    "environment",
+                    ParamType::Enum(vec!["dev".to_string(), "prod".to_string()]),
+                    None,
+                ),
+                param("jobs", ParamType::Int, Some("4")),
+                param("tag", ParamType::String, Some("{{ version }}")),
+            ],
+            tags: Vec::new(),
+        };
+
+        assert_eq!(
+            format_task_help(&task),
+            "deploy: Deploy the app\n  \
+             environment  dev|prod  (required)\n  \
+             jobs         int       4\n  \
+             tag          string    {{ version }}"
+        );
+
+        task.description = None;
+        task.params.clear();
+        assert_eq!(format_task_help(&task), "deploy\n  No parameters");
+    }
+}
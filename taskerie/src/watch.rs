@@ -0,0 +1,122 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, mpsc},
+    time::Duration,
+};
+
+use globset::{Glob, GlobMatcher};
+use notify::{Event, RecursiveMode, Watcher};
+use taskerie_core::model::TaskerieContext;
+
+use crate::{Cli, run_from_args};
+
+/// Time without file events after which changes are handled, so that saving several files at once
+/// reruns the task only once.
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(200);
+
+/// Run a task, then run it again every time a file of the current directory matching `pattern`
+/// changes. The config is reloaded when it changes.
+///
+/// Changes made while the task runs are queued and trigger a single run once it is done.
+pub fn watch(
+    config_path: &Path,
+    mut taskerie: Arc<TaskerieContext>,
+    task_name: &str,
+    pattern: &str,
+    cli: &Cli,
+) -> anyhow::Result<()> {
+    let matcher = Glob::new(pattern)?.compile_matcher();
+    let config_path = config_path.canonicalize()?;
+    let current_dir = std::env::current_dir()?;
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(&current_dir, RecursiveMode::Recursive)?;
+    if !config_path.starts_with(&current_dir)
+        && let Some(config_dir) = config_path.parent()
+    {
+        watcher.watch(config_dir, RecursiveMode::NonRecursive)?;
+    }
+
+    loop {
+        run_from_args(&taskerie, task_name, cli)?;
+        println!("\u{1F440} Watching {pattern} for changes");
+
+        loop {
+            let changed_paths = wait_for_changes(&rx)?;
+            if changed_paths.contains(&config_path) {
+                match taskerie_core::load(&config_path) {
+                    Ok(reloaded) => {
+                        taskerie = Arc::new(reloaded);
+                        println!("Sucessfully reloaded");
+                    }
+                    Err(e) => {
+                        eprintln!("\u{274C} Could not reload {}: {e:#}", config_path.display());
+                    }
+                }
+                break;
+            }
+            if changed_paths
+                .iter()
+                .any(|path| is_watched(&matcher, &current_dir, path))
+            {
+                break;
+            }
+        }
+    }
+}
+
+/// Block until some files change, then gather every path changed until no event comes for
+/// [`DEBOUNCE_DELAY`].
+fn wait_for_changes(rx: &mpsc::Receiver<notify::Result<Event>>) -> anyhow::Result<Vec<PathBuf>> {
+    let mut changed_paths = Vec::new();
+    let mut event = rx.recv()?;
+    loop {
+        let Event { kind, paths, .. } = event?;
+        if kind.is_create() || kind.is_modify() || kind.is_remove() {
+            changed_paths.extend(paths);
+        }
+        match rx.recv_timeout(DEBOUNCE_DELAY) {
+            Ok(next_event) => event = next_event,
+            Err(mpsc::RecvTimeoutError::Timeout) if !changed_paths.is_empty() => {
+                return Ok(changed_paths);
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => event = rx.recv()?,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                anyhow::bail!("Stopped receiving file changes")
+            }
+        }
+    }
+}
+
+/// Check whether a changed path matches the watched pattern, relative to `current_dir`.
+fn is_watched(matcher: &GlobMatcher, current_dir: &Path, path: &Path) -> bool {
+    matcher.is_match(path.strip_prefix(current_dir).unwrap_or(path))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_watched() {
+        let matcher = Glob::new("src/**/*.rs").unwrap().compile_matcher();
+        let current_dir = Path::new("/project");
+
+        assert!(is_watched(
+            &matcher,
+            current_dir,
+            Path::new("/project/src/service/mod.rs")
+        ));
+        assert!(!is_watched(
+            &matcher,
+            current_dir,
+            Path::new("/project/README.md")
+        ));
+        assert!(!is_watched(
+            &matcher,
+            current_dir,
+            Path::new("/other/src/main.rs")
+        ));
+    }
+}